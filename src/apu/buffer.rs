@@ -0,0 +1,62 @@
+//NOTE: Fixed size ring buffer for the stereo frames of the apu. Once it is full the oldest frames
+//      get overwritten, a frontend that doesn't keep up loses audio instead of stalling the
+//      emulation.
+pub struct SampleBuffer {
+    frames: Vec<[f32; 2]>,
+    start: usize,
+    len: usize,
+}
+
+impl SampleBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            frames: vec![[0.0; 2]; capacity],
+            start: 0,
+            len: 0,
+        }
+    }
+
+    pub fn push(&mut self, frame: [f32; 2]) {
+        let capacity = self.frames.len();
+        let end = (self.start + self.len) % capacity;
+        self.frames[end] = frame;
+
+        if self.len == capacity {
+            self.start = (self.start + 1) % capacity;
+        } else {
+            self.len += 1;
+        }
+    }
+
+    //NOTE: Hands out every buffered frame, oldest first, and empties the buffer
+    pub fn drain(&mut self) -> Vec<[f32; 2]> {
+        let capacity = self.frames.len();
+        let frames = (0..self.len)
+            .map(|offset| self.frames[(self.start + offset) % capacity])
+            .collect();
+
+        self.start = 0;
+        self.len = 0;
+
+        frames
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_overwrites_the_oldest_frames() {
+        let mut buffer = SampleBuffer::new(4);
+        for sample in 0..6 {
+            buffer.push([sample as f32, -(sample as f32)]);
+        }
+
+        assert_eq!(
+            buffer.drain(),
+            vec![[2.0, -2.0], [3.0, -3.0], [4.0, -4.0], [5.0, -5.0]]
+        );
+        assert!(buffer.drain().is_empty());
+    }
+}
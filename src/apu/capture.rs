@@ -0,0 +1,93 @@
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+
+use super::filter::HighPass;
+use super::wav::write_wav;
+use super::SAMPLE_RATE;
+
+const CHANNEL_NAMES: [&str; 4] = ["square1", "square2", "wave", "noise"];
+
+//NOTE: Keeps every frame the apu produces while a recording runs, unlike the ring buffer nothing
+//      gets dropped. The channel tracks are panned and scaled by the master volume the same way
+//      the mix is, so they add up to it.
+pub struct AudioCapture {
+    mixed: Vec<[f32; 2]>,
+    //NOTE: Only there if the recording asked for a separate track per channel
+    tracks: Option<[Vec<[f32; 2]>; 4]>,
+    filters: [HighPass; 4],
+}
+
+impl AudioCapture {
+    pub fn new(split_channels: bool) -> Self {
+        Self {
+            mixed: Vec::new(),
+            tracks: split_channels.then(Default::default),
+            filters: [HighPass::default(); 4],
+        }
+    }
+
+    pub fn push(&mut self, mixed: [f32; 2], channels: [[f32; 2]; 4]) {
+        self.mixed.push(mixed);
+
+        if let Some(tracks) = &mut self.tracks {
+            for (track, (filter, frame)) in
+                tracks.iter_mut().zip(self.filters.iter_mut().zip(channels))
+            {
+                track.push(filter.apply(frame));
+            }
+        }
+    }
+
+    //NOTE: The mix ends up in `path`, the channel tracks next to it as `<name>_<channel>.wav`.
+    //      Returns every file that got written.
+    pub fn save(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        let mut files = vec![(path.to_path_buf(), &self.mixed)];
+
+        if let Some(tracks) = &self.tracks {
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+
+            for (name, track) in CHANNEL_NAMES.iter().zip(tracks) {
+                let track_path = path.with_file_name(format!("{}_{}.wav", stem, name));
+                files.push((track_path, track));
+            }
+        }
+
+        for (path, frames) in &files {
+            let mut writer = BufWriter::new(File::create(path)?);
+            write_wav(&mut writer, SAMPLE_RATE, frames)?;
+        }
+
+        Ok(files.into_iter().map(|(path, _)| path).collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::temp_path;
+
+    #[test]
+    fn test_saves_a_track_per_channel() {
+        let mut capture = AudioCapture::new(true);
+        for _ in 0..10 {
+            capture.push([0.5, 0.5], [[0.5, 0.0], [0.0, 0.5], [0.0; 2], [0.0; 2]]);
+        }
+
+        let path = temp_path("capture.wav");
+        let files = capture.save(&path).unwrap();
+
+        assert_eq!(files.len(), 5);
+        assert_eq!(files[0], path);
+        assert_eq!(files[3], temp_path("capture_wave.wav"));
+
+        for file in &files {
+            assert_eq!(std::fs::metadata(file).unwrap().len(), 44 + 10 * 4);
+            std::fs::remove_file(file).unwrap();
+        }
+
+        let capture = AudioCapture::new(false);
+        assert_eq!(capture.save(&path).unwrap(), vec![path.clone()]);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,90 @@
+//NOTE: Moves the volume of a channel up or down by one step every `period` clocks of the frame
+//      sequencer (64 Hz). The upper five bits of the register double as the dac enable.
+#[derive(Default)]
+pub struct Envelope {
+    initial_volume: u8,
+    increase: bool,
+    period: u8,
+
+    volume: u8,
+    timer: u8,
+}
+
+impl Envelope {
+    pub fn write(&mut self, value: u8) {
+        self.initial_volume = value >> 4;
+        self.increase = value & 0x08 != 0;
+        self.period = value & 0x07;
+    }
+
+    pub fn is_dac_enabled(value: u8) -> bool {
+        value & 0xF8 != 0
+    }
+
+    pub fn volume(&self) -> u8 {
+        self.volume
+    }
+
+    pub fn trigger(&mut self) {
+        self.volume = self.initial_volume;
+        self.timer = self.reload();
+    }
+
+    pub fn clock(&mut self) {
+        if self.period == 0 {
+            return;
+        }
+
+        self.timer = self.timer.saturating_sub(1);
+        if self.timer > 0 {
+            return;
+        }
+
+        self.timer = self.reload();
+
+        if self.increase && self.volume < 0x0F {
+            self.volume += 1;
+        } else if !self.increase && self.volume > 0x00 {
+            self.volume -= 1;
+        }
+    }
+
+    //NOTE: A period of zero reloads the timer with eight
+    fn reload(&self) -> u8 {
+        if self.period == 0 {
+            8
+        } else {
+            self.period
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_volume_steps() {
+        let mut envelope = Envelope::default();
+        envelope.write(0x22);
+        envelope.trigger();
+        assert_eq!(envelope.volume(), 2);
+
+        envelope.clock();
+        assert_eq!(envelope.volume(), 2);
+        envelope.clock();
+        assert_eq!(envelope.volume(), 1);
+
+        for _ in 0..10 {
+            envelope.clock();
+        }
+        assert_eq!(envelope.volume(), 0);
+
+        envelope.write(0xE9);
+        envelope.trigger();
+        for _ in 0..4 {
+            envelope.clock();
+        }
+        assert_eq!(envelope.volume(), 0x0F);
+    }
+}
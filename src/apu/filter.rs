@@ -0,0 +1,40 @@
+//NOTE: Charge factor of the capacitor per output sample, the real one sits on the analog output and
+//      removes the dc offset the dacs leave behind
+const HIGH_PASS_FACTOR: f32 = 0.996;
+
+#[derive(Copy, Clone, Default)]
+pub struct HighPass {
+    capacitors: [f32; 2],
+}
+
+impl HighPass {
+    pub fn apply(&mut self, frame: [f32; 2]) -> [f32; 2] {
+        let mut output = [0.0; 2];
+
+        for side in 0..2 {
+            output[side] = frame[side] - self.capacitors[side];
+            self.capacitors[side] = frame[side] - output[side] * HIGH_PASS_FACTOR;
+        }
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_removes_the_dc_offset() {
+        let mut filter = HighPass::default();
+        let first = filter.apply([1.0, -1.0]);
+        assert_eq!(first, [1.0, -1.0]);
+
+        let mut last = first;
+        for _ in 0..4800 {
+            last = filter.apply([1.0, -1.0]);
+        }
+
+        assert!(last[0].abs() < 0.01 && last[1].abs() < 0.01);
+    }
+}
@@ -0,0 +1,80 @@
+//NOTE: Turns a channel off once it played for the time written into its length register. The
+//      counter gets clocked by the frame sequencer at 256 Hz while it is enabled.
+pub struct LengthCounter {
+    max: u16,
+    counter: u16,
+    enabled: bool,
+}
+
+impl LengthCounter {
+    pub fn new(max: u16) -> Self {
+        Self {
+            max,
+            counter: 0,
+            enabled: false,
+        }
+    }
+
+    pub fn load(&mut self, value: u8) {
+        self.counter = self.max - value as u16;
+    }
+
+    //NOTE: Returns true if the counter ran out and the channel has to be turned off
+    pub fn clock(&mut self) -> bool {
+        if !self.enabled || self.counter == 0 {
+            return false;
+        }
+
+        self.counter -= 1;
+        self.counter == 0
+    }
+
+    //NOTE: Enabling the counter in the half of the frame sequencer period that doesn't clock it
+    //      clocks it once right away.
+    pub fn set_enabled(&mut self, enabled: bool, extra_clock: bool) -> bool {
+        let was_enabled = self.enabled;
+        self.enabled = enabled;
+
+        !was_enabled && enabled && extra_clock && self.clock()
+    }
+
+    //NOTE: A trigger with an empty counter starts over from the maximum length, the same extra
+    //      clock applies to it.
+    pub fn trigger(&mut self, extra_clock: bool) {
+        if self.counter == 0 {
+            self.counter = self.max;
+
+            if self.enabled && extra_clock {
+                self.counter -= 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_length_runs_out() {
+        let mut length = LengthCounter::new(64);
+        length.load(62);
+        assert!(!length.clock());
+
+        length.set_enabled(true, false);
+        assert!(!length.clock());
+        assert!(length.clock());
+        assert!(!length.clock());
+    }
+
+    #[test]
+    fn test_extra_clock_when_enabled() {
+        let mut length = LengthCounter::new(64);
+        length.load(63);
+        assert!(length.set_enabled(true, true));
+
+        //NOTE: The trigger reloads the empty counter, minus the extra clock
+        length.trigger(true);
+        assert_eq!(length.counter, 63);
+    }
+}
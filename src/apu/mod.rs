@@ -0,0 +1,373 @@
+use std::ops::RangeInclusive;
+
+use self::buffer::SampleBuffer;
+use self::capture::AudioCapture;
+use self::filter::HighPass;
+use self::noise::NoiseChannel;
+use self::square::SquareChannel;
+use self::wave::WaveChannel;
+use crate::memory::bus::IoDevice;
+
+mod buffer;
+pub mod capture;
+mod envelope;
+mod filter;
+mod length;
+mod noise;
+mod square;
+mod wav;
+mod wave;
+
+pub const NR10_REGISTER: u16 = 0xFF10;
+pub const NR50_REGISTER: u16 = 0xFF24;
+pub const NR51_REGISTER: u16 = 0xFF25;
+pub const NR52_REGISTER: u16 = 0xFF26;
+pub const WAVE_RAM_START: u16 = 0xFF30;
+pub const WAVE_RAM_END: u16 = 0xFF3F;
+
+pub const SAMPLE_RATE: u32 = 48000;
+const CPU_CLOCK: u32 = 4194304;
+//NOTE: One second of audio, enough to bridge a frontend that stops draining for a while
+const BUFFER_FRAMES: usize = SAMPLE_RATE as usize;
+
+//NOTE: Bits that always read as one, write only bits and unused registers read back as 0xFF. The
+//      table covers 0xFF10 - 0xFF2F, NR52 gets built from the state of the apu instead.
+const READ_MASKS: [u8; 0x20] = [
+    0x80, 0x3F, 0x00, 0xFF, 0xBF, // NR10 - NR14
+    0xFF, 0x3F, 0x00, 0xFF, 0xBF, // NR20 - NR24
+    0x7F, 0xFF, 0x9F, 0xFF, 0xBF, // NR30 - NR34
+    0xFF, 0xFF, 0x00, 0x00, 0xBF, // NR40 - NR44
+    0x00, 0x00, 0x70, 0xFF, 0xFF, // NR50 - NR52
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+];
+
+pub struct Apu {
+    registers: [u8; 0x20],
+    powered: bool,
+
+    square1: SquareChannel,
+    square2: SquareChannel,
+    wave: WaveChannel,
+    noise: NoiseChannel,
+
+    //NOTE: The next step of the frame sequencer, it runs at 512 Hz off the div register
+    frame_step: u8,
+    sample_counter: u32,
+    high_pass: HighPass,
+    samples: SampleBuffer,
+    capture: Option<AudioCapture>,
+}
+
+impl Default for Apu {
+    fn default() -> Self {
+        Self {
+            registers: [0x00; 0x20],
+            powered: false,
+
+            square1: SquareChannel::new(true),
+            square2: SquareChannel::new(false),
+            wave: WaveChannel::default(),
+            noise: NoiseChannel::default(),
+
+            frame_step: 0,
+            sample_counter: 0,
+            high_pass: HighPass::default(),
+            samples: SampleBuffer::new(BUFFER_FRAMES),
+            capture: None,
+        }
+    }
+}
+
+impl Apu {
+    //NOTE: While the apu is off every register but NR52 ignores writes. The dmg still lets the
+    //      length counters be loaded, the cgb doesn't but we stick to the dmg behaviour for now.
+    fn write_length_while_off(&mut self, addr: u16, value: u8) {
+        match addr {
+            0xFF11 => self.square1.write_length(value),
+            0xFF16 => self.square2.write_length(value),
+            0xFF1B => self.wave.write_length(value),
+            0xFF20 => self.noise.write_length(value),
+            _ => (),
+        }
+    }
+
+    //NOTE: Turning the apu off clears every register, the wave ram and the length counters stay
+    fn write_power(&mut self, powered: bool) {
+        if self.powered && !powered {
+            self.registers = [0x00; 0x20];
+            self.square1.power_off();
+            self.square2.power_off();
+            self.wave.power_off();
+            self.noise.power_off();
+        }
+
+        if !self.powered && powered {
+            self.frame_step = 0;
+        }
+
+        self.powered = powered;
+    }
+
+    //NOTE: Gets called on every falling edge of the div bit that drives the frame sequencer
+    pub fn clock_frame_sequencer(&mut self) {
+        if !self.powered {
+            return;
+        }
+
+        if self.frame_step.is_multiple_of(2) {
+            self.square1.clock_length();
+            self.square2.clock_length();
+            self.wave.clock_length();
+            self.noise.clock_length();
+        }
+
+        if self.frame_step == 2 || self.frame_step == 6 {
+            self.square1.clock_sweep();
+        }
+
+        if self.frame_step == 7 {
+            self.square1.clock_envelope();
+            self.square2.clock_envelope();
+            self.noise.clock_envelope();
+        }
+
+        self.frame_step = (self.frame_step + 1) % 8;
+    }
+
+    //NOTE: The channels run off the normal clock, even in double speed mode
+    pub fn update(&mut self, t_cycles: usize) {
+        for _ in 0..t_cycles {
+            if self.powered {
+                self.square1.tick();
+                self.square2.tick();
+                self.wave.tick();
+                self.noise.tick();
+            }
+
+            self.sample_counter += SAMPLE_RATE;
+            if self.sample_counter >= CPU_CLOCK {
+                self.sample_counter -= CPU_CLOCK;
+
+                let frame = self.mix();
+                self.samples.push(frame);
+            }
+        }
+    }
+
+    //NOTE: Every stereo frame produced since the last call, oldest first
+    pub fn take_samples(&mut self) -> Vec<[f32; 2]> {
+        self.samples.drain()
+    }
+
+    //NOTE: Starts recording every frame from now on, a running recording gets thrown away
+    pub fn start_capture(&mut self, split_channels: bool) {
+        self.capture = Some(AudioCapture::new(split_channels));
+    }
+
+    pub fn stop_capture(&mut self) -> Option<AudioCapture> {
+        self.capture.take()
+    }
+
+    pub fn is_capturing(&self) -> bool {
+        self.capture.is_some()
+    }
+
+    fn mix(&mut self) -> [f32; 2] {
+        let channels = self.channel_frames();
+        let frame = channels.iter().fold([0.0; 2], |mix, channel| {
+            [mix[0] + channel[0], mix[1] + channel[1]]
+        });
+        let frame = self.high_pass.apply(frame);
+
+        if let Some(capture) = &mut self.capture {
+            capture.push(frame, channels);
+        }
+
+        frame
+    }
+
+    //NOTE: The stereo output of every channel on its own, already panned and scaled by the master
+    //      volume. The four of them add up to the mix before the high pass.
+    fn channel_frames(&self) -> [[f32; 2]; 4] {
+        let channels = [
+            (self.square1.output(), self.square1.is_dac_enabled()),
+            (self.square2.output(), self.square2.is_dac_enabled()),
+            (self.wave.output(), self.wave.is_dac_enabled()),
+            (self.noise.output(), self.noise.is_dac_enabled()),
+        ];
+
+        let panning = self.registers[(NR51_REGISTER - NR10_REGISTER) as usize];
+        let volume = self.registers[(NR50_REGISTER - NR10_REGISTER) as usize];
+        let left_volume = (((volume >> 4) & 0x07) as f32 + 1.0) / 8.0 / 4.0;
+        let right_volume = ((volume & 0x07) as f32 + 1.0) / 8.0 / 4.0;
+
+        let mut frames = [[0.0; 2]; 4];
+        for (channel, (output, dac_enabled)) in channels.into_iter().enumerate() {
+            //NOTE: The dacs turn the digital 0 - 15 into -1.0 - 1.0, a disabled dac outputs nothing
+            if !dac_enabled {
+                continue;
+            }
+
+            let analog = output as f32 / 7.5 - 1.0;
+
+            if panning & (0x10 << channel) != 0 {
+                frames[channel][0] = analog * left_volume;
+            }
+
+            if panning & (0x01 << channel) != 0 {
+                frames[channel][1] = analog * right_volume;
+            }
+        }
+
+        frames
+    }
+}
+
+impl IoDevice for Apu {
+    fn address_ranges(&self) -> &'static [RangeInclusive<u16>] {
+        &[NR10_REGISTER..=WAVE_RAM_END]
+    }
+
+    fn read(&self, addr: u16) -> u8 {
+        match addr {
+            WAVE_RAM_START..=WAVE_RAM_END => self.wave.read_ram((addr - WAVE_RAM_START) as usize),
+            NR52_REGISTER => {
+                let power = if self.powered { 0x80 } else { 0x00 };
+                let channels = [
+                    self.square1.is_enabled(),
+                    self.square2.is_enabled(),
+                    self.wave.is_enabled(),
+                    self.noise.is_enabled(),
+                ];
+
+                channels
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, enabled)| **enabled)
+                    .fold(0x70 | power, |value, (channel, _)| value | 1 << channel)
+            }
+            _ => {
+                let index = (addr - NR10_REGISTER) as usize;
+                self.registers[index] | READ_MASKS[index]
+            }
+        }
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        match addr {
+            WAVE_RAM_START..=WAVE_RAM_END => {
+                self.wave.write_ram((addr - WAVE_RAM_START) as usize, value)
+            }
+            NR52_REGISTER => self.write_power(value & 0x80 != 0),
+            _ if !self.powered => self.write_length_while_off(addr, value),
+            _ => {
+                self.registers[(addr - NR10_REGISTER) as usize] = value;
+
+                //NOTE: Enabling a length counter in a step that doesn't clock it clocks it once
+                let extra_clock = !self.frame_step.is_multiple_of(2);
+
+                match addr {
+                    0xFF10..=0xFF14 => self.square1.write(addr - 0xFF10, value, extra_clock),
+                    0xFF16..=0xFF19 => self.square2.write(addr - 0xFF15, value, extra_clock),
+                    0xFF1A..=0xFF1E => self.wave.write(addr - 0xFF1A, value, extra_clock),
+                    0xFF20..=0xFF23 => self.noise.write(addr - 0xFF1F, value, extra_clock),
+                    _ => (),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::wav::write_wav;
+    use super::*;
+    use crate::test_util::temp_path;
+
+    #[test]
+    fn test_power_off_clears_the_registers() {
+        let mut apu = Apu::default();
+        assert_eq!(apu.read(NR52_REGISTER), 0x70);
+
+        //NOTE: Only the length can be written while the apu is off
+        apu.write(0xFF11, 0xFF);
+        apu.write(NR50_REGISTER, 0x77);
+        assert_eq!(apu.read(0xFF11), 0x3F);
+        assert_eq!(apu.read(NR50_REGISTER), 0x00);
+
+        apu.write(NR52_REGISTER, 0x80);
+        apu.write(NR50_REGISTER, 0x77);
+        apu.write(0xFF11, 0xBF);
+        apu.write(0xFF12, 0xF0);
+        apu.write(0xFF14, 0x80);
+        apu.write(WAVE_RAM_START, 0x12);
+        assert_eq!(apu.read(NR50_REGISTER), 0x77);
+        assert_eq!(apu.read(0xFF11), 0xBF);
+        assert_eq!(apu.read(0xFF15), 0xFF);
+        assert_eq!(apu.read(NR52_REGISTER), 0xF1);
+
+        apu.write(NR52_REGISTER, 0x00);
+        assert_eq!(apu.read(NR50_REGISTER), 0x00);
+        assert_eq!(apu.read(0xFF11), 0x3F);
+        assert_eq!(apu.read(NR52_REGISTER), 0x70);
+        assert_eq!(apu.read(WAVE_RAM_START), 0x12);
+    }
+
+    #[test]
+    fn test_headless_wav_dump() {
+        let mut apu = Apu::default();
+        apu.write(NR52_REGISTER, 0x80);
+        apu.write(NR50_REGISTER, 0x77);
+        apu.write(NR51_REGISTER, 0x11);
+
+        //NOTE: A 440 Hz square wave, 131072 / (2048 - 1750) Hz
+        apu.write(0xFF11, 0x80);
+        apu.write(0xFF12, 0xF0);
+        apu.write(0xFF13, (1750 & 0xFF) as u8);
+        apu.write(0xFF14, 0x80 | (1750 >> 8) as u8);
+
+        //NOTE: A tenth of a second, the last frame isn't quite done yet
+        apu.update(CPU_CLOCK as usize / 10);
+        let frames = apu.take_samples();
+        assert_eq!(frames.len(), SAMPLE_RATE as usize / 10 - 1);
+
+        //NOTE: Both sides play the channel, the high pass keeps the output centered around zero
+        assert!(frames.iter().any(|frame| frame[0] > 0.1));
+        assert!(frames.iter().any(|frame| frame[0] < -0.1));
+        assert!(frames.iter().all(|frame| frame[0] == frame[1]));
+
+        let path = temp_path("square_440.wav");
+        let mut file = std::fs::File::create(&path).unwrap();
+        write_wav(&mut file, SAMPLE_RATE, &frames).unwrap();
+
+        let size = std::fs::metadata(&path).unwrap().len();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(size, 44 + frames.len() as u64 * 4);
+    }
+
+    #[test]
+    fn test_capture_keeps_a_track_per_channel() {
+        let mut apu = Apu::default();
+        apu.write(NR52_REGISTER, 0x80);
+        apu.write(NR51_REGISTER, 0xFF);
+        apu.write(0xFF17, 0xF0);
+        apu.write(0xFF19, 0x87);
+
+        apu.start_capture(true);
+        assert!(apu.is_capturing());
+        apu.update(CPU_CLOCK as usize / 100);
+
+        let capture = apu.stop_capture().unwrap();
+        assert!(!apu.is_capturing());
+
+        let files = capture.save(&temp_path("channels.wav")).unwrap();
+        let square2 = std::fs::read(&files[2]).unwrap();
+        let noise = std::fs::read(&files[4]).unwrap();
+        for file in files {
+            std::fs::remove_file(file).unwrap();
+        }
+
+        assert!(square2[44..].iter().any(|byte| *byte != 0x00));
+        assert!(noise[44..].iter().all(|byte| *byte == 0x00));
+    }
+}
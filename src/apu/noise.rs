@@ -0,0 +1,164 @@
+use super::envelope::Envelope;
+use super::length::LengthCounter;
+
+const DIVISORS: [u16; 8] = [8, 16, 32, 48, 64, 80, 96, 112];
+
+//NOTE: Pseudo random noise out of a 15 bit linear feedback shift register, the short mode feeds
+//      the result back into bit 6 as well which gives a more metallic sound.
+pub struct NoiseChannel {
+    enabled: bool,
+    dac_enabled: bool,
+
+    shift: u8,
+    short_mode: bool,
+    divisor: u8,
+    timer: u16,
+    lfsr: u16,
+
+    length: LengthCounter,
+    envelope: Envelope,
+}
+
+impl Default for NoiseChannel {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dac_enabled: false,
+
+            shift: 0,
+            short_mode: false,
+            divisor: 0,
+            timer: 0,
+            lfsr: 0x7FFF,
+
+            length: LengthCounter::new(64),
+            envelope: Envelope::default(),
+        }
+    }
+}
+
+impl NoiseChannel {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn is_dac_enabled(&self) -> bool {
+        self.dac_enabled
+    }
+
+    //NOTE: The registers NR41 - NR44, there is no NR40
+    pub fn write(&mut self, register: u16, value: u8, extra_clock: bool) {
+        match register {
+            1 => self.length.load(value & 0x3F),
+            2 => {
+                self.envelope.write(value);
+                self.dac_enabled = Envelope::is_dac_enabled(value);
+                self.enabled &= self.dac_enabled;
+            }
+            3 => {
+                self.shift = value >> 4;
+                self.short_mode = value & 0x08 != 0;
+                self.divisor = value & 0x07;
+            }
+            4 => {
+                if self.length.set_enabled(value & 0x40 != 0, extra_clock) {
+                    self.enabled = false;
+                }
+
+                if value & 0x80 != 0 {
+                    self.trigger(extra_clock);
+                }
+            }
+            _ => panic!("Invalid register for the noise channel"),
+        }
+    }
+
+    pub fn write_length(&mut self, value: u8) {
+        self.length.load(value & 0x3F);
+    }
+
+    //NOTE: Resets everything but the length counter
+    pub fn power_off(&mut self) {
+        let length = std::mem::replace(&mut self.length, LengthCounter::new(64));
+        *self = Self {
+            length,
+            ..Self::default()
+        };
+    }
+
+    fn trigger(&mut self, extra_clock: bool) {
+        self.enabled = self.dac_enabled;
+        self.timer = self.period();
+        self.lfsr = 0x7FFF;
+        self.length.trigger(extra_clock);
+        self.envelope.trigger();
+    }
+
+    fn period(&self) -> u16 {
+        DIVISORS[self.divisor as usize] << self.shift
+    }
+
+    pub fn tick(&mut self) {
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+
+        self.timer = self.period().saturating_sub(1);
+
+        //NOTE: Shifts 14 and 15 stop the clock of the shift register
+        if self.shift >= 14 {
+            return;
+        }
+
+        let bit = (self.lfsr ^ (self.lfsr >> 1)) & 0x01;
+        self.lfsr = (self.lfsr >> 1) | (bit << 14);
+
+        if self.short_mode {
+            self.lfsr = (self.lfsr & !0x40) | (bit << 6);
+        }
+    }
+
+    pub fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.enabled = false;
+        }
+    }
+
+    pub fn clock_envelope(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub fn output(&self) -> u8 {
+        if !self.enabled || self.lfsr & 0x01 != 0 {
+            return 0;
+        }
+
+        self.envelope.volume()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_short_mode_repeats_every_127_steps() {
+        let mut channel = NoiseChannel::default();
+        channel.write(2, 0xF0, false);
+        channel.write(3, 0x08, false);
+        channel.write(4, 0x80, false);
+
+        //NOTE: A divisor of 8 without a shift clocks the shift register every eight t-cycles
+        let mut steps = Vec::new();
+        for _ in 0..254 {
+            for _ in 0..8 {
+                channel.tick();
+            }
+            steps.push(channel.lfsr & 0x7F);
+        }
+
+        assert_eq!(steps[..127], steps[127..]);
+        assert!(steps.iter().any(|step| step & 0x01 == 0));
+    }
+}
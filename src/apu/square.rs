@@ -0,0 +1,285 @@
+use super::envelope::Envelope;
+use super::length::LengthCounter;
+
+const DUTY_PATTERNS: [[u8; 8]; 4] = [
+    [0, 0, 0, 0, 0, 0, 0, 1],
+    [1, 0, 0, 0, 0, 0, 0, 1],
+    [1, 0, 0, 0, 0, 1, 1, 1],
+    [0, 1, 1, 1, 1, 1, 1, 0],
+];
+
+//NOTE: Only the first square channel has a sweep unit, it changes the frequency at 128 Hz
+#[derive(Default)]
+struct Sweep {
+    period: u8,
+    negate: bool,
+    shift: u8,
+
+    timer: u8,
+    enabled: bool,
+    shadow_frequency: u16,
+    //NOTE: Switching back from subtraction to addition after a subtraction turns the channel off
+    negate_used: bool,
+}
+
+impl Sweep {
+    //NOTE: Returns false if the write turned the channel off
+    fn write(&mut self, value: u8) -> bool {
+        self.period = (value >> 4) & 0x07;
+        self.negate = value & 0x08 != 0;
+        self.shift = value & 0x07;
+
+        !self.negate_used || self.negate
+    }
+
+    //NOTE: Returns false if the first calculation already overflows
+    fn trigger(&mut self, frequency: u16) -> bool {
+        self.shadow_frequency = frequency;
+        self.timer = self.reload();
+        self.enabled = self.period != 0 || self.shift != 0;
+        self.negate_used = false;
+
+        self.shift == 0 || self.calculate() <= 0x7FF
+    }
+
+    //NOTE: Returns false if the frequency overflowed and the channel has to be turned off
+    fn clock(&mut self, frequency: &mut u16) -> bool {
+        self.timer = self.timer.saturating_sub(1);
+        if self.timer > 0 {
+            return true;
+        }
+
+        self.timer = self.reload();
+        if !self.enabled || self.period == 0 {
+            return true;
+        }
+
+        let new_frequency = self.calculate();
+        if new_frequency > 0x7FF {
+            return false;
+        }
+
+        if self.shift == 0 {
+            return true;
+        }
+
+        self.shadow_frequency = new_frequency;
+        *frequency = new_frequency;
+
+        //NOTE: The hardware checks the next step for an overflow right away
+        self.calculate() <= 0x7FF
+    }
+
+    fn calculate(&mut self) -> u16 {
+        let delta = self.shadow_frequency >> self.shift;
+
+        if self.negate {
+            self.negate_used = true;
+            self.shadow_frequency.wrapping_sub(delta)
+        } else {
+            self.shadow_frequency + delta
+        }
+    }
+
+    fn reload(&self) -> u8 {
+        if self.period == 0 {
+            8
+        } else {
+            self.period
+        }
+    }
+}
+
+pub struct SquareChannel {
+    enabled: bool,
+    dac_enabled: bool,
+
+    duty: u8,
+    duty_step: u8,
+    frequency: u16,
+    timer: u16,
+
+    sweep: Option<Sweep>,
+    length: LengthCounter,
+    envelope: Envelope,
+}
+
+impl SquareChannel {
+    pub fn new(with_sweep: bool) -> Self {
+        Self {
+            enabled: false,
+            dac_enabled: false,
+
+            duty: 0,
+            duty_step: 0,
+            frequency: 0,
+            timer: 0,
+
+            sweep: with_sweep.then(Sweep::default),
+            length: LengthCounter::new(64),
+            envelope: Envelope::default(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    //NOTE: The registers NRx0 - NRx4 of the channel, `extra_clock` tells the length counter that
+    //      the frame sequencer won't clock it next.
+    pub fn write(&mut self, register: u16, value: u8, extra_clock: bool) {
+        match register {
+            0 => {
+                if let Some(sweep) = &mut self.sweep {
+                    self.enabled &= sweep.write(value);
+                }
+            }
+            1 => {
+                self.duty = value >> 6;
+                self.length.load(value & 0x3F);
+            }
+            2 => {
+                self.envelope.write(value);
+                self.dac_enabled = Envelope::is_dac_enabled(value);
+                self.enabled &= self.dac_enabled;
+            }
+            3 => self.frequency = (self.frequency & 0x700) | value as u16,
+            4 => {
+                self.frequency = (self.frequency & 0xFF) | ((value & 0x07) as u16) << 8;
+
+                if self.length.set_enabled(value & 0x40 != 0, extra_clock) {
+                    self.enabled = false;
+                }
+
+                if value & 0x80 != 0 {
+                    self.trigger(extra_clock);
+                }
+            }
+            _ => panic!("Invalid register for a square channel"),
+        }
+    }
+
+    //NOTE: Only the length part of NRx1 can be written while the apu is off
+    pub fn write_length(&mut self, value: u8) {
+        self.length.load(value & 0x3F);
+    }
+
+    //NOTE: Resets everything but the length counter
+    pub fn power_off(&mut self) {
+        let length = std::mem::replace(&mut self.length, LengthCounter::new(64));
+        *self = Self {
+            length,
+            ..Self::new(self.sweep.is_some())
+        };
+    }
+
+    fn trigger(&mut self, extra_clock: bool) {
+        self.enabled = self.dac_enabled;
+        self.timer = (2048 - self.frequency) * 4;
+        self.length.trigger(extra_clock);
+        self.envelope.trigger();
+
+        if let Some(sweep) = &mut self.sweep {
+            if !sweep.trigger(self.frequency) {
+                self.enabled = false;
+            }
+        }
+    }
+
+    pub fn tick(&mut self) {
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+
+        self.timer = (2048 - self.frequency) * 4 - 1;
+        self.duty_step = (self.duty_step + 1) & 0x07;
+    }
+
+    pub fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.enabled = false;
+        }
+    }
+
+    pub fn clock_envelope(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub fn clock_sweep(&mut self) {
+        let Some(sweep) = &mut self.sweep else {
+            return;
+        };
+
+        if !sweep.clock(&mut self.frequency) {
+            self.enabled = false;
+        }
+    }
+
+    pub fn is_dac_enabled(&self) -> bool {
+        self.dac_enabled
+    }
+
+    //NOTE: Digital output between 0 and 15
+    pub fn output(&self) -> u8 {
+        if !self.enabled {
+            return 0;
+        }
+
+        DUTY_PATTERNS[self.duty as usize][self.duty_step as usize] * self.envelope.volume()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_duty_cycle() {
+        let mut channel = SquareChannel::new(false);
+        channel.write(1, 0x80, false);
+        channel.write(2, 0xF0, false);
+        channel.write(3, 0xFF, false);
+        channel.write(4, 0x87, false);
+
+        //NOTE: A frequency of 0x7FF moves one step of the duty cycle every four t-cycles
+        let mut outputs = Vec::new();
+        for _ in 0..8 {
+            outputs.push(channel.output());
+            for _ in 0..4 {
+                channel.tick();
+            }
+        }
+
+        assert_eq!(outputs.iter().filter(|output| **output == 0x0F).count(), 4);
+    }
+
+    #[test]
+    fn test_sweep_overflow_turns_the_channel_off() {
+        let mut channel = SquareChannel::new(true);
+        channel.write(0, 0x11, false);
+        channel.write(2, 0xF0, false);
+        channel.write(3, 0x00, false);
+        channel.write(4, 0x84, false);
+        assert!(channel.is_enabled());
+
+        channel.clock_sweep();
+        assert_eq!(channel.frequency, 0x600);
+        assert!(!channel.is_enabled());
+    }
+
+    #[test]
+    fn test_dac_off_turns_the_channel_off() {
+        let mut channel = SquareChannel::new(false);
+        channel.write(2, 0xF0, false);
+        channel.write(4, 0x80, false);
+        assert!(channel.is_enabled());
+
+        channel.write(2, 0x07, false);
+        assert!(!channel.is_enabled());
+
+        //NOTE: Turning the dac back on doesn't restart the channel
+        channel.write(2, 0xF0, false);
+        assert!(!channel.is_enabled());
+    }
+}
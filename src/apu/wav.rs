@@ -0,0 +1,51 @@
+use std::io::{self, Write};
+
+//NOTE: Writes the frames as a 16 bit stereo pcm wave file
+pub fn write_wav(writer: &mut impl Write, sample_rate: u32, frames: &[[f32; 2]]) -> io::Result<()> {
+    let data_size = frames.len() as u32 * 4;
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(36 + data_size).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;
+    //NOTE: Format 1 is uncompressed pcm, followed by the channel count
+    writer.write_all(&1u16.to_le_bytes())?;
+    writer.write_all(&2u16.to_le_bytes())?;
+    writer.write_all(&sample_rate.to_le_bytes())?;
+    writer.write_all(&(sample_rate * 4).to_le_bytes())?;
+    writer.write_all(&4u16.to_le_bytes())?;
+    writer.write_all(&16u16.to_le_bytes())?;
+
+    writer.write_all(b"data")?;
+    writer.write_all(&data_size.to_le_bytes())?;
+
+    for frame in frames {
+        for sample in frame {
+            let sample = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+            writer.write_all(&sample.to_le_bytes())?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_wav_header() {
+        let mut bytes = Vec::new();
+        write_wav(&mut bytes, 48000, &[[1.0, -1.0], [0.0, 2.0]]).unwrap();
+
+        assert_eq!(bytes.len(), 44 + 8);
+        assert_eq!(&bytes[0..4], b"RIFF");
+        assert_eq!(&bytes[8..16], b"WAVEfmt ");
+        assert_eq!(&bytes[24..28], &48000u32.to_le_bytes());
+        assert_eq!(&bytes[36..44], b"data\x08\x00\x00\x00");
+        assert_eq!(&bytes[44..48], &[0xFF, 0x7F, 0x01, 0x80]);
+        assert_eq!(&bytes[50..52], &[0xFF, 0x7F]);
+    }
+}
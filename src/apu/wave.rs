@@ -0,0 +1,168 @@
+use super::length::LengthCounter;
+
+pub const WAVE_RAM_SIZE: usize = 0x10;
+
+//NOTE: Plays back the 32 four bit samples of the wave ram, the upper nibble of a byte comes first
+pub struct WaveChannel {
+    enabled: bool,
+    dac_enabled: bool,
+
+    volume_shift: u8,
+    frequency: u16,
+    timer: u16,
+    position: usize,
+    sample: u8,
+
+    ram: [u8; WAVE_RAM_SIZE],
+    length: LengthCounter,
+}
+
+impl Default for WaveChannel {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dac_enabled: false,
+
+            volume_shift: 0,
+            frequency: 0,
+            timer: 0,
+            position: 0,
+            sample: 0,
+
+            ram: [0x00; WAVE_RAM_SIZE],
+            length: LengthCounter::new(256),
+        }
+    }
+}
+
+impl WaveChannel {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn is_dac_enabled(&self) -> bool {
+        self.dac_enabled
+    }
+
+    //NOTE: The registers NR30 - NR34, see `SquareChannel::write` for `extra_clock`
+    pub fn write(&mut self, register: u16, value: u8, extra_clock: bool) {
+        match register {
+            0 => {
+                self.dac_enabled = value & 0x80 != 0;
+                self.enabled &= self.dac_enabled;
+            }
+            1 => self.length.load(value),
+            //NOTE: 0 mutes the channel, 1 - 3 play it at 100%, 50% and 25%
+            2 => self.volume_shift = (value >> 5) & 0x03,
+            3 => self.frequency = (self.frequency & 0x700) | value as u16,
+            4 => {
+                self.frequency = (self.frequency & 0xFF) | ((value & 0x07) as u16) << 8;
+
+                if self.length.set_enabled(value & 0x40 != 0, extra_clock) {
+                    self.enabled = false;
+                }
+
+                if value & 0x80 != 0 {
+                    self.trigger(extra_clock);
+                }
+            }
+            _ => panic!("Invalid register for the wave channel"),
+        }
+    }
+
+    pub fn write_length(&mut self, value: u8) {
+        self.length.load(value);
+    }
+
+    pub fn read_ram(&self, offset: usize) -> u8 {
+        self.ram[offset]
+    }
+
+    pub fn write_ram(&mut self, offset: usize, value: u8) {
+        self.ram[offset] = value;
+    }
+
+    //NOTE: Resets everything but the length counter and the wave ram
+    pub fn power_off(&mut self) {
+        let length = std::mem::replace(&mut self.length, LengthCounter::new(256));
+        *self = Self {
+            ram: self.ram,
+            length,
+            ..Self::default()
+        };
+    }
+
+    fn trigger(&mut self, extra_clock: bool) {
+        self.enabled = self.dac_enabled;
+        //NOTE: The first sample only gets picked up after the timer ran out once
+        self.timer = (2048 - self.frequency) * 2 + 6;
+        self.position = 0;
+        self.length.trigger(extra_clock);
+    }
+
+    pub fn tick(&mut self) {
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+
+        self.timer = (2048 - self.frequency) * 2 - 1;
+        self.position = (self.position + 1) & 0x1F;
+
+        let byte = self.ram[self.position / 2];
+        self.sample = if self.position.is_multiple_of(2) {
+            byte >> 4
+        } else {
+            byte & 0x0F
+        };
+    }
+
+    pub fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.enabled = false;
+        }
+    }
+
+    pub fn output(&self) -> u8 {
+        if !self.enabled || self.volume_shift == 0 {
+            return 0;
+        }
+
+        self.sample >> (self.volume_shift - 1)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_wave_playback() {
+        let mut channel = WaveChannel::default();
+        for offset in 0..WAVE_RAM_SIZE {
+            channel.write_ram(offset, 0x0F);
+        }
+
+        channel.write(0, 0x80, false);
+        channel.write(2, 0x20, false);
+        channel.write(3, 0xFF, false);
+        channel.write(4, 0x87, false);
+        assert!(channel.is_enabled());
+
+        //NOTE: Skips the startup delay, then every two t-cycles move on to the next nibble
+        for _ in 0..9 {
+            channel.tick();
+        }
+        assert_eq!(channel.output(), 0x0F);
+
+        channel.tick();
+        channel.tick();
+        assert_eq!(channel.output(), 0x00);
+
+        //NOTE: A volume of 25% shifts the sample by two
+        channel.write(2, 0x60, false);
+        channel.tick();
+        channel.tick();
+        assert_eq!(channel.output(), 0x03);
+    }
+}
@@ -0,0 +1,313 @@
+use std::fmt::Display;
+
+use super::CartridgeError;
+
+const HEADER_END: usize = 0x0150;
+const TITLE_START: usize = 0x0134;
+const TITLE_END: usize = 0x0144;
+const MANUFACTURER_START: usize = 0x013F;
+const MANUFACTURER_END: usize = 0x0143;
+const CGB_FLAG_ADDRESS: usize = 0x0143;
+const SGB_FLAG_ADDRESS: usize = 0x0146;
+const CARTRIDGE_TYPE_ADDRESS: usize = 0x0147;
+const ROM_SIZE_ADDRESS: usize = 0x0148;
+const RAM_SIZE_ADDRESS: usize = 0x0149;
+const DESTINATION_ADDRESS: usize = 0x014A;
+const VERSION_ADDRESS: usize = 0x014C;
+const HEADER_CHECKSUM_ADDRESS: usize = 0x014D;
+const GLOBAL_CHECKSUM_ADDRESS: usize = 0x014E;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum CgbSupport {
+    None,
+    Enhanced,
+    Only,
+}
+
+impl Display for CgbSupport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CgbSupport::None => write!(f, "NONE"),
+            CgbSupport::Enhanced => write!(f, "ENHANCED"),
+            CgbSupport::Only => write!(f, "ONLY"),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Destination {
+    Japan,
+    Overseas,
+}
+
+impl Display for Destination {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Destination::Japan => write!(f, "JAPAN"),
+            Destination::Overseas => write!(f, "OVERSEAS"),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CartridgeHeader {
+    pub title: String,
+    pub manufacturer_code: Option<String>,
+    pub cgb_support: CgbSupport,
+    pub sgb_support: bool,
+    pub cartridge_type: u8,
+    pub rom_size: usize,
+    pub ram_size: usize,
+    pub destination: Destination,
+    pub version: u8,
+    pub header_checksum: u8,
+    pub global_checksum: u16,
+}
+
+impl CartridgeHeader {
+    pub fn parse(rom: &[u8]) -> Result<Self, CartridgeError> {
+        if rom.len() < HEADER_END {
+            return Err(CartridgeError::TooSmall(rom.len()));
+        }
+
+        let cgb_support = match rom[CGB_FLAG_ADDRESS] {
+            0x80 => CgbSupport::Enhanced,
+            0xC0 => CgbSupport::Only,
+            _ => CgbSupport::None,
+        };
+
+        //NOTE: Newer cartridges cut the title short to make room for the manufacturer code and the
+        //      cgb flag, older ones use all 16 bytes for the title.
+        let (title_end, manufacturer_code) = match cgb_support {
+            CgbSupport::None => (TITLE_END, None),
+            _ => (
+                MANUFACTURER_START,
+                Some(Self::read_text(&rom[MANUFACTURER_START..MANUFACTURER_END])),
+            ),
+        };
+
+        Ok(Self {
+            title: Self::read_text(&rom[TITLE_START..title_end]),
+            manufacturer_code: manufacturer_code.filter(|code| code.len() == 4),
+            cgb_support,
+            sgb_support: rom[SGB_FLAG_ADDRESS] == 0x03,
+            cartridge_type: rom[CARTRIDGE_TYPE_ADDRESS],
+            rom_size: Self::rom_size(rom[ROM_SIZE_ADDRESS])?,
+            ram_size: Self::ram_size(rom[RAM_SIZE_ADDRESS])?,
+            destination: if rom[DESTINATION_ADDRESS] == 0x00 {
+                Destination::Japan
+            } else {
+                Destination::Overseas
+            },
+            version: rom[VERSION_ADDRESS],
+            header_checksum: rom[HEADER_CHECKSUM_ADDRESS],
+            global_checksum: (rom[GLOBAL_CHECKSUM_ADDRESS] as u16) << 8
+                | rom[GLOBAL_CHECKSUM_ADDRESS + 1] as u16,
+        })
+    }
+
+    //NOTE: The boot rom refuses to start a cartridge with a broken header checksum, the global
+    //      checksum is never looked at by the hardware. We still report both so they can be shown.
+    pub fn verify_checksums(&self, rom: &[u8]) -> Vec<CartridgeError> {
+        let mut errors = Vec::new();
+
+        let header_checksum = Self::compute_header_checksum(rom);
+        if header_checksum != self.header_checksum {
+            errors.push(CartridgeError::HeaderChecksum {
+                expected: self.header_checksum,
+                actual: header_checksum,
+            });
+        }
+
+        let global_checksum = Self::compute_global_checksum(rom);
+        if global_checksum != self.global_checksum {
+            errors.push(CartridgeError::GlobalChecksum {
+                expected: self.global_checksum,
+                actual: global_checksum,
+            });
+        }
+
+        errors
+    }
+
+    pub fn compute_header_checksum(rom: &[u8]) -> u8 {
+        rom[TITLE_START..HEADER_CHECKSUM_ADDRESS]
+            .iter()
+            .fold(0u8, |checksum, byte| {
+                checksum.wrapping_sub(*byte).wrapping_sub(1)
+            })
+    }
+
+    pub fn compute_global_checksum(rom: &[u8]) -> u16 {
+        rom.iter()
+            .enumerate()
+            .filter(|(addr, _)| {
+                *addr != GLOBAL_CHECKSUM_ADDRESS && *addr != GLOBAL_CHECKSUM_ADDRESS + 1
+            })
+            .fold(0u16, |checksum, (_, byte)| {
+                checksum.wrapping_add(*byte as u16)
+            })
+    }
+
+    pub fn cartridge_type_name(&self) -> &'static str {
+        match self.cartridge_type {
+            0x00 => "ROM ONLY",
+            0x01 => "MBC1",
+            0x02 => "MBC1+RAM",
+            0x03 => "MBC1+RAM+BATTERY",
+            0x05 => "MBC2",
+            0x06 => "MBC2+BATTERY",
+            0x08 => "ROM+RAM",
+            0x09 => "ROM+RAM+BATTERY",
+            0x0B => "MMM01",
+            0x0C => "MMM01+RAM",
+            0x0D => "MMM01+RAM+BATTERY",
+            0x0F => "MBC3+TIMER+BATTERY",
+            0x10 => "MBC3+TIMER+RAM+BATTERY",
+            0x11 => "MBC3",
+            0x12 => "MBC3+RAM",
+            0x13 => "MBC3+RAM+BATTERY",
+            0x19 => "MBC5",
+            0x1A => "MBC5+RAM",
+            0x1B => "MBC5+RAM+BATTERY",
+            0x1C => "MBC5+RUMBLE",
+            0x1D => "MBC5+RUMBLE+RAM",
+            0x1E => "MBC5+RUMBLE+RAM+BATTERY",
+            0x20 => "MBC6",
+            0x22 => "MBC7+SENSOR+RUMBLE+RAM+BATTERY",
+            0xFC => "POCKET CAMERA",
+            0xFD => "BANDAI TAMA5",
+            0xFE => "HuC3",
+            0xFF => "HuC1+RAM+BATTERY",
+            _ => "UNKNOWN",
+        }
+    }
+
+    pub fn has_battery(&self) -> bool {
+        matches!(
+            self.cartridge_type,
+            0x03 | 0x06 | 0x09 | 0x0D | 0x0F | 0x10 | 0x13 | 0x1B | 0x1E | 0x22 | 0xFF
+        )
+    }
+
+    pub fn has_timer(&self) -> bool {
+        matches!(self.cartridge_type, 0x0F | 0x10)
+    }
+
+    fn read_text(bytes: &[u8]) -> String {
+        bytes
+            .iter()
+            .take_while(|byte| **byte != 0x00)
+            .map(|byte| {
+                if byte.is_ascii_graphic() || *byte == b' ' {
+                    *byte as char
+                } else {
+                    '?'
+                }
+            })
+            .collect()
+    }
+
+    fn rom_size(value: u8) -> Result<usize, CartridgeError> {
+        match value {
+            0x00..=0x08 => Ok(0x8000 << value),
+            _ => Err(CartridgeError::InvalidRomSize(value)),
+        }
+    }
+
+    fn ram_size(value: u8) -> Result<usize, CartridgeError> {
+        match value {
+            0x00 => Ok(0),
+            //NOTE: Never used by any licensed game but some homebrew roms still use it
+            0x01 => Ok(0x800),
+            0x02 => Ok(0x2000),
+            0x03 => Ok(0x8000),
+            0x04 => Ok(0x20000),
+            0x05 => Ok(0x10000),
+            _ => Err(CartridgeError::InvalidRamSize(value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn build_header() -> Vec<u8> {
+        let mut rom = vec![0x00; 0x8000];
+
+        rom[TITLE_START..TITLE_START + 8].copy_from_slice(b"GBOYRUST");
+        rom[CARTRIDGE_TYPE_ADDRESS] = 0x13;
+        rom[ROM_SIZE_ADDRESS] = 0x00;
+        rom[RAM_SIZE_ADDRESS] = 0x03;
+        rom[DESTINATION_ADDRESS] = 0x01;
+        rom[VERSION_ADDRESS] = 0x02;
+        rom[HEADER_CHECKSUM_ADDRESS] = CartridgeHeader::compute_header_checksum(&rom);
+
+        let global_checksum = CartridgeHeader::compute_global_checksum(&rom);
+        rom[GLOBAL_CHECKSUM_ADDRESS] = (global_checksum >> 8) as u8;
+        rom[GLOBAL_CHECKSUM_ADDRESS + 1] = global_checksum as u8;
+
+        rom
+    }
+
+    #[test]
+    fn test_parse_header() {
+        let rom = build_header();
+        let header = CartridgeHeader::parse(&rom).unwrap();
+
+        assert_eq!(header.title, "GBOYRUST");
+        assert_eq!(header.manufacturer_code, None);
+        assert_eq!(header.cgb_support, CgbSupport::None);
+        assert!(!header.sgb_support);
+        assert_eq!(header.cartridge_type_name(), "MBC3+RAM+BATTERY");
+        assert!(header.has_battery());
+        assert!(!header.has_timer());
+        assert_eq!(header.rom_size, 0x8000);
+        assert_eq!(header.ram_size, 0x8000);
+        assert_eq!(header.destination, Destination::Overseas);
+        assert_eq!(header.version, 0x02);
+        assert!(header.verify_checksums(&rom).is_empty());
+    }
+
+    #[test]
+    fn test_parse_cgb_header() {
+        let mut rom = build_header();
+        rom[MANUFACTURER_START..MANUFACTURER_END].copy_from_slice(b"AGBE");
+        rom[CGB_FLAG_ADDRESS] = 0xC0;
+
+        let header = CartridgeHeader::parse(&rom).unwrap();
+
+        assert_eq!(header.title, "GBOYRUST");
+        assert_eq!(header.manufacturer_code, Some(String::from("AGBE")));
+        assert_eq!(header.cgb_support, CgbSupport::Only);
+    }
+
+    #[test]
+    fn test_bad_checksums() {
+        let mut rom = build_header();
+        rom[VERSION_ADDRESS] = 0x03;
+
+        let header = CartridgeHeader::parse(&rom).unwrap();
+        let errors = header.verify_checksums(&rom);
+
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(errors[0], CartridgeError::HeaderChecksum { .. }));
+        assert!(matches!(errors[1], CartridgeError::GlobalChecksum { .. }));
+    }
+
+    #[test]
+    fn test_invalid_header() {
+        assert_eq!(
+            CartridgeHeader::parse(&[0x00; 0x100]),
+            Err(CartridgeError::TooSmall(0x100))
+        );
+
+        let mut rom = build_header();
+        rom[ROM_SIZE_ADDRESS] = 0x42;
+        assert_eq!(
+            CartridgeHeader::parse(&rom),
+            Err(CartridgeError::InvalidRomSize(0x42))
+        );
+    }
+}
@@ -0,0 +1,213 @@
+use super::{load_ram, read_banked, write_banked, Mbc, RAM_BANK_SIZE, ROM_BANK_SIZE};
+
+const MULTICART_ROM_SIZE: usize = 0x100000;
+const LOGO_START: usize = 0x0104;
+const LOGO_END: usize = 0x0134;
+
+pub struct Mbc1 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+
+    ram_enabled: bool,
+    bank1: u8,
+    bank2: u8,
+    advanced_banking: bool,
+
+    //NOTE: Multicarts wire the upper bank bits one line lower so every game sees 16 banks of its own
+    multicart: bool,
+}
+
+impl Mbc1 {
+    pub fn new(rom: Vec<u8>, ram: Vec<u8>) -> Self {
+        let multicart = Self::is_multicart(&rom);
+
+        Self {
+            rom,
+            ram,
+
+            ram_enabled: false,
+            bank1: 0x01,
+            bank2: 0x00,
+            advanced_banking: false,
+
+            multicart,
+        }
+    }
+
+    //NOTE: There is no header flag for multicarts, but every game on them starts with its own header
+    //      so the nintendo logo shows up again at the beginning of bank 0x10.
+    fn is_multicart(rom: &[u8]) -> bool {
+        if rom.len() != MULTICART_ROM_SIZE {
+            return false;
+        }
+
+        let second_header = 0x10 * ROM_BANK_SIZE;
+        rom[LOGO_START..LOGO_END] == rom[second_header + LOGO_START..second_header + LOGO_END]
+    }
+
+    fn upper_bank_bits(&self) -> usize {
+        if self.multicart {
+            (self.bank2 as usize) << 4
+        } else {
+            (self.bank2 as usize) << 5
+        }
+    }
+
+    fn lower_bank_bits(&self) -> usize {
+        if self.multicart {
+            (self.bank1 & 0x0F) as usize
+        } else {
+            self.bank1 as usize
+        }
+    }
+}
+
+impl Mbc for Mbc1 {
+    fn read_rom(&self, addr: u16) -> u8 {
+        let offset = (addr & 0x3FFF) as usize;
+
+        match addr {
+            0x0000..=0x3FFF if self.advanced_banking => {
+                read_banked(&self.rom, ROM_BANK_SIZE, self.upper_bank_bits(), offset)
+            }
+            0x0000..=0x3FFF => read_banked(&self.rom, ROM_BANK_SIZE, 0, offset),
+            _ => {
+                let bank = self.upper_bank_bits() | self.lower_bank_bits();
+                read_banked(&self.rom, ROM_BANK_SIZE, bank, offset)
+            }
+        }
+    }
+
+    fn write_rom(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.ram_enabled = value & 0x0F == 0x0A,
+            //NOTE: The zero check looks at all five bits, so bank 0x20 maps to 0x21 and so on
+            0x2000..=0x3FFF => {
+                self.bank1 = if value & 0x1F == 0 {
+                    0x01
+                } else {
+                    value & 0x1F
+                }
+            }
+            0x4000..=0x5FFF => self.bank2 = value & 0x03,
+            _ => self.advanced_banking = value & 0x01 == 0x01,
+        }
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+
+        let bank = if self.advanced_banking {
+            self.bank2 as usize
+        } else {
+            0
+        };
+        read_banked(&self.ram, RAM_BANK_SIZE, bank, (addr & 0x1FFF) as usize)
+    }
+
+    fn write_ram(&mut self, addr: u16, value: u8) {
+        if !self.ram_enabled {
+            return;
+        }
+
+        let bank = if self.advanced_banking {
+            self.bank2 as usize
+        } else {
+            0
+        };
+        write_banked(
+            &mut self.ram,
+            RAM_BANK_SIZE,
+            bank,
+            (addr & 0x1FFF) as usize,
+            value,
+        )
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        self.ram.clone()
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cartridge::test::build_rom;
+
+    fn build_mbc1(banks: usize, ram_banks: usize) -> Mbc1 {
+        Mbc1::new(
+            build_rom(0x03, 0x00, banks),
+            vec![0x00; ram_banks * RAM_BANK_SIZE],
+        )
+    }
+
+    #[test]
+    fn test_rom_banking() {
+        let mut mbc = build_mbc1(128, 0);
+
+        assert_eq!(mbc.read_rom(0x4000), 0x01);
+
+        mbc.write_rom(0x2000, 0x00);
+        assert_eq!(mbc.read_rom(0x4000), 0x01);
+
+        mbc.write_rom(0x2000, 0x1F);
+        assert_eq!(mbc.read_rom(0x4000), 0x1F);
+
+        mbc.write_rom(0x4000, 0x02);
+        assert_eq!(mbc.read_rom(0x4000), 0x5F);
+
+        //NOTE: Writing zero to the lower bits still selects 0x41 and not 0x40
+        mbc.write_rom(0x2000, 0x20);
+        assert_eq!(mbc.read_rom(0x4000), 0x41);
+
+        assert_eq!(mbc.read_rom(0x0000), 0x00);
+        mbc.write_rom(0x6000, 0x01);
+        assert_eq!(mbc.read_rom(0x0000), 0x40);
+    }
+
+    #[test]
+    fn test_ram_banking() {
+        let mut mbc = build_mbc1(4, 4);
+
+        mbc.write_ram(0xA000, 0x42);
+        assert_eq!(mbc.read_ram(0xA000), 0xFF);
+
+        mbc.write_rom(0x0000, 0x0A);
+        mbc.write_ram(0xA000, 0x42);
+        assert_eq!(mbc.read_ram(0xA000), 0x42);
+
+        mbc.write_rom(0x6000, 0x01);
+        mbc.write_rom(0x4000, 0x01);
+        assert_eq!(mbc.read_ram(0xA000), 0x00);
+        mbc.write_ram(0xA000, 0x24);
+
+        mbc.write_rom(0x4000, 0x00);
+        assert_eq!(mbc.read_ram(0xA000), 0x42);
+
+        mbc.write_rom(0x0000, 0x00);
+        assert_eq!(mbc.read_ram(0xA000), 0xFF);
+    }
+
+    #[test]
+    fn test_multicart() {
+        let mut rom = build_rom(0x01, 0x00, 64);
+        rom[LOGO_START..LOGO_END].fill(0xCE);
+        rom[0x10 * ROM_BANK_SIZE + LOGO_START..0x10 * ROM_BANK_SIZE + LOGO_END].fill(0xCE);
+        let mut mbc = Mbc1::new(rom, vec![]);
+
+        assert!(mbc.multicart);
+
+        mbc.write_rom(0x4000, 0x01);
+        mbc.write_rom(0x2000, 0x12);
+        assert_eq!(mbc.read_rom(0x4000), 0x12);
+
+        mbc.write_rom(0x6000, 0x01);
+        assert_eq!(mbc.read_rom(0x0000), 0x10);
+    }
+}
@@ -0,0 +1,117 @@
+use super::{load_ram, read_banked, Mbc, ROM_BANK_SIZE};
+
+const RAM_SIZE: usize = 0x200;
+
+//NOTE: The mbc2 has 512 half bytes of ram built in, the upper four bits aren't connected and always
+//      read back as ones.
+pub struct Mbc2 {
+    rom: Vec<u8>,
+    ram: [u8; RAM_SIZE],
+
+    ram_enabled: bool,
+    rom_bank: u8,
+}
+
+impl Mbc2 {
+    pub fn new(rom: Vec<u8>) -> Self {
+        Self {
+            rom,
+            ram: [0x00; RAM_SIZE],
+
+            ram_enabled: false,
+            rom_bank: 0x01,
+        }
+    }
+}
+
+impl Mbc for Mbc2 {
+    fn read_rom(&self, addr: u16) -> u8 {
+        let bank = if addr < 0x4000 {
+            0
+        } else {
+            self.rom_bank as usize
+        };
+        read_banked(&self.rom, ROM_BANK_SIZE, bank, (addr & 0x3FFF) as usize)
+    }
+
+    fn write_rom(&mut self, addr: u16, value: u8) {
+        //NOTE: Bit 8 of the address decides which register gets written
+        match addr {
+            0x0000..=0x3FFF if addr & 0x0100 == 0 => self.ram_enabled = value & 0x0F == 0x0A,
+            0x0000..=0x3FFF => {
+                self.rom_bank = if value & 0x0F == 0 {
+                    0x01
+                } else {
+                    value & 0x0F
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+
+        //NOTE: The 512 bytes are mirrored over the whole external ram area
+        self.ram[(addr as usize) % RAM_SIZE] | 0xF0
+    }
+
+    fn write_ram(&mut self, addr: u16, value: u8) {
+        if self.ram_enabled {
+            self.ram[(addr as usize) % RAM_SIZE] = value & 0x0F;
+        }
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        self.ram.to_vec()
+    }
+
+    //NOTE: Other emulators don't agree on the upper four bits, we drop them like the hardware does
+    fn load_save_data(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+        self.ram.iter_mut().for_each(|value| *value &= 0x0F);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cartridge::test::build_rom;
+
+    #[test]
+    fn test_register_selection_by_address_bit() {
+        let mut mbc = Mbc2::new(build_rom(0x05, 0x00, 16));
+
+        mbc.write_rom(0x2100, 0x05);
+        assert_eq!(mbc.read_rom(0x4000), 0x05);
+        assert!(!mbc.ram_enabled);
+
+        mbc.write_rom(0x0000, 0x0A);
+        assert!(mbc.ram_enabled);
+        assert_eq!(mbc.read_rom(0x4000), 0x05);
+
+        mbc.write_rom(0x2100, 0x00);
+        assert_eq!(mbc.read_rom(0x4000), 0x01);
+    }
+
+    #[test]
+    fn test_half_byte_ram() {
+        let mut mbc = Mbc2::new(build_rom(0x06, 0x00, 2));
+        mbc.write_rom(0x0000, 0x0A);
+
+        mbc.write_ram(0xA000, 0x5A);
+        assert_eq!(mbc.read_ram(0xA000), 0xFA);
+        assert_eq!(mbc.read_ram(0xA200), 0xFA);
+        assert_eq!(mbc.read_ram(0xBE00), 0xFA);
+    }
+
+    #[test]
+    fn test_save_data_is_one_byte_per_half_byte() {
+        let mut mbc = Mbc2::new(build_rom(0x06, 0x00, 2));
+        mbc.load_save_data(&[0xF5; RAM_SIZE]);
+
+        assert_eq!(mbc.save_data(), vec![0x05; RAM_SIZE]);
+    }
+}
@@ -0,0 +1,192 @@
+use super::rtc::Rtc;
+use super::{load_ram, read_banked, write_banked, Mbc, RAM_BANK_SIZE, ROM_BANK_SIZE};
+
+pub struct Mbc3 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    //NOTE: Only the cartridge types with a timer come with the clock chip
+    rtc: Option<Rtc>,
+
+    ram_enabled: bool,
+    rom_bank: u8,
+    //NOTE: 0x00 - 0x07 select a ram bank, 0x08 - 0x0C one of the clock registers
+    ram_bank: u8,
+}
+
+impl Mbc3 {
+    pub fn new(rom: Vec<u8>, ram: Vec<u8>, rtc: Option<Rtc>) -> Self {
+        Self {
+            rom,
+            ram,
+            rtc,
+
+            ram_enabled: false,
+            rom_bank: 0x01,
+            ram_bank: 0x00,
+        }
+    }
+}
+
+impl Mbc for Mbc3 {
+    fn read_rom(&self, addr: u16) -> u8 {
+        let bank = if addr < 0x4000 {
+            0
+        } else {
+            self.rom_bank as usize
+        };
+        read_banked(&self.rom, ROM_BANK_SIZE, bank, (addr & 0x3FFF) as usize)
+    }
+
+    fn write_rom(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.ram_enabled = value & 0x0F == 0x0A,
+            0x2000..=0x3FFF => {
+                self.rom_bank = if value & 0x7F == 0 {
+                    0x01
+                } else {
+                    value & 0x7F
+                }
+            }
+            0x4000..=0x5FFF => self.ram_bank = value & 0x0F,
+            0x6000..=0x7FFF => {
+                if let Some(rtc) = &mut self.rtc {
+                    rtc.write_latch(value);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        match self.ram_bank {
+            0x00..=0x07 if self.ram_enabled => read_banked(
+                &self.ram,
+                RAM_BANK_SIZE,
+                self.ram_bank as usize,
+                (addr & 0x1FFF) as usize,
+            ),
+            0x08..=0x0C if self.ram_enabled => self
+                .rtc
+                .as_ref()
+                .map(|rtc| rtc.read(self.ram_bank))
+                .unwrap_or(0xFF),
+            _ => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, addr: u16, value: u8) {
+        if !self.ram_enabled {
+            return;
+        }
+
+        match self.ram_bank {
+            0x00..=0x07 => write_banked(
+                &mut self.ram,
+                RAM_BANK_SIZE,
+                self.ram_bank as usize,
+                (addr & 0x1FFF) as usize,
+                value,
+            ),
+            0x08..=0x0C => {
+                if let Some(rtc) = &mut self.rtc {
+                    rtc.write(self.ram_bank, value);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn tick(&mut self, t_cycles: usize) {
+        if let Some(rtc) = &mut self.rtc {
+            rtc.tick(t_cycles);
+        }
+    }
+
+    //NOTE: The clock state gets appended to the ram dump, the same trailing layout bgb and vba use
+    fn save_data(&self) -> Vec<u8> {
+        let mut data = self.ram.clone();
+
+        if let Some(rtc) = &self.rtc {
+            data.extend_from_slice(&rtc.save_data());
+        }
+
+        data
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+
+        if let (Some(rtc), Some(rtc_data)) = (&mut self.rtc, data.get(self.ram.len()..)) {
+            rtc.load_save_data(rtc_data);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cartridge::rtc::{RtcMode, RTC_SAVE_SIZE};
+    use crate::cartridge::test::build_rom;
+
+    #[test]
+    fn test_rom_banking() {
+        let mut mbc = Mbc3::new(build_rom(0x11, 0x00, 128), vec![], None);
+
+        mbc.write_rom(0x2000, 0x00);
+        assert_eq!(mbc.read_rom(0x4000), 0x01);
+
+        mbc.write_rom(0x2000, 0x7F);
+        assert_eq!(mbc.read_rom(0x4000), 0x7F);
+        assert_eq!(mbc.read_rom(0x0000), 0x00);
+    }
+
+    #[test]
+    fn test_ram_banking() {
+        let mut mbc = Mbc3::new(
+            build_rom(0x13, 0x03, 4),
+            vec![0x00; 4 * RAM_BANK_SIZE],
+            None,
+        );
+        mbc.write_rom(0x0000, 0x0A);
+
+        for bank in 0..4 {
+            mbc.write_rom(0x4000, bank);
+            mbc.write_ram(0xA000, 0x10 + bank);
+        }
+
+        for bank in 0..4 {
+            mbc.write_rom(0x4000, bank);
+            assert_eq!(mbc.read_ram(0xA000), 0x10 + bank);
+        }
+    }
+    #[test]
+    fn test_clock_registers() {
+        let mut mbc = Mbc3::new(
+            build_rom(0x10, 0x03, 4),
+            vec![0x00; 4 * RAM_BANK_SIZE],
+            Some(Rtc::new(RtcMode::Emulated)),
+        );
+        mbc.write_rom(0x0000, 0x0A);
+
+        mbc.write_rom(0x4000, 0x09);
+        mbc.write_ram(0xA000, 0x2A);
+        mbc.tick(4194304 * 3);
+        mbc.write_rom(0x6000, 0x00);
+        mbc.write_rom(0x6000, 0x01);
+
+        assert_eq!(mbc.read_ram(0xA000), 0x2A);
+        mbc.write_rom(0x4000, 0x08);
+        assert_eq!(mbc.read_ram(0xA000), 0x03);
+
+        let save = mbc.save_data();
+        assert_eq!(save.len(), 4 * RAM_BANK_SIZE + RTC_SAVE_SIZE);
+        assert_eq!(save[4 * RAM_BANK_SIZE], 0x03);
+        assert_eq!(save[4 * RAM_BANK_SIZE + 4], 0x2A);
+
+        //NOTE: Without the clock chip the registers read as open bus
+        let mut mbc = Mbc3::new(build_rom(0x13, 0x03, 4), vec![], None);
+        mbc.write_rom(0x0000, 0x0A);
+        mbc.write_rom(0x4000, 0x08);
+        assert_eq!(mbc.read_ram(0xA000), 0xFF);
+    }
+}
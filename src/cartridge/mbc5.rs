@@ -0,0 +1,130 @@
+use super::{load_ram, read_banked, write_banked, Mbc, RAM_BANK_SIZE, ROM_BANK_SIZE};
+
+pub struct Mbc5 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+
+    ram_enabled: bool,
+    rom_bank: u16,
+    ram_bank: u8,
+
+    //NOTE: Rumble carts use bit 3 of the ram bank register to drive the motor
+    rumble: bool,
+}
+
+impl Mbc5 {
+    pub fn new(rom: Vec<u8>, ram: Vec<u8>, rumble: bool) -> Self {
+        Self {
+            rom,
+            ram,
+
+            ram_enabled: false,
+            rom_bank: 0x01,
+            ram_bank: 0x00,
+
+            rumble,
+        }
+    }
+
+    fn ram_bank(&self) -> usize {
+        if self.rumble {
+            (self.ram_bank & 0x07) as usize
+        } else {
+            self.ram_bank as usize
+        }
+    }
+}
+
+impl Mbc for Mbc5 {
+    fn read_rom(&self, addr: u16) -> u8 {
+        //NOTE: Unlike the other controllers the mbc5 happily maps bank 0 into 0x4000 - 0x7FFF
+        let bank = if addr < 0x4000 {
+            0
+        } else {
+            self.rom_bank as usize
+        };
+        read_banked(&self.rom, ROM_BANK_SIZE, bank, (addr & 0x3FFF) as usize)
+    }
+
+    fn write_rom(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.ram_enabled = value == 0x0A,
+            0x2000..=0x2FFF => self.rom_bank = (self.rom_bank & 0x100) | value as u16,
+            0x3000..=0x3FFF => {
+                self.rom_bank = (self.rom_bank & 0xFF) | ((value as u16 & 0x01) << 8)
+            }
+            0x4000..=0x5FFF => self.ram_bank = value & 0x0F,
+            _ => (),
+        }
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+
+        read_banked(
+            &self.ram,
+            RAM_BANK_SIZE,
+            self.ram_bank(),
+            (addr & 0x1FFF) as usize,
+        )
+    }
+
+    fn write_ram(&mut self, addr: u16, value: u8) {
+        if self.ram_enabled {
+            let bank = self.ram_bank();
+            write_banked(
+                &mut self.ram,
+                RAM_BANK_SIZE,
+                bank,
+                (addr & 0x1FFF) as usize,
+                value,
+            );
+        }
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        self.ram.clone()
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cartridge::test::build_rom;
+
+    #[test]
+    fn test_nine_bit_rom_bank() {
+        let mut mbc = Mbc5::new(build_rom(0x19, 0x00, 512), vec![], false);
+
+        mbc.write_rom(0x2000, 0x00);
+        assert_eq!(mbc.read_rom(0x4000), 0x00);
+
+        mbc.write_rom(0x2000, 0x42);
+        mbc.write_rom(0x3000, 0x01);
+        //NOTE: Bank 0x142, its first byte holds the lower eight bits of the bank number
+        assert_eq!(mbc.read_rom(0x4000), 0x42);
+        assert_eq!(mbc.rom_bank, 0x142);
+    }
+
+    #[test]
+    fn test_rumble_ignores_motor_bit() {
+        let mut mbc = Mbc5::new(
+            build_rom(0x1E, 0x03, 4),
+            vec![0x00; 4 * RAM_BANK_SIZE],
+            true,
+        );
+        mbc.write_rom(0x0000, 0x0A);
+
+        mbc.write_rom(0x4000, 0x01);
+        mbc.write_ram(0xA000, 0x42);
+
+        mbc.write_rom(0x4000, 0x09);
+        assert_eq!(mbc.read_ram(0xA000), 0x42);
+    }
+}
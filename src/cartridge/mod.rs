@@ -0,0 +1,314 @@
+use std::fmt::Display;
+use std::ops::RangeInclusive;
+
+pub mod header;
+mod mbc1;
+mod mbc2;
+mod mbc3;
+mod mbc5;
+mod rom_only;
+pub mod rtc;
+
+use self::header::CartridgeHeader;
+use self::mbc1::Mbc1;
+use self::mbc2::Mbc2;
+use self::mbc3::Mbc3;
+use self::mbc5::Mbc5;
+use self::rom_only::RomOnly;
+use self::rtc::{Rtc, RtcMode};
+use crate::memory::bus::IoDevice;
+
+pub const ROM_BANK_SIZE: usize = 0x4000;
+pub const RAM_BANK_SIZE: usize = 0x2000;
+
+//NOTE: Reads from 0x0000 - 0x7FFF go to the rom, writes to the same range end up in the registers
+//      of the memory bank controller. 0xA000 - 0xBFFF is the external ram of the cartridge.
+pub trait Mbc {
+    fn read_rom(&self, addr: u16) -> u8;
+    fn write_rom(&mut self, addr: u16, value: u8);
+    fn read_ram(&self, addr: u16) -> u8;
+    fn write_ram(&mut self, addr: u16, value: u8);
+
+    //NOTE: Only for controllers with something that runs on its own, t-cycles are counted at
+    //      normal speed.
+    fn tick(&mut self, _t_cycles: usize) {}
+
+    //NOTE: The contents of a .sav file, a raw dump of the external ram just like every other
+    //      emulator writes it.
+    fn save_data(&self) -> Vec<u8>;
+    fn load_save_data(&mut self, data: &[u8]);
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum CartridgeError {
+    Unreadable(std::io::ErrorKind),
+    UnreadableSave(std::io::ErrorKind),
+    TooSmall(usize),
+    UnsupportedMbc(u8),
+    InvalidRomSize(u8),
+    InvalidRamSize(u8),
+    HeaderChecksum { expected: u8, actual: u8 },
+    GlobalChecksum { expected: u16, actual: u16 },
+}
+
+impl Display for CartridgeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CartridgeError::Unreadable(kind) => write!(f, "Could not read the cartridge: {}", kind),
+            CartridgeError::UnreadableSave(kind) => {
+                write!(f, "Could not read the save file: {}", kind)
+            }
+            CartridgeError::TooSmall(size) => write!(
+                f,
+                "The cartridge is only {} bytes big, it doesn't even hold a header",
+                size
+            ),
+            CartridgeError::UnsupportedMbc(kind) => {
+                write!(f, "Unsupported cartridge type {:02X}", kind)
+            }
+            CartridgeError::InvalidRomSize(size) => write!(f, "Invalid rom size {:02X}", size),
+            CartridgeError::InvalidRamSize(size) => write!(f, "Invalid ram size {:02X}", size),
+            CartridgeError::HeaderChecksum { expected, actual } => write!(
+                f,
+                "Header checksum mismatch, expected {:02X} but got {:02X}",
+                expected, actual
+            ),
+            CartridgeError::GlobalChecksum { expected, actual } => write!(
+                f,
+                "Global checksum mismatch, expected {:04X} but got {:04X}",
+                expected, actual
+            ),
+        }
+    }
+}
+
+pub struct Cartridge {
+    header: Option<CartridgeHeader>,
+    mbc: Box<dyn Mbc>,
+    //NOTE: Set by every write into the external ram so we only touch the .sav file when needed
+    ram_dirty: bool,
+}
+
+//NOTE: An empty slot, everything reads as open bus
+impl Default for Cartridge {
+    fn default() -> Self {
+        Self {
+            header: None,
+            mbc: Box::new(RomOnly::new(vec![], vec![])),
+            ram_dirty: false,
+        }
+    }
+}
+
+impl Cartridge {
+    pub fn from_bytes(bytes: &[u8], rtc_mode: RtcMode) -> Result<Self, CartridgeError> {
+        let header = CartridgeHeader::parse(bytes)?;
+        let rom = bytes.to_vec();
+        let ram = vec![0x00; header.ram_size];
+        let rtc = header.has_timer().then(|| Rtc::new(rtc_mode));
+
+        let mbc: Box<dyn Mbc> = match header.cartridge_type {
+            0x00 | 0x08 | 0x09 => Box::new(RomOnly::new(rom, ram)),
+            0x01..=0x03 => Box::new(Mbc1::new(rom, ram)),
+            0x05 | 0x06 => Box::new(Mbc2::new(rom)),
+            0x0F..=0x13 => Box::new(Mbc3::new(rom, ram, rtc)),
+            0x19..=0x1B => Box::new(Mbc5::new(rom, ram, false)),
+            0x1C..=0x1E => Box::new(Mbc5::new(rom, ram, true)),
+            kind => return Err(CartridgeError::UnsupportedMbc(kind)),
+        };
+
+        Ok(Self {
+            header: Some(header),
+            mbc,
+            ram_dirty: false,
+        })
+    }
+
+    pub fn header(&self) -> Option<&CartridgeHeader> {
+        self.header.as_ref()
+    }
+
+    pub fn read_rom(&self, addr: u16) -> u8 {
+        self.mbc.read_rom(addr)
+    }
+
+    pub fn write_rom(&mut self, addr: u16, value: u8) {
+        self.mbc.write_rom(addr, value)
+    }
+
+    pub fn read_ram(&self, addr: u16) -> u8 {
+        self.mbc.read_ram(addr)
+    }
+
+    pub fn write_ram(&mut self, addr: u16, value: u8) {
+        self.ram_dirty = true;
+        self.mbc.write_ram(addr, value)
+    }
+
+    pub fn tick(&mut self, t_cycles: usize) {
+        self.mbc.tick(t_cycles)
+    }
+
+    pub fn has_battery(&self) -> bool {
+        self.header
+            .as_ref()
+            .is_some_and(|header| header.has_battery())
+    }
+
+    //NOTE: The clock keeps running without the game writing anything, so it always needs saving
+    pub fn is_ram_dirty(&self) -> bool {
+        self.ram_dirty
+            || self
+                .header
+                .as_ref()
+                .is_some_and(|header| header.has_timer())
+    }
+
+    pub fn save_data(&mut self) -> Vec<u8> {
+        self.ram_dirty = false;
+        self.mbc.save_data()
+    }
+
+    pub fn load_save_data(&mut self, data: &[u8]) {
+        self.mbc.load_save_data(data);
+        self.ram_dirty = false;
+    }
+}
+
+impl IoDevice for Cartridge {
+    fn address_ranges(&self) -> &'static [RangeInclusive<u16>] {
+        &[0x0000..=0x7FFF, 0xA000..=0xBFFF]
+    }
+
+    fn read(&self, addr: u16) -> u8 {
+        match addr {
+            0x0000..=0x7FFF => self.read_rom(addr),
+            _ => self.read_ram(addr),
+        }
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x0000..=0x7FFF => self.write_rom(addr, value),
+            _ => self.write_ram(addr, value),
+        }
+    }
+}
+
+//NOTE: Saves from other emulators or an older dump might not have the exact size of the ram, we take
+//      whatever fits and leave the rest alone.
+fn load_ram(ram: &mut [u8], data: &[u8]) {
+    let len = ram.len().min(data.len());
+    ram[..len].copy_from_slice(&data[..len]);
+}
+
+//NOTE: Bank numbers wrap around the amount of banks that are actually there, just like the unused
+//      upper bank bits on the real hardware. Anything outside of the data reads as open bus.
+fn read_banked(data: &[u8], bank_size: usize, bank: usize, offset: usize) -> u8 {
+    if data.is_empty() {
+        return 0xFF;
+    }
+
+    let bank_count = data.len().div_ceil(bank_size);
+    let index = (bank % bank_count) * bank_size + offset;
+
+    data.get(index % data.len()).copied().unwrap_or(0xFF)
+}
+
+fn write_banked(data: &mut [u8], bank_size: usize, bank: usize, offset: usize, value: u8) {
+    if data.is_empty() {
+        return;
+    }
+
+    let bank_count = data.len().div_ceil(bank_size);
+    let index = (bank % bank_count) * bank_size + offset;
+    let len = data.len();
+
+    data[index % len] = value;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    pub fn build_rom(kind: u8, ram_size: u8, banks: usize) -> Vec<u8> {
+        let mut rom = vec![0x00; banks * ROM_BANK_SIZE];
+
+        //NOTE: The first byte of every bank holds the bank number so we can tell them apart
+        for bank in 0..banks {
+            rom[bank * ROM_BANK_SIZE] = bank as u8;
+        }
+
+        rom[0x0147] = kind;
+        rom[0x0149] = ram_size;
+        rom
+    }
+
+    #[test]
+    fn test_mbc_selection() {
+        assert!(Cartridge::from_bytes(&build_rom(0x00, 0x00, 2), RtcMode::Host).is_ok());
+        assert!(Cartridge::from_bytes(&build_rom(0x03, 0x03, 4), RtcMode::Host).is_ok());
+        assert!(Cartridge::from_bytes(&build_rom(0x06, 0x00, 4), RtcMode::Host).is_ok());
+        assert!(Cartridge::from_bytes(&build_rom(0x13, 0x03, 4), RtcMode::Host).is_ok());
+        assert!(Cartridge::from_bytes(&build_rom(0x1B, 0x04, 4), RtcMode::Host).is_ok());
+
+        assert_eq!(
+            Cartridge::from_bytes(&build_rom(0xFC, 0x00, 2), RtcMode::Host).err(),
+            Some(CartridgeError::UnsupportedMbc(0xFC))
+        );
+        assert_eq!(
+            Cartridge::from_bytes(&build_rom(0x01, 0x07, 2), RtcMode::Host).err(),
+            Some(CartridgeError::InvalidRamSize(0x07))
+        );
+    }
+
+    #[test]
+    fn test_rom_only() {
+        let mut cartridge =
+            Cartridge::from_bytes(&build_rom(0x00, 0x00, 2), RtcMode::Host).unwrap();
+
+        assert_eq!(cartridge.read_rom(0x0000), 0x00);
+        assert_eq!(cartridge.read_rom(0x4000), 0x01);
+
+        //NOTE: There is nothing to switch, writes into the rom are simply ignored
+        cartridge.write_rom(0x2000, 0x05);
+        assert_eq!(cartridge.read_rom(0x4000), 0x01);
+        assert_eq!(cartridge.read_ram(0xA000), 0xFF);
+    }
+
+    #[test]
+    fn test_bank_numbers_wrap_around() {
+        let data = [0x00, 0x01, 0x02, 0x03];
+
+        assert_eq!(read_banked(&data, 2, 0, 1), 0x01);
+        assert_eq!(read_banked(&data, 2, 1, 0), 0x02);
+        assert_eq!(read_banked(&data, 2, 3, 0), 0x02);
+        assert_eq!(read_banked(&[], 2, 0, 0), 0xFF);
+    }
+
+    #[test]
+    fn test_save_data_round_trip() {
+        let mut cartridge =
+            Cartridge::from_bytes(&build_rom(0x03, 0x03, 4), RtcMode::Host).unwrap();
+        assert!(cartridge.has_battery());
+        assert!(!cartridge.is_ram_dirty());
+
+        cartridge.write_rom(0x0000, 0x0A);
+        cartridge.write_ram(0xA000, 0x42);
+        cartridge.write_rom(0x6000, 0x01);
+        cartridge.write_rom(0x4000, 0x03);
+        cartridge.write_ram(0xBFFF, 0x24);
+        assert!(cartridge.is_ram_dirty());
+
+        let save = cartridge.save_data();
+        assert!(!cartridge.is_ram_dirty());
+        assert_eq!(save.len(), 0x8000);
+        assert_eq!(save[0x0000], 0x42);
+        assert_eq!(save[0x7FFF], 0x24);
+
+        let mut restored = Cartridge::from_bytes(&build_rom(0x03, 0x03, 4), RtcMode::Host).unwrap();
+        restored.load_save_data(&save);
+        restored.write_rom(0x0000, 0x0A);
+        assert_eq!(restored.read_ram(0xA000), 0x42);
+    }
+}
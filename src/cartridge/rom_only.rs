@@ -0,0 +1,43 @@
+use super::{load_ram, read_banked, write_banked, Mbc, RAM_BANK_SIZE};
+
+//NOTE: 32 KiB of rom without any banking, some of these carts still come with up to 8 KiB of ram
+pub struct RomOnly {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+}
+
+impl RomOnly {
+    pub fn new(rom: Vec<u8>, ram: Vec<u8>) -> Self {
+        Self { rom, ram }
+    }
+}
+
+impl Mbc for RomOnly {
+    fn read_rom(&self, addr: u16) -> u8 {
+        self.rom.get(addr as usize).copied().unwrap_or(0xFF)
+    }
+
+    fn write_rom(&mut self, _addr: u16, _value: u8) {}
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        read_banked(&self.ram, RAM_BANK_SIZE, 0, (addr & 0x1FFF) as usize)
+    }
+
+    fn write_ram(&mut self, addr: u16, value: u8) {
+        write_banked(
+            &mut self.ram,
+            RAM_BANK_SIZE,
+            0,
+            (addr & 0x1FFF) as usize,
+            value,
+        )
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        self.ram.clone()
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data)
+    }
+}
@@ -0,0 +1,422 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+//NOTE: The clock crystal runs at 32768 Hz, that's exactly one second every 4194304 t-cycles of a
+//      gameboy running at normal speed.
+const T_CYCLES_PER_SECOND: usize = 4194304;
+
+//NOTE: Five 32 bit values for the running clock, five for the latched one and a 64 bit unix
+//      timestamp. Some older emulators only write a 32 bit timestamp, we read both.
+pub const RTC_SAVE_SIZE: usize = 48;
+const RTC_SAVE_SIZE_SHORT: usize = 44;
+
+const SECONDS_REGISTER: u8 = 0x08;
+const MINUTES_REGISTER: u8 = 0x09;
+const HOURS_REGISTER: u8 = 0x0A;
+const DAYS_LOW_REGISTER: u8 = 0x0B;
+const DAYS_HIGH_REGISTER: u8 = 0x0C;
+
+const HALT_FLAG: u8 = 0x40;
+const DAY_CARRY_FLAG: u8 = 0x80;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum RtcMode {
+    //NOTE: The clock only moves while the emulation runs, one second every 4194304 t-cycles
+    Emulated,
+    //NOTE: The clock follows the wall clock of the host, even while the emulator isn't running
+    Host,
+}
+
+impl RtcMode {
+    pub fn from_name(name: &str) -> Option<RtcMode> {
+        match name.to_ascii_lowercase().as_str() {
+            "emulated" => Some(RtcMode::Emulated),
+            "host" => Some(RtcMode::Host),
+            _ => None,
+        }
+    }
+}
+
+//NOTE: Seconds since the unix epoch, tests swap this out to move the time around
+pub trait HostClock {
+    fn now(&self) -> u64;
+}
+
+struct SystemClock;
+
+impl HostClock for SystemClock {
+    fn now(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0)
+    }
+}
+
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug)]
+struct ClockRegisters {
+    seconds: u8,
+    minutes: u8,
+    hours: u8,
+    days: u16,
+    halted: bool,
+    day_carry: bool,
+}
+
+impl ClockRegisters {
+    fn read(&self, register: u8) -> u8 {
+        match register {
+            SECONDS_REGISTER => self.seconds,
+            MINUTES_REGISTER => self.minutes,
+            HOURS_REGISTER => self.hours,
+            DAYS_LOW_REGISTER => self.days as u8,
+            DAYS_HIGH_REGISTER => {
+                let mut value = (self.days >> 8) as u8 & 0x01;
+
+                if self.halted {
+                    value |= HALT_FLAG;
+                }
+
+                if self.day_carry {
+                    value |= DAY_CARRY_FLAG;
+                }
+
+                value
+            }
+            _ => 0xFF,
+        }
+    }
+
+    //NOTE: The unused bits of every register aren't there, so they simply get dropped
+    fn write(&mut self, register: u8, value: u8) {
+        match register {
+            SECONDS_REGISTER => self.seconds = value & 0x3F,
+            MINUTES_REGISTER => self.minutes = value & 0x3F,
+            HOURS_REGISTER => self.hours = value & 0x1F,
+            DAYS_LOW_REGISTER => self.days = (self.days & 0x100) | value as u16,
+            DAYS_HIGH_REGISTER => {
+                self.days = (self.days & 0xFF) | ((value as u16 & 0x01) << 8);
+                self.halted = value & HALT_FLAG != 0;
+                self.day_carry = value & DAY_CARRY_FLAG != 0;
+            }
+            _ => (),
+        }
+    }
+
+    //NOTE: Every counter only carries over when it hits its regular limit. A game can write values
+    //      past that limit, those count up until the register overflows without a carry.
+    fn tick_second(&mut self) {
+        self.seconds = (self.seconds + 1) & 0x3F;
+        if self.seconds != 60 {
+            return;
+        }
+
+        self.seconds = 0;
+        self.minutes = (self.minutes + 1) & 0x3F;
+        if self.minutes != 60 {
+            return;
+        }
+
+        self.minutes = 0;
+        self.hours = (self.hours + 1) & 0x1F;
+        if self.hours != 24 {
+            return;
+        }
+
+        self.hours = 0;
+        self.days += 1;
+        if self.days == 512 {
+            self.days = 0;
+            self.day_carry = true;
+        }
+    }
+
+    fn advance(&mut self, mut seconds: u64) {
+        if self.halted {
+            return;
+        }
+
+        //NOTE: Out of range values have to go through the slow path until they wrapped around
+        while seconds > 0 && (self.seconds >= 60 || self.minutes >= 60 || self.hours >= 24) {
+            self.tick_second();
+            seconds -= 1;
+        }
+
+        let total = self.seconds as u64
+            + self.minutes as u64 * 60
+            + self.hours as u64 * 3600
+            + self.days as u64 * 86400
+            + seconds;
+
+        let days = total / 86400;
+        if days >= 512 {
+            self.day_carry = true;
+        }
+
+        self.seconds = (total % 60) as u8;
+        self.minutes = (total / 60 % 60) as u8;
+        self.hours = (total / 3600 % 24) as u8;
+        self.days = (days % 512) as u16;
+    }
+
+    fn to_save_data(self) -> [u8; 20] {
+        let mut data = [0x00; 20];
+
+        for (index, register) in (SECONDS_REGISTER..=DAYS_HIGH_REGISTER).enumerate() {
+            data[index * 4] = self.read(register);
+        }
+
+        data
+    }
+
+    fn from_save_data(data: &[u8]) -> Self {
+        let mut registers = Self::default();
+
+        for (index, register) in (SECONDS_REGISTER..=DAYS_HIGH_REGISTER).enumerate() {
+            registers.write(register, data[index * 4]);
+        }
+
+        registers
+    }
+}
+
+pub struct Rtc {
+    clock: ClockRegisters,
+    latched: ClockRegisters,
+    //NOTE: The clock gets latched by writing 0x00 followed by 0x01
+    latch_armed: bool,
+
+    mode: RtcMode,
+    host_clock: Box<dyn HostClock>,
+    last_host_time: u64,
+    t_cycles: usize,
+}
+
+impl Rtc {
+    pub fn new(mode: RtcMode) -> Self {
+        Self::with_host_clock(mode, Box::new(SystemClock))
+    }
+
+    pub fn with_host_clock(mode: RtcMode, host_clock: Box<dyn HostClock>) -> Self {
+        let last_host_time = host_clock.now();
+
+        Self {
+            clock: ClockRegisters::default(),
+            latched: ClockRegisters::default(),
+            latch_armed: false,
+
+            mode,
+            host_clock,
+            last_host_time,
+            t_cycles: 0,
+        }
+    }
+
+    pub fn tick(&mut self, t_cycles: usize) {
+        if self.mode != RtcMode::Emulated {
+            return;
+        }
+
+        self.t_cycles += t_cycles;
+        let seconds = self.t_cycles / T_CYCLES_PER_SECOND;
+        self.t_cycles %= T_CYCLES_PER_SECOND;
+
+        self.clock.advance(seconds as u64);
+    }
+
+    fn sync_host_time(&mut self) {
+        if self.mode != RtcMode::Host {
+            return;
+        }
+
+        let now = self.host_clock.now();
+        self.clock.advance(now.saturating_sub(self.last_host_time));
+        self.last_host_time = now;
+    }
+
+    pub fn write_latch(&mut self, value: u8) {
+        if self.latch_armed && value == 0x01 {
+            self.sync_host_time();
+            self.latched = self.clock;
+        }
+
+        self.latch_armed = value == 0x00;
+    }
+
+    pub fn read(&self, register: u8) -> u8 {
+        self.latched.read(register)
+    }
+
+    pub fn write(&mut self, register: u8, value: u8) {
+        self.sync_host_time();
+
+        //NOTE: Writing the seconds resets the part of the second that already went by
+        if register == SECONDS_REGISTER {
+            self.t_cycles = 0;
+        }
+
+        self.clock.write(register, value);
+    }
+
+    pub fn save_data(&self) -> Vec<u8> {
+        let mut clock = self.clock;
+        let now = self.host_clock.now();
+
+        if self.mode == RtcMode::Host {
+            clock.advance(now.saturating_sub(self.last_host_time));
+        }
+
+        let mut data = Vec::with_capacity(RTC_SAVE_SIZE);
+        data.extend_from_slice(&clock.to_save_data());
+        data.extend_from_slice(&self.latched.to_save_data());
+        data.extend_from_slice(&now.to_le_bytes());
+        data
+    }
+
+    //NOTE: In host mode the clock catches up on the time that passed since the save was written
+    pub fn load_save_data(&mut self, data: &[u8]) {
+        if data.len() < RTC_SAVE_SIZE_SHORT {
+            return;
+        }
+
+        self.clock = ClockRegisters::from_save_data(&data[0..20]);
+        self.latched = ClockRegisters::from_save_data(&data[20..40]);
+
+        let timestamp = if data.len() >= RTC_SAVE_SIZE {
+            u64::from_le_bytes(data[40..48].try_into().unwrap())
+        } else {
+            u32::from_le_bytes(data[40..44].try_into().unwrap()) as u64
+        };
+
+        self.last_host_time = timestamp;
+        self.sync_host_time();
+        self.last_host_time = self.host_clock.now();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{cell::Cell, rc::Rc};
+
+    struct FakeClock(Rc<Cell<u64>>);
+
+    impl HostClock for FakeClock {
+        fn now(&self) -> u64 {
+            self.0.get()
+        }
+    }
+
+    fn build_rtc(mode: RtcMode) -> (Rtc, Rc<Cell<u64>>) {
+        let time = Rc::new(Cell::new(1_000_000));
+        let rtc = Rtc::with_host_clock(mode, Box::new(FakeClock(time.clone())));
+
+        (rtc, time)
+    }
+
+    fn latch(rtc: &mut Rtc) {
+        rtc.write_latch(0x00);
+        rtc.write_latch(0x01);
+    }
+
+    #[test]
+    fn test_registers_only_change_when_latched() {
+        let (mut rtc, time) = build_rtc(RtcMode::Host);
+
+        time.set(time.get() + 61);
+        assert_eq!(rtc.read(SECONDS_REGISTER), 0);
+
+        //NOTE: Without the 0x00 write first nothing gets latched
+        rtc.write_latch(0x01);
+        assert_eq!(rtc.read(SECONDS_REGISTER), 0);
+
+        latch(&mut rtc);
+        assert_eq!(rtc.read(SECONDS_REGISTER), 1);
+        assert_eq!(rtc.read(MINUTES_REGISTER), 1);
+    }
+
+    #[test]
+    fn test_emulated_clock_follows_the_cycles() {
+        let (mut rtc, time) = build_rtc(RtcMode::Emulated);
+
+        time.set(time.get() + 3600);
+        rtc.tick(T_CYCLES_PER_SECOND - 1);
+        latch(&mut rtc);
+        assert_eq!(rtc.read(SECONDS_REGISTER), 0);
+
+        rtc.tick(1);
+        latch(&mut rtc);
+        assert_eq!(rtc.read(SECONDS_REGISTER), 1);
+        assert_eq!(rtc.read(HOURS_REGISTER), 0);
+    }
+
+    #[test]
+    fn test_halt_stops_the_clock() {
+        let (mut rtc, time) = build_rtc(RtcMode::Host);
+
+        rtc.write(DAYS_HIGH_REGISTER, HALT_FLAG);
+        time.set(time.get() + 100);
+        latch(&mut rtc);
+        assert_eq!(rtc.read(SECONDS_REGISTER), 0);
+        assert_eq!(rtc.read(DAYS_HIGH_REGISTER), HALT_FLAG);
+
+        rtc.write(DAYS_HIGH_REGISTER, 0x00);
+        time.set(time.get() + 5);
+        latch(&mut rtc);
+        assert_eq!(rtc.read(SECONDS_REGISTER), 5);
+    }
+
+    #[test]
+    fn test_day_counter_overflow_sets_the_carry() {
+        let (mut rtc, time) = build_rtc(RtcMode::Host);
+
+        rtc.write(HOURS_REGISTER, 23);
+        rtc.write(MINUTES_REGISTER, 59);
+        rtc.write(SECONDS_REGISTER, 59);
+        rtc.write(DAYS_LOW_REGISTER, 0xFF);
+        rtc.write(DAYS_HIGH_REGISTER, 0x01);
+
+        time.set(time.get() + 1);
+        latch(&mut rtc);
+        assert_eq!(rtc.read(DAYS_LOW_REGISTER), 0x00);
+        assert_eq!(rtc.read(DAYS_HIGH_REGISTER), DAY_CARRY_FLAG);
+
+        //NOTE: The carry stays until the game clears it
+        time.set(time.get() + 86400);
+        latch(&mut rtc);
+        assert_eq!(rtc.read(DAYS_LOW_REGISTER), 0x01);
+        assert_eq!(rtc.read(DAYS_HIGH_REGISTER), DAY_CARRY_FLAG);
+    }
+
+    #[test]
+    fn test_out_of_range_values_wrap_without_carry() {
+        let (mut rtc, time) = build_rtc(RtcMode::Host);
+
+        rtc.write(SECONDS_REGISTER, 63);
+        time.set(time.get() + 1);
+        latch(&mut rtc);
+        assert_eq!(rtc.read(SECONDS_REGISTER), 0);
+        assert_eq!(rtc.read(MINUTES_REGISTER), 0);
+    }
+
+    #[test]
+    fn test_save_data_catches_up_with_the_host_time() {
+        let (mut rtc, time) = build_rtc(RtcMode::Host);
+
+        rtc.write(MINUTES_REGISTER, 10);
+        latch(&mut rtc);
+
+        let save = rtc.save_data();
+        assert_eq!(save.len(), RTC_SAVE_SIZE);
+        assert_eq!(save[4], 10);
+        assert_eq!(save[24], 10);
+
+        time.set(time.get() + 7200);
+        let mut restored = Rtc::with_host_clock(RtcMode::Host, Box::new(FakeClock(time.clone())));
+        restored.load_save_data(&save);
+
+        assert_eq!(restored.read(MINUTES_REGISTER), 10);
+        latch(&mut restored);
+        assert_eq!(restored.read(HOURS_REGISTER), 2);
+        assert_eq!(restored.read(MINUTES_REGISTER), 10);
+    }
+}
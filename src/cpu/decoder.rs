@@ -0,0 +1,594 @@
+use std::fmt::Display;
+
+use crate::cpu::register::{RegByte, RegWord};
+
+//NOTE: This is the one place where opcodes get decoded, the cpu dispatches on these entries and the
+//      disassembler renders them. Cycles are machine cycles, `branch_cycles` is the cost of a
+//      conditional instruction when the branch is taken (equal to `cycles` for everything else).
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct InstructionDesc {
+    pub opcode: Opcode,
+    pub dest: Operand,
+    pub src: Operand,
+    pub length: u8,
+    pub cycles: u8,
+    pub branch_cycles: u8,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Operand {
+    None,
+
+    Reg8(RegByte),
+    Reg16(RegWord),
+    Sp,
+
+    Imm8,
+    SignedImm8,
+    Imm16,
+
+    Indirect(RegWord),
+    IndirectInc,
+    IndirectDec,
+    Address,
+    HighOffset,
+    HighC,
+    SpOffset,
+
+    Condition(Flag),
+    Bit(u8),
+    Vector(u8),
+}
+
+impl Operand {
+    const fn immediate_size(self) -> u8 {
+        match self {
+            Operand::Imm8 | Operand::SignedImm8 | Operand::HighOffset | Operand::SpOffset => 1,
+            Operand::Imm16 | Operand::Address => 2,
+            _ => 0,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Opcode {
+    Byte,
+    Load,
+    Add,
+    Adc,
+    Sub,
+    Sbc,
+    Inc,
+    Dec,
+    Push,
+    Pop,
+    And,
+    Xor,
+    Or,
+    Cp,
+    Rlca,
+    Rrca,
+    Rla,
+    Rra,
+    Nop,
+    Stop,
+    Jr,
+    Jp,
+    Call,
+    Ret,
+    Rst,
+    Ei,
+    Di,
+    Reti,
+    Daa,
+    Scf,
+    Cpl,
+    Ccf,
+    Halt,
+    Rlc,
+    Rrc,
+    Rl,
+    Rr,
+    Sla,
+    Sra,
+    Swap,
+    Srl,
+    Bit,
+    Res,
+    Set, //TODO: And catch fire...
+}
+
+impl Display for Opcode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Opcode::Byte => write!(f, "BYTE"),
+            Opcode::Load => write!(f, "LD  "),
+            Opcode::Add => write!(f, "ADD "),
+            Opcode::Adc => write!(f, "ADC "),
+            Opcode::Sub => write!(f, "SUB "),
+            Opcode::Sbc => write!(f, "SBC "),
+            Opcode::Inc => write!(f, "INC "),
+            Opcode::Dec => write!(f, "DEC "),
+            Opcode::Push => write!(f, "PUSH"),
+            Opcode::Pop => write!(f, "POP "),
+            Opcode::And => write!(f, "AND "),
+            Opcode::Xor => write!(f, "XOR "),
+            Opcode::Or => write!(f, "OR  "),
+            Opcode::Cp => write!(f, "CP  "),
+            Opcode::Rlca => write!(f, "RLCA"),
+            Opcode::Rrca => write!(f, "RRCA"),
+            Opcode::Rla => write!(f, "RLA "),
+            Opcode::Rra => write!(f, "RRA "),
+            Opcode::Nop => write!(f, "NOP "),
+            Opcode::Stop => write!(f, "STOP"),
+            Opcode::Jr => write!(f, "JR  "),
+            Opcode::Jp => write!(f, "JP  "),
+            Opcode::Call => write!(f, "CALL"),
+            Opcode::Ret => write!(f, "RET "),
+            Opcode::Rst => write!(f, "RST "),
+            Opcode::Ei => write!(f, "EI  "),
+            Opcode::Di => write!(f, "DI  "),
+            Opcode::Reti => write!(f, "RETI"),
+            Opcode::Daa => write!(f, "DAA "),
+            Opcode::Scf => write!(f, "SCF "),
+            Opcode::Cpl => write!(f, "CPL "),
+            Opcode::Ccf => write!(f, "CCF "),
+            Opcode::Halt => write!(f, "HALT"),
+            Opcode::Rlc => write!(f, "RLC "),
+            Opcode::Rrc => write!(f, "RRC "),
+            Opcode::Rl => write!(f, "RL  "),
+            Opcode::Rr => write!(f, "RR  "),
+            Opcode::Sla => write!(f, "SLA "),
+            Opcode::Sra => write!(f, "SRA "),
+            Opcode::Swap => write!(f, "SWAP"),
+            Opcode::Srl => write!(f, "SRL "),
+            Opcode::Bit => write!(f, "BIT "),
+            Opcode::Res => write!(f, "RES "),
+            Opcode::Set => write!(f, "SET "),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum Flag {
+    None,
+    NotZero,
+    Zero,
+    NotCarry,
+    Carry,
+}
+
+impl Flag {
+    pub const fn decode_conditional(opcode: u8) -> Self {
+        let mut y = (opcode >> 3) & 0x07;
+        let z = opcode & 0x07;
+        let x = (opcode >> 6) & 0x03;
+        let p = (opcode >> 4) & 0x03;
+
+        //TODO: Improve this check it looks pretty dumb
+        if z == 0x05
+            || z == 0x03
+            || (x == 0x00 && z == 0x00 && y == 0x03)
+            || (x == 0x03 && z == 0x01 && p == 0x00)
+        {
+            return Self::None;
+        }
+
+        if x == 0x00 && (y >= 4 && y <= 7) {
+            y -= 4;
+        }
+
+        match y {
+            0 => Self::NotZero,
+            1 => Self::Zero,
+            2 => Self::NotCarry,
+            3 => Self::Carry,
+            _ => panic!("Flag opcode value out of range !"),
+        }
+    }
+}
+
+impl Display for Flag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Flag::None => Ok(()),
+            Flag::NotZero => write!(f, "NZ"),
+            Flag::Zero => write!(f, "Z"),
+            Flag::NotCarry => write!(f, "NC"),
+            Flag::Carry => write!(f, "C"),
+        }
+    }
+}
+
+#[rustfmt::skip]
+const CYCLE_TABLE: [u8; 256] = [
+    1, 3, 2, 2, 1, 1, 2, 1, 5, 2, 2, 2, 1, 1, 2, 1,
+    1, 3, 2, 2, 1, 1, 2, 1, 3, 2, 2, 2, 1, 1, 2, 1,
+    2, 3, 2, 2, 1, 1, 2, 1, 2, 2, 2, 2, 1, 1, 2, 1,
+    2, 3, 2, 2, 3, 3, 3, 1, 2, 2, 2, 2, 1, 1, 2, 1,
+    1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
+    1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
+    1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
+    2, 2, 2, 2, 2, 2, 1, 2, 1, 1, 1, 1, 1, 1, 2, 1,
+    1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
+    1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
+    1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
+    1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
+    2, 3, 3, 4, 3, 4, 2, 4, 2, 4, 3, 0, 3, 6, 2, 4,
+    2, 3, 3, 0, 3, 4, 2, 4, 2, 4, 3, 0, 3, 0, 2, 4,
+    3, 3, 2, 0, 0, 4, 2, 4, 4, 1, 4, 0, 0, 0, 2, 4,
+    3, 3, 2, 1, 0, 4, 2, 4, 3, 2, 4, 1, 0, 0, 2, 4,
+];
+
+const REGISTER_TABLE: [Operand; 8] = [
+    Operand::Reg8(RegByte::B),
+    Operand::Reg8(RegByte::C),
+    Operand::Reg8(RegByte::D),
+    Operand::Reg8(RegByte::E),
+    Operand::Reg8(RegByte::H),
+    Operand::Reg8(RegByte::L),
+    Operand::Indirect(RegWord::Hl),
+    Operand::Reg8(RegByte::A),
+];
+
+const REGISTER_PAIR_TABLE: [Operand; 4] = [
+    Operand::Reg16(RegWord::Bc),
+    Operand::Reg16(RegWord::De),
+    Operand::Reg16(RegWord::Hl),
+    Operand::Sp,
+];
+
+//NOTE: Push and pop use AF instead of SP as their fourth register pair
+const STACK_REGISTER_PAIR_TABLE: [Operand; 4] = [
+    Operand::Reg16(RegWord::Bc),
+    Operand::Reg16(RegWord::De),
+    Operand::Reg16(RegWord::Hl),
+    Operand::Reg16(RegWord::Af),
+];
+
+const ALU_TABLE: [Opcode; 8] = [
+    Opcode::Add,
+    Opcode::Adc,
+    Opcode::Sub,
+    Opcode::Sbc,
+    Opcode::And,
+    Opcode::Xor,
+    Opcode::Or,
+    Opcode::Cp,
+];
+
+const ROTATE_TABLE: [Opcode; 8] = [
+    Opcode::Rlc,
+    Opcode::Rrc,
+    Opcode::Rl,
+    Opcode::Rr,
+    Opcode::Sla,
+    Opcode::Sra,
+    Opcode::Swap,
+    Opcode::Srl,
+];
+
+const ACCUMULATOR_TABLE: [Opcode; 8] = [
+    Opcode::Rlca,
+    Opcode::Rrca,
+    Opcode::Rla,
+    Opcode::Rra,
+    Opcode::Daa,
+    Opcode::Cpl,
+    Opcode::Scf,
+    Opcode::Ccf,
+];
+
+static UNPREFIXED_TABLE: [InstructionDesc; 256] = build_unprefixed_table();
+static PREFIXED_TABLE: [InstructionDesc; 256] = build_prefixed_table();
+
+pub fn decode(opcode: u8) -> &'static InstructionDesc {
+    &UNPREFIXED_TABLE[opcode as usize]
+}
+
+pub fn decode_prefixed(opcode: u8) -> &'static InstructionDesc {
+    &PREFIXED_TABLE[opcode as usize]
+}
+
+const fn build_unprefixed_table() -> [InstructionDesc; 256] {
+    let mut table = [instruction(Opcode::Byte, Operand::None, Operand::None); 256];
+
+    let mut opcode = 0;
+    while opcode < 256 {
+        let mut desc = decode_unprefixed(opcode as u8);
+        desc.cycles = CYCLE_TABLE[opcode];
+        desc.branch_cycles = branch_cycles(desc.opcode, desc.dest, desc.cycles);
+
+        table[opcode] = desc;
+        opcode += 1;
+    }
+
+    table
+}
+
+const fn build_prefixed_table() -> [InstructionDesc; 256] {
+    let mut table = [instruction(Opcode::Byte, Operand::None, Operand::None); 256];
+
+    let mut opcode = 0;
+    while opcode < 256 {
+        let mut desc = decode_cb_prefixed(opcode as u8);
+
+        desc.length = 2;
+        desc.cycles = match (desc.opcode, desc.src, desc.dest) {
+            (Opcode::Bit, Operand::Indirect(_), _) => 3,
+            (_, Operand::Indirect(_), _) | (_, _, Operand::Indirect(_)) => 4,
+            _ => 2,
+        };
+        desc.branch_cycles = desc.cycles;
+
+        table[opcode] = desc;
+        opcode += 1;
+    }
+
+    table
+}
+
+const fn instruction(opcode: Opcode, dest: Operand, src: Operand) -> InstructionDesc {
+    InstructionDesc {
+        opcode,
+        dest,
+        src,
+        length: 1 + dest.immediate_size() + src.immediate_size(),
+        cycles: 0,
+        branch_cycles: 0,
+    }
+}
+
+const fn branch_cycles(opcode: Opcode, dest: Operand, cycles: u8) -> u8 {
+    match (opcode, dest) {
+        (Opcode::Jr, Operand::Condition(_)) => 3,
+        (Opcode::Jp, Operand::Condition(_)) => 4,
+        (Opcode::Call, Operand::Condition(_)) => 6,
+        (Opcode::Ret, Operand::Condition(_)) => 5,
+        _ => cycles,
+    }
+}
+
+const fn conditional(opcode: u8) -> Operand {
+    Operand::Condition(Flag::decode_conditional(opcode))
+}
+
+//NOTE: The opcode gets split up into the fields xx_yyy_zzz, y is further split into pp_q
+const fn decode_unprefixed(opcode: u8) -> InstructionDesc {
+    let x = opcode >> 6;
+    let y = (opcode >> 3) & 0x07;
+    let z = opcode & 0x07;
+    let p = (y >> 1) as usize;
+    let q = y & 0x01;
+
+    let r_y = REGISTER_TABLE[y as usize];
+    let r_z = REGISTER_TABLE[z as usize];
+    let a = Operand::Reg8(RegByte::A);
+    let hl = Operand::Reg16(RegWord::Hl);
+
+    match (x, z) {
+        (0, 0) => match y {
+            0 => instruction(Opcode::Nop, Operand::None, Operand::None),
+            1 => instruction(Opcode::Load, Operand::Address, Operand::Sp),
+            2 => {
+                //NOTE: STOP is followed by a padding byte which gets skipped
+                let mut stop = instruction(Opcode::Stop, Operand::None, Operand::None);
+                stop.length = 2;
+                stop
+            }
+            3 => instruction(Opcode::Jr, Operand::None, Operand::SignedImm8),
+            _ => instruction(Opcode::Jr, conditional(opcode), Operand::SignedImm8),
+        },
+        (0, 1) if q == 0 => instruction(Opcode::Load, REGISTER_PAIR_TABLE[p], Operand::Imm16),
+        (0, 1) => instruction(Opcode::Add, hl, REGISTER_PAIR_TABLE[p]),
+        (0, 2) => {
+            let memory = match p {
+                0 => Operand::Indirect(RegWord::Bc),
+                1 => Operand::Indirect(RegWord::De),
+                2 => Operand::IndirectInc,
+                _ => Operand::IndirectDec,
+            };
+
+            if q == 0 {
+                instruction(Opcode::Load, memory, a)
+            } else {
+                instruction(Opcode::Load, a, memory)
+            }
+        }
+        (0, 3) if q == 0 => instruction(Opcode::Inc, REGISTER_PAIR_TABLE[p], Operand::None),
+        (0, 3) => instruction(Opcode::Dec, REGISTER_PAIR_TABLE[p], Operand::None),
+        (0, 4) => instruction(Opcode::Inc, r_y, Operand::None),
+        (0, 5) => instruction(Opcode::Dec, r_y, Operand::None),
+        (0, 6) => instruction(Opcode::Load, r_y, Operand::Imm8),
+        (0, _) => instruction(ACCUMULATOR_TABLE[y as usize], Operand::None, Operand::None),
+
+        (1, 6) if y == 6 => instruction(Opcode::Halt, Operand::None, Operand::None),
+        (1, _) => instruction(Opcode::Load, r_y, r_z),
+
+        (2, _) => alu_instruction(ALU_TABLE[y as usize], r_z),
+
+        (3, 0) => match y {
+            0..=3 => instruction(Opcode::Ret, conditional(opcode), Operand::None),
+            4 => instruction(Opcode::Load, Operand::HighOffset, a),
+            5 => instruction(Opcode::Add, Operand::Sp, Operand::SignedImm8),
+            6 => instruction(Opcode::Load, a, Operand::HighOffset),
+            _ => instruction(Opcode::Load, hl, Operand::SpOffset),
+        },
+        (3, 1) if q == 0 => instruction(Opcode::Pop, STACK_REGISTER_PAIR_TABLE[p], Operand::None),
+        (3, 1) => match p {
+            0 => instruction(Opcode::Ret, Operand::None, Operand::None),
+            1 => instruction(Opcode::Reti, Operand::None, Operand::None),
+            2 => instruction(Opcode::Jp, Operand::None, Operand::Indirect(RegWord::Hl)),
+            _ => instruction(Opcode::Load, Operand::Sp, hl),
+        },
+        (3, 2) => match y {
+            0..=3 => instruction(Opcode::Jp, conditional(opcode), Operand::Imm16),
+            4 => instruction(Opcode::Load, Operand::HighC, a),
+            5 => instruction(Opcode::Load, Operand::Address, a),
+            6 => instruction(Opcode::Load, a, Operand::HighC),
+            _ => instruction(Opcode::Load, a, Operand::Address),
+        },
+        (3, 3) => match y {
+            0 => instruction(Opcode::Jp, Operand::None, Operand::Imm16),
+            6 => instruction(Opcode::Di, Operand::None, Operand::None),
+            7 => instruction(Opcode::Ei, Operand::None, Operand::None),
+            //NOTE: 0xCB is the prefix byte, everything else here is an illegal opcode
+            _ => instruction(Opcode::Byte, Operand::None, Operand::None),
+        },
+        (3, 4) if y < 4 => instruction(Opcode::Call, conditional(opcode), Operand::Imm16),
+        (3, 5) if q == 0 => instruction(Opcode::Push, Operand::None, STACK_REGISTER_PAIR_TABLE[p]),
+        (3, 5) if p == 0 => instruction(Opcode::Call, Operand::None, Operand::Imm16),
+        (3, 6) => alu_instruction(ALU_TABLE[y as usize], Operand::Imm8),
+        (3, 7) => instruction(Opcode::Rst, Operand::None, Operand::Vector(y * 8)),
+        _ => instruction(Opcode::Byte, Operand::None, Operand::None),
+    }
+}
+
+const fn alu_instruction(opcode: Opcode, src: Operand) -> InstructionDesc {
+    match opcode {
+        //NOTE: Only the instructions involving a carry name their accumulator in the mnemonic
+        Opcode::Add | Opcode::Adc | Opcode::Sbc => {
+            instruction(opcode, Operand::Reg8(RegByte::A), src)
+        }
+        _ => instruction(opcode, Operand::None, src),
+    }
+}
+
+const fn decode_cb_prefixed(opcode: u8) -> InstructionDesc {
+    let x = opcode >> 6;
+    let y = (opcode >> 3) & 0x07;
+    let r_z = REGISTER_TABLE[(opcode & 0x07) as usize];
+
+    match x {
+        0 => instruction(ROTATE_TABLE[y as usize], r_z, Operand::None),
+        1 => instruction(Opcode::Bit, Operand::Bit(y), r_z),
+        2 => instruction(Opcode::Res, Operand::Bit(y), r_z),
+        _ => instruction(Opcode::Set, Operand::Bit(y), r_z),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_conditional_flags_decoding() {
+        let result_jp_nz = Flag::decode_conditional(0xC2);
+        let result_jp_nc = Flag::decode_conditional(0xD2);
+        let result_jp_z = Flag::decode_conditional(0xCC);
+        let result_jp_c = Flag::decode_conditional(0xDC);
+        let result_jp = Flag::decode_conditional(0xC3);
+
+        assert_eq!(result_jp_nz, Flag::NotZero);
+        assert_eq!(result_jp_nc, Flag::NotCarry);
+        assert_eq!(result_jp_z, Flag::Zero);
+        assert_eq!(result_jp_c, Flag::Carry);
+        assert_eq!(result_jp, Flag::None);
+
+        let result_jr_nz = Flag::decode_conditional(0x20);
+        let result_jr_nc = Flag::decode_conditional(0x30);
+        let result_jr_z = Flag::decode_conditional(0x28);
+        let result_jr_c = Flag::decode_conditional(0x38);
+        let result_jr = Flag::decode_conditional(0x18);
+
+        assert_eq!(result_jr_nz, Flag::NotZero);
+        assert_eq!(result_jr_nc, Flag::NotCarry);
+        assert_eq!(result_jr_z, Flag::Zero);
+        assert_eq!(result_jr_c, Flag::Carry);
+        assert_eq!(result_jr, Flag::None);
+
+        let result_ret_nz = Flag::decode_conditional(0xC0);
+        let result_ret_nc = Flag::decode_conditional(0xD0);
+        let result_ret_z = Flag::decode_conditional(0xC8);
+        let result_ret_c = Flag::decode_conditional(0xD8);
+        let result_ret = Flag::decode_conditional(0xC9);
+
+        assert_eq!(result_ret_nz, Flag::NotZero);
+        assert_eq!(result_ret_nc, Flag::NotCarry);
+        assert_eq!(result_ret_z, Flag::Zero);
+        assert_eq!(result_ret_c, Flag::Carry);
+        assert_eq!(result_ret, Flag::None);
+
+        let result_call_nz = Flag::decode_conditional(0xC4);
+        let result_call_nc = Flag::decode_conditional(0xD4);
+        let result_call_z = Flag::decode_conditional(0xCC);
+        let result_call_c = Flag::decode_conditional(0xDC);
+        let result_call = Flag::decode_conditional(0xCD);
+
+        assert_eq!(result_call_nz, Flag::NotZero);
+        assert_eq!(result_call_nc, Flag::NotCarry);
+        assert_eq!(result_call_z, Flag::Zero);
+        assert_eq!(result_call_c, Flag::Carry);
+        assert_eq!(result_call, Flag::None);
+    }
+
+    #[test]
+    fn test_decode_lengths() {
+        assert_eq!(decode(0x00).length, 1);
+        assert_eq!(decode(0x01).length, 3);
+        assert_eq!(decode(0x06).length, 2);
+        assert_eq!(decode(0x08).length, 3);
+        assert_eq!(decode(0x10).length, 2);
+        assert_eq!(decode(0x18).length, 2);
+        assert_eq!(decode(0xC3).length, 3);
+        assert_eq!(decode(0xE0).length, 2);
+        assert_eq!(decode(0xE2).length, 1);
+        assert_eq!(decode(0xF8).length, 2);
+        assert_eq!(decode(0xFA).length, 3);
+
+        for opcode in 0..=0xFF {
+            assert_eq!(decode_prefixed(opcode).length, 2);
+        }
+    }
+
+    #[test]
+    fn test_decode_branch_cycles() {
+        let jr_nz = decode(0x20);
+        assert_eq!(jr_nz.dest, Operand::Condition(Flag::NotZero));
+        assert_eq!((jr_nz.cycles, jr_nz.branch_cycles), (2, 3));
+
+        let ret_c = decode(0xD8);
+        assert_eq!((ret_c.cycles, ret_c.branch_cycles), (2, 5));
+
+        let call_z = decode(0xCC);
+        assert_eq!((call_z.cycles, call_z.branch_cycles), (3, 6));
+
+        let jp = decode(0xC3);
+        assert_eq!((jp.cycles, jp.branch_cycles), (4, 4));
+    }
+
+    #[test]
+    fn test_decode_prefixed() {
+        let bit = decode_prefixed(0x7E);
+        assert_eq!(bit.opcode, Opcode::Bit);
+        assert_eq!(bit.dest, Operand::Bit(7));
+        assert_eq!(bit.src, Operand::Indirect(RegWord::Hl));
+        assert_eq!(bit.cycles, 3);
+
+        let set = decode_prefixed(0xC6);
+        assert_eq!(set.opcode, Opcode::Set);
+        assert_eq!(set.cycles, 4);
+
+        let swap = decode_prefixed(0x37);
+        assert_eq!(swap.opcode, Opcode::Swap);
+        assert_eq!(swap.dest, Operand::Reg8(RegByte::A));
+        assert_eq!(swap.cycles, 2);
+    }
+
+    #[test]
+    fn test_illegal_opcodes() {
+        let illegal_opcodes = [
+            0xD3, 0xDB, 0xDD, 0xE3, 0xE4, 0xEB, 0xEC, 0xED, 0xF4, 0xFC, 0xFD,
+        ];
+
+        for opcode in 0..=0xFF {
+            let is_byte = decode(opcode).opcode == Opcode::Byte;
+            assert_eq!(
+                is_byte,
+                illegal_opcodes.contains(&opcode) || opcode == 0xCB,
+                "opcode {:02X}",
+                opcode
+            );
+        }
+    }
+}
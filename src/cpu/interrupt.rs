@@ -0,0 +1,74 @@
+pub const INTERRUPT_FLAG_REGISTER: u16 = 0xFF0F;
+pub const INTERRUPT_ENABLE_REGISTER: u16 = 0xFFFF;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Interrupt {
+    VBlank,
+    LcdStat,
+    Timer,
+    Serial,
+    Joypad,
+}
+
+impl Interrupt {
+    //NOTE: The order of this table is the hardware priority, the lowest bit always wins !
+    const PRIORITY_TABLE: [Interrupt; 5] = [
+        Interrupt::VBlank,
+        Interrupt::LcdStat,
+        Interrupt::Timer,
+        Interrupt::Serial,
+        Interrupt::Joypad,
+    ];
+
+    pub fn bit_index(self) -> u8 {
+        match self {
+            Interrupt::VBlank => 0,
+            Interrupt::LcdStat => 1,
+            Interrupt::Timer => 2,
+            Interrupt::Serial => 3,
+            Interrupt::Joypad => 4,
+        }
+    }
+
+    pub fn mask(self) -> u8 {
+        0x01 << self.bit_index()
+    }
+
+    pub fn vector(self) -> u16 {
+        //NOTE: Bit Index * 8 + 0x40 == Target Interrupt Address !
+        0x40 + (self.bit_index() as u16) * 8
+    }
+
+    pub fn highest_priority(pending: u8) -> Option<Interrupt> {
+        Self::PRIORITY_TABLE
+            .iter()
+            .copied()
+            .find(|interrupt| pending & interrupt.mask() != 0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_interrupt_vectors() {
+        assert_eq!(Interrupt::VBlank.vector(), 0x40);
+        assert_eq!(Interrupt::LcdStat.vector(), 0x48);
+        assert_eq!(Interrupt::Timer.vector(), 0x50);
+        assert_eq!(Interrupt::Serial.vector(), 0x58);
+        assert_eq!(Interrupt::Joypad.vector(), 0x60);
+    }
+
+    #[test]
+    fn test_interrupt_priority() {
+        assert_eq!(Interrupt::highest_priority(0x00), None);
+        assert_eq!(Interrupt::highest_priority(0x1F), Some(Interrupt::VBlank));
+        assert_eq!(Interrupt::highest_priority(0x1E), Some(Interrupt::LcdStat));
+        assert_eq!(Interrupt::highest_priority(0x1C), Some(Interrupt::Timer));
+        assert_eq!(Interrupt::highest_priority(0x18), Some(Interrupt::Serial));
+        assert_eq!(Interrupt::highest_priority(0x10), Some(Interrupt::Joypad));
+        //NOTE: Only the lower five bits are wired up to an interrupt source
+        assert_eq!(Interrupt::highest_priority(0xE0), None);
+    }
+}
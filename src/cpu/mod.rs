@@ -1,16 +1,45 @@
+use std::fmt::Display;
+
 mod alu;
+pub mod decoder;
+pub mod interrupt;
 pub mod register;
 pub mod timer;
 
 use crate::{
     cpu::register::{RegByte, RegWord},
-    memory::Memory,
+    joypad::JOYPAD_REGISTER,
+    memory::{Memory, DIV_CLOCK_REGISTER, HEADER_CHECKSUM_ADDRESS},
+    model::Model,
 };
 
 use self::alu::FlagState;
+use self::decoder::{Flag, InstructionDesc, Opcode, Operand};
+use self::interrupt::{Interrupt, INTERRUPT_ENABLE_REGISTER, INTERRUPT_FLAG_REGISTER};
 use self::register::Registers;
 use self::timer::TimerController;
 
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum CpuError {
+    IllegalOpcode { pc: u16, opcode: u8 },
+    LockedUp { pc: u16, opcode: u8 },
+}
+
+impl Display for CpuError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CpuError::IllegalOpcode { pc, opcode } => {
+                write!(f, "Illegal opcode {:02X} at {:04X}", opcode, pc)
+            }
+            CpuError::LockedUp { pc, opcode } => write!(
+                f,
+                "Cpu is locked up by the illegal opcode {:02X} at {:04X}",
+                opcode, pc
+            ),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Cpu {
     pub regs: Registers,
@@ -18,101 +47,101 @@ pub struct Cpu {
     pub pc: u16,
     pub machine_cycles: usize,
     halt_mode_enabled: bool,
+    halt_bug_triggered: bool,
+    stop_mode_enabled: bool,
+    //NOTE: Executing an illegal opcode hangs the cpu until the next power cycle
+    locked_up_by: Option<(u16, u8)>,
 
     interrupts_enabled: bool,
+    //NOTE: EI only sets the master enable after the instruction following it has been executed
+    interrupts_enable_scheduled: bool,
 }
 
 impl Cpu {
-    fn new(a: u8, b: u8, c: u8, d: u8, e: u8, f: u8, h: u8, l: u8, sp: u16, pc: u16) -> Self {
+    fn new(a: u8, f: u8, b: u8, c: u8, d: u8, e: u8, h: u8, l: u8, sp: u16, pc: u16) -> Self {
         Self {
-            regs: Registers::new(a, b, c, d, e, f, h, l),
+            regs: Registers::new(a, f, b, c, d, e, h, l),
             sp,
             pc,
             machine_cycles: 0,
             halt_mode_enabled: false,
+            halt_bug_triggered: false,
+            stop_mode_enabled: false,
+            locked_up_by: None,
 
             interrupts_enabled: false,
+            interrupts_enable_scheduled: false,
         }
     }
 }
 
 impl Default for Cpu {
     fn default() -> Self {
-        Self {
-            regs: Registers::new(0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00),
-            sp: 0xFFFE,
-            pc: 0x0101,
-            machine_cycles: 0,
-            halt_mode_enabled: false,
+        let [a, f, b, c, d, e, h, l] = Model::Dmg.post_boot_registers(0xFF, false);
 
-            interrupts_enabled: false,
-        }
+        Self::new(a, f, b, c, d, e, h, l, 0xFFFE, 0x0100)
+    }
+}
+
+impl Cpu {
+    //NOTE: The state the boot rom leaves behind right before it jumps to the cartridge entry point
+    pub fn post_boot(model: Model, memory: &Memory) -> Self {
+        let header_checksum = memory.read(HEADER_CHECKSUM_ADDRESS);
+        let [a, f, b, c, d, e, h, l] =
+            model.post_boot_registers(header_checksum, memory.is_cgb_mode());
+
+        Self::new(a, f, b, c, d, e, h, l, 0xFFFE, 0x0100)
+    }
+
+    //NOTE: Used when a real boot rom gets executed, it sets up everything on its own
+    pub fn power_on() -> Self {
+        Self::new(
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x0000, 0x0000,
+        )
     }
 }
 
 //TODO: The entire cpu codebase is a mess right now and is in dire need of a refactor !
 impl Cpu {
     fn handle_interrupts(&mut self, mem: &mut Memory) {
-        let if_table = mem.read(0xFF0F);
-        let ie_table = mem.read(0xFFFF);
-
-        //NOTE: Masking out all possible interrupts in both tables
-        let vblank_if = if_table & 0x01;
-        let lcdstat_if = (if_table >> 1) & 0x01;
-        let timer_if = (if_table >> 2) & 0x01;
-        let serial_if = (if_table >> 3) & 0x01;
-        let joypad_if = (if_table >> 4) & 0x01;
-
-        let vblank_ie = ie_table & 0x01;
-        let lcdstat_ie = (ie_table >> 1) & 0x01;
-        let timer_ie = (ie_table >> 2) & 0x01;
-        let serial_ie = (ie_table >> 3) & 0x01;
-        let joypad_ie = (ie_table >> 4) & 0x01;
-
-        if if_table != 0 && ie_table != 0 && !self.interrupts_enabled {
+        let if_table = mem.read(INTERRUPT_FLAG_REGISTER);
+        let ie_table = mem.read(INTERRUPT_ENABLE_REGISTER);
+
+        //NOTE: Only requests which are also enabled in the IE table are considered pending
+        let pending = if_table & ie_table & 0x1F;
+
+        if pending == 0x00 {
+            return;
+        }
+
+        if !self.interrupts_enabled {
             self.halt_mode_enabled = false;
             return;
         }
 
-        if self.interrupts_enabled {
+        //NOTE: Only one interrupt gets serviced per dispatch, the others stay pending in IF
+        //      until the handler of the current one returns.
+        if let Some(interrupt) = Interrupt::highest_priority(pending) {
             self.advance_clock(2);
 
-            if vblank_ie == 0x01 && vblank_if == 0x01 {
-                todo!();
-            }
-
-            if lcdstat_ie == 0x01 && lcdstat_if == 0x01 {
-                todo!();
-            }
-
-            if timer_if == 0x01 && timer_ie == 0x01 {
-                //NOTE: Bit Index * 2 + 64 == Target Interrupt Address !
-                let jump_addr = (2 * 8) + 64;
-
-                let lo_byte = self.pc as u8;
-                let hi_byte = (self.pc >> 8) as u8;
-                self.advance_clock(2);
-
-                self.sp -= 1;
-                mem.write(self.sp, hi_byte);
-                self.sp -= 1;
-                mem.write(self.sp, lo_byte);
-
-                let if_table = if_table & !(0x01 << 2);
-                mem.write(0xFF0F, if_table);
+            let lo_byte = self.pc as u8;
+            let hi_byte = (self.pc >> 8) as u8;
 
-                self.pc = jump_addr;
-                self.advance_clock(1);
-                self.halt_mode_enabled = false;
-            }
+            self.sp = self.sp.wrapping_sub(1);
+            self.write_byte(mem, self.sp, hi_byte);
+            self.sp = self.sp.wrapping_sub(1);
+            self.write_byte(mem, self.sp, lo_byte);
 
-            if serial_ie == 0x01 && serial_if == 0x01 {
-                todo!();
-            }
+            //NOTE: The components keep running during the push, whatever they requested meanwhile
+            //      stays in IF
+            let if_table = mem.read(INTERRUPT_FLAG_REGISTER);
+            mem.write(INTERRUPT_FLAG_REGISTER, if_table & !interrupt.mask());
 
-            if joypad_ie == 0x01 && joypad_if == 0x01 {
-                todo!();
-            }
+            //NOTE: The handler runs with interrupts disabled until it executes EI or RETI
+            self.interrupts_enabled = false;
+            self.pc = interrupt.vector();
+            self.advance_clock(1);
+            self.halt_mode_enabled = false;
         }
     }
 
@@ -130,612 +159,366 @@ impl Cpu {
         }
     }
 
-    pub fn cycle(&mut self, memory: &mut Memory) {
+    pub fn cycle(&mut self, memory: &mut Memory) -> Result<(), CpuError> {
+        if let Some((pc, opcode)) = self.locked_up_by {
+            return Err(CpuError::LockedUp { pc, opcode });
+        }
+
+        if self.stop_mode_enabled {
+            //NOTE: The system clock is stopped, nothing but a low joypad line wakes the cpu up. The
+            //      cpu still counts its machine cycles, DIV and the lcd stay frozen meanwhile.
+            if memory.read(JOYPAD_REGISTER) & 0x0F == 0x0F {
+                self.advance_clock(1);
+                memory.skip_stopped(self.machine_cycles);
+                return Ok(());
+            }
+
+            self.stop_mode_enabled = false;
+        }
+
+        let enable_interrupts = self.interrupts_enable_scheduled;
+
         self.handle_interrupts(memory);
 
         if (!self.halt_mode_enabled) {
-            let opcode = self.fetch_byte(memory);
+            let opcode_pc = self.pc;
+            let opcode = self.fetch_opcode(memory);
 
-            if opcode == 0xCB {
+            let desc = if opcode == 0xCB {
                 let opcode = self.fetch_byte(memory);
-
-                match opcode {
-                    0x00 => self.opcode_rlc(RegByte::B),
-                    0x01 => self.opcode_rlc(RegByte::C),
-                    0x02 => self.opcode_rlc(RegByte::D),
-                    0x03 => self.opcode_rlc(RegByte::E),
-                    0x04 => self.opcode_rlc(RegByte::H),
-                    0x05 => self.opcode_rlc(RegByte::L),
-                    0x06 => self.opcode_rlc_memory(memory, RegWord::Hl),
-                    0x07 => self.opcode_rlc(RegByte::A),
-                    0x08 => self.opcode_rrc(RegByte::B),
-                    0x09 => self.opcode_rrc(RegByte::C),
-                    0x0A => self.opcode_rrc(RegByte::D),
-                    0x0B => self.opcode_rrc(RegByte::E),
-                    0x0C => self.opcode_rrc(RegByte::H),
-                    0x0D => self.opcode_rrc(RegByte::L),
-                    0x0E => self.opcode_rrc_memory(memory, RegWord::Hl),
-                    0x0F => self.opcode_rrc(RegByte::A),
-                    0x10 => self.opcode_rl(RegByte::B),
-                    0x11 => self.opcode_rl(RegByte::C),
-                    0x12 => self.opcode_rl(RegByte::D),
-                    0x13 => self.opcode_rl(RegByte::E),
-                    0x14 => self.opcode_rl(RegByte::H),
-                    0x15 => self.opcode_rl(RegByte::L),
-                    0x16 => self.opcode_rl_memory(memory, RegWord::Hl),
-                    0x17 => self.opcode_rl(RegByte::A),
-                    0x18 => self.opcode_rr(RegByte::B),
-                    0x19 => self.opcode_rr(RegByte::C),
-                    0x1A => self.opcode_rr(RegByte::D),
-                    0x1B => self.opcode_rr(RegByte::E),
-                    0x1C => self.opcode_rr(RegByte::H),
-                    0x1D => self.opcode_rr(RegByte::L),
-                    0x1E => self.opcode_rr_memory(memory, RegWord::Hl),
-                    0x1F => self.opcode_rr(RegByte::A),
-                    0x20 => self.opcode_sla(RegByte::B),
-                    0x21 => self.opcode_sla(RegByte::C),
-                    0x22 => self.opcode_sla(RegByte::D),
-                    0x23 => self.opcode_sla(RegByte::E),
-                    0x24 => self.opcode_sla(RegByte::H),
-                    0x25 => self.opcode_sla(RegByte::L),
-                    0x26 => self.opcode_sla_memory(memory, RegWord::Hl),
-                    0x27 => self.opcode_sla(RegByte::A),
-                    0x28 => self.opcode_sra(RegByte::B),
-                    0x29 => self.opcode_sra(RegByte::C),
-                    0x2A => self.opcode_sra(RegByte::D),
-                    0x2B => self.opcode_sra(RegByte::E),
-                    0x2C => self.opcode_sra(RegByte::H),
-                    0x2D => self.opcode_sra(RegByte::L),
-                    0x2E => self.opcode_sra_memory(memory, RegWord::Hl),
-                    0x2F => self.opcode_sra(RegByte::A),
-
-                    0x30 => self.opcode_swap_register(RegByte::B),
-                    0x31 => self.opcode_swap_register(RegByte::C),
-                    0x32 => self.opcode_swap_register(RegByte::D),
-                    0x33 => self.opcode_swap_register(RegByte::E),
-                    0x34 => self.opcode_swap_register(RegByte::H),
-                    0x35 => self.opcode_swap_register(RegByte::L),
-                    0x36 => self.opcode_swap_memory(memory, RegWord::Hl),
-                    0x37 => self.opcode_swap_register(RegByte::A),
-
-                    0x38 => self.opcode_srl(RegByte::B),
-                    0x39 => self.opcode_srl(RegByte::C),
-                    0x3A => self.opcode_srl(RegByte::D),
-                    0x3B => self.opcode_srl(RegByte::E),
-                    0x3C => self.opcode_srl(RegByte::H),
-                    0x3D => self.opcode_srl(RegByte::L),
-                    0x3E => self.opcode_srl_memory(memory, RegWord::Hl),
-                    0x3F => self.opcode_srl(RegByte::A),
-
-                    0x40 => self.opcode_bit(RegByte::B, 0),
-                    0x41 => self.opcode_bit(RegByte::C, 0),
-                    0x42 => self.opcode_bit(RegByte::D, 0),
-                    0x43 => self.opcode_bit(RegByte::E, 0),
-                    0x44 => self.opcode_bit(RegByte::H, 0),
-                    0x45 => self.opcode_bit(RegByte::L, 0),
-                    0x46 => self.opcode_bit_memory(memory, RegWord::Hl, 0),
-                    0x47 => self.opcode_bit(RegByte::A, 0),
-
-                    0x48 => self.opcode_bit(RegByte::B, 1),
-                    0x49 => self.opcode_bit(RegByte::C, 1),
-                    0x4A => self.opcode_bit(RegByte::D, 1),
-                    0x4B => self.opcode_bit(RegByte::E, 1),
-                    0x4C => self.opcode_bit(RegByte::H, 1),
-                    0x4D => self.opcode_bit(RegByte::L, 1),
-                    0x4E => self.opcode_bit_memory(memory, RegWord::Hl, 1),
-                    0x4F => self.opcode_bit(RegByte::A, 1),
-
-                    0x50 => self.opcode_bit(RegByte::B, 2),
-                    0x51 => self.opcode_bit(RegByte::C, 2),
-                    0x52 => self.opcode_bit(RegByte::D, 2),
-                    0x53 => self.opcode_bit(RegByte::E, 2),
-                    0x54 => self.opcode_bit(RegByte::H, 2),
-                    0x55 => self.opcode_bit(RegByte::L, 2),
-                    0x56 => self.opcode_bit_memory(memory, RegWord::Hl, 2),
-                    0x57 => self.opcode_bit(RegByte::A, 2),
-
-                    0x58 => self.opcode_bit(RegByte::B, 3),
-                    0x59 => self.opcode_bit(RegByte::C, 3),
-                    0x5A => self.opcode_bit(RegByte::D, 3),
-                    0x5B => self.opcode_bit(RegByte::E, 3),
-                    0x5C => self.opcode_bit(RegByte::H, 3),
-                    0x5D => self.opcode_bit(RegByte::L, 3),
-                    0x5E => self.opcode_bit_memory(memory, RegWord::Hl, 3),
-                    0x5F => self.opcode_bit(RegByte::A, 3),
-
-                    0x60 => self.opcode_bit(RegByte::B, 4),
-                    0x61 => self.opcode_bit(RegByte::C, 4),
-                    0x62 => self.opcode_bit(RegByte::D, 4),
-                    0x63 => self.opcode_bit(RegByte::E, 4),
-                    0x64 => self.opcode_bit(RegByte::H, 4),
-                    0x65 => self.opcode_bit(RegByte::L, 4),
-                    0x66 => self.opcode_bit_memory(memory, RegWord::Hl, 4),
-                    0x67 => self.opcode_bit(RegByte::A, 4),
-
-                    0x68 => self.opcode_bit(RegByte::B, 5),
-                    0x69 => self.opcode_bit(RegByte::C, 5),
-                    0x6A => self.opcode_bit(RegByte::D, 5),
-                    0x6B => self.opcode_bit(RegByte::E, 5),
-                    0x6C => self.opcode_bit(RegByte::H, 5),
-                    0x6D => self.opcode_bit(RegByte::L, 5),
-                    0x6E => self.opcode_bit_memory(memory, RegWord::Hl, 5),
-                    0x6F => self.opcode_bit(RegByte::A, 5),
-
-                    0x70 => self.opcode_bit(RegByte::B, 6),
-                    0x71 => self.opcode_bit(RegByte::C, 6),
-                    0x72 => self.opcode_bit(RegByte::D, 6),
-                    0x73 => self.opcode_bit(RegByte::E, 6),
-                    0x74 => self.opcode_bit(RegByte::H, 6),
-                    0x75 => self.opcode_bit(RegByte::L, 6),
-                    0x76 => self.opcode_bit_memory(memory, RegWord::Hl, 6),
-                    0x77 => self.opcode_bit(RegByte::A, 6),
-
-                    0x78 => self.opcode_bit(RegByte::B, 7),
-                    0x79 => self.opcode_bit(RegByte::C, 7),
-                    0x7A => self.opcode_bit(RegByte::D, 7),
-                    0x7B => self.opcode_bit(RegByte::E, 7),
-                    0x7C => self.opcode_bit(RegByte::H, 7),
-                    0x7D => self.opcode_bit(RegByte::L, 7),
-                    0x7E => self.opcode_bit_memory(memory, RegWord::Hl, 7),
-                    0x7F => self.opcode_bit(RegByte::A, 7),
-
-                    0x80 => self.opcode_res(RegByte::B, 0),
-                    0x81 => self.opcode_res(RegByte::C, 0),
-                    0x82 => self.opcode_res(RegByte::D, 0),
-                    0x83 => self.opcode_res(RegByte::E, 0),
-                    0x84 => self.opcode_res(RegByte::H, 0),
-                    0x85 => self.opcode_res(RegByte::L, 0),
-                    0x86 => self.opcode_res_memory(memory, RegWord::Hl, 0),
-                    0x87 => self.opcode_res(RegByte::A, 0),
-
-                    0x88 => self.opcode_res(RegByte::B, 1),
-                    0x89 => self.opcode_res(RegByte::C, 1),
-                    0x8A => self.opcode_res(RegByte::D, 1),
-                    0x8B => self.opcode_res(RegByte::E, 1),
-                    0x8C => self.opcode_res(RegByte::H, 1),
-                    0x8D => self.opcode_res(RegByte::L, 1),
-                    0x8E => self.opcode_res_memory(memory, RegWord::Hl, 1),
-                    0x8F => self.opcode_res(RegByte::A, 1),
-
-                    0x90 => self.opcode_res(RegByte::B, 2),
-                    0x91 => self.opcode_res(RegByte::C, 2),
-                    0x92 => self.opcode_res(RegByte::D, 2),
-                    0x93 => self.opcode_res(RegByte::E, 2),
-                    0x94 => self.opcode_res(RegByte::H, 2),
-                    0x95 => self.opcode_res(RegByte::L, 2),
-                    0x96 => self.opcode_res_memory(memory, RegWord::Hl, 2),
-                    0x97 => self.opcode_res(RegByte::A, 2),
-
-                    0x98 => self.opcode_res(RegByte::B, 3),
-                    0x99 => self.opcode_res(RegByte::C, 3),
-                    0x9A => self.opcode_res(RegByte::D, 3),
-                    0x9B => self.opcode_res(RegByte::E, 3),
-                    0x9C => self.opcode_res(RegByte::H, 3),
-                    0x9D => self.opcode_res(RegByte::L, 3),
-                    0x9E => self.opcode_res_memory(memory, RegWord::Hl, 3),
-                    0x9F => self.opcode_res(RegByte::A, 3),
-
-                    0xA0 => self.opcode_res(RegByte::B, 4),
-                    0xA1 => self.opcode_res(RegByte::C, 4),
-                    0xA2 => self.opcode_res(RegByte::D, 4),
-                    0xA3 => self.opcode_res(RegByte::E, 4),
-                    0xA4 => self.opcode_res(RegByte::H, 4),
-                    0xA5 => self.opcode_res(RegByte::L, 4),
-                    0xA6 => self.opcode_res_memory(memory, RegWord::Hl, 4),
-                    0xA7 => self.opcode_res(RegByte::A, 4),
-
-                    0xA8 => self.opcode_res(RegByte::B, 5),
-                    0xA9 => self.opcode_res(RegByte::C, 5),
-                    0xAA => self.opcode_res(RegByte::D, 5),
-                    0xAB => self.opcode_res(RegByte::E, 5),
-                    0xAC => self.opcode_res(RegByte::H, 5),
-                    0xAD => self.opcode_res(RegByte::L, 5),
-                    0xAE => self.opcode_res_memory(memory, RegWord::Hl, 5),
-                    0xAF => self.opcode_res(RegByte::A, 5),
-
-                    0xB0 => self.opcode_res(RegByte::B, 6),
-                    0xB1 => self.opcode_res(RegByte::C, 6),
-                    0xB2 => self.opcode_res(RegByte::D, 6),
-                    0xB3 => self.opcode_res(RegByte::E, 6),
-                    0xB4 => self.opcode_res(RegByte::H, 6),
-                    0xB5 => self.opcode_res(RegByte::L, 6),
-                    0xB6 => self.opcode_res_memory(memory, RegWord::Hl, 6),
-                    0xB7 => self.opcode_res(RegByte::A, 6),
-
-                    0xB8 => self.opcode_res(RegByte::B, 7),
-                    0xB9 => self.opcode_res(RegByte::C, 7),
-                    0xBA => self.opcode_res(RegByte::D, 7),
-                    0xBB => self.opcode_res(RegByte::E, 7),
-                    0xBC => self.opcode_res(RegByte::H, 7),
-                    0xBD => self.opcode_res(RegByte::L, 7),
-                    0xBE => self.opcode_res_memory(memory, RegWord::Hl, 7),
-                    0xBF => self.opcode_res(RegByte::A, 7),
-
-                    0xC0 => self.opcode_set(RegByte::B, 0),
-                    0xC1 => self.opcode_set(RegByte::C, 0),
-                    0xC2 => self.opcode_set(RegByte::D, 0),
-                    0xC3 => self.opcode_set(RegByte::E, 0),
-                    0xC4 => self.opcode_set(RegByte::H, 0),
-                    0xC5 => self.opcode_set(RegByte::L, 0),
-                    0xC6 => self.opcode_set_memory(memory, RegWord::Hl, 0),
-                    0xC7 => self.opcode_set(RegByte::A, 0),
-
-                    0xC8 => self.opcode_set(RegByte::B, 1),
-                    0xC9 => self.opcode_set(RegByte::C, 1),
-                    0xCA => self.opcode_set(RegByte::D, 1),
-                    0xCB => self.opcode_set(RegByte::E, 1),
-                    0xCC => self.opcode_set(RegByte::H, 1),
-                    0xCD => self.opcode_set(RegByte::L, 1),
-                    0xCE => self.opcode_set_memory(memory, RegWord::Hl, 1),
-                    0xCF => self.opcode_set(RegByte::A, 1),
-
-                    0xD0 => self.opcode_set(RegByte::B, 2),
-                    0xD1 => self.opcode_set(RegByte::C, 2),
-                    0xD2 => self.opcode_set(RegByte::D, 2),
-                    0xD3 => self.opcode_set(RegByte::E, 2),
-                    0xD4 => self.opcode_set(RegByte::H, 2),
-                    0xD5 => self.opcode_set(RegByte::L, 2),
-                    0xD6 => self.opcode_set_memory(memory, RegWord::Hl, 2),
-                    0xD7 => self.opcode_set(RegByte::A, 2),
-
-                    0xD8 => self.opcode_set(RegByte::B, 3),
-                    0xD9 => self.opcode_set(RegByte::C, 3),
-                    0xDA => self.opcode_set(RegByte::D, 3),
-                    0xDB => self.opcode_set(RegByte::E, 3),
-                    0xDC => self.opcode_set(RegByte::H, 3),
-                    0xDD => self.opcode_set(RegByte::L, 3),
-                    0xDE => self.opcode_set_memory(memory, RegWord::Hl, 3),
-                    0xDF => self.opcode_set(RegByte::A, 3),
-
-                    0xE0 => self.opcode_set(RegByte::B, 4),
-                    0xE1 => self.opcode_set(RegByte::C, 4),
-                    0xE2 => self.opcode_set(RegByte::D, 4),
-                    0xE3 => self.opcode_set(RegByte::E, 4),
-                    0xE4 => self.opcode_set(RegByte::H, 4),
-                    0xE5 => self.opcode_set(RegByte::L, 4),
-                    0xE6 => self.opcode_set_memory(memory, RegWord::Hl, 4),
-                    0xE7 => self.opcode_set(RegByte::A, 4),
-
-                    0xE8 => self.opcode_set(RegByte::B, 5),
-                    0xE9 => self.opcode_set(RegByte::C, 5),
-                    0xEA => self.opcode_set(RegByte::D, 5),
-                    0xEB => self.opcode_set(RegByte::E, 5),
-                    0xEC => self.opcode_set(RegByte::H, 5),
-                    0xED => self.opcode_set(RegByte::L, 5),
-                    0xEE => self.opcode_set_memory(memory, RegWord::Hl, 5),
-                    0xEF => self.opcode_set(RegByte::A, 5),
-
-                    0xF0 => self.opcode_set(RegByte::B, 6),
-                    0xF1 => self.opcode_set(RegByte::C, 6),
-                    0xF2 => self.opcode_set(RegByte::D, 6),
-                    0xF3 => self.opcode_set(RegByte::E, 6),
-                    0xF4 => self.opcode_set(RegByte::H, 6),
-                    0xF5 => self.opcode_set(RegByte::L, 6),
-                    0xF6 => self.opcode_set_memory(memory, RegWord::Hl, 6),
-                    0xF7 => self.opcode_set(RegByte::A, 6),
-
-                    0xF8 => self.opcode_set(RegByte::B, 7),
-                    0xF9 => self.opcode_set(RegByte::C, 7),
-                    0xFA => self.opcode_set(RegByte::D, 7),
-                    0xFB => self.opcode_set(RegByte::E, 7),
-                    0xFC => self.opcode_set(RegByte::H, 7),
-                    0xFD => self.opcode_set(RegByte::L, 7),
-                    0xFE => self.opcode_set_memory(memory, RegWord::Hl, 7),
-                    0xFF => self.opcode_set(RegByte::A, 7),
-                }
+                decoder::decode_prefixed(opcode)
             } else {
-                match opcode {
-                    0x00 => self.opcode_nop(),
-                    0x01 => self.opcode_ld_word_to_register(memory, RegWord::Bc),
-                    0x02 => self.opcode_ld_register_to_memory(memory, RegWord::Bc, RegByte::A),
-                    0x03 => self.opcode_inc16(RegWord::Bc),
-                    0x04 => self.opcode_inc8(RegByte::B),
-                    0x05 => self.opcode_dec8(RegByte::B),
-                    0x06 => self.opcode_ld_byte_to_register(memory, RegByte::B),
-                    0x07 => self.opcode_rlca(RegByte::A),
-                    0x08 => self.opcode_ld_sp_to_address(memory),
-                    0x09 => self.opcode_add_register_word(RegWord::Hl, RegWord::Bc),
-                    0x0F => self.opcode_rrca(RegByte::A),
-                    0x10 => self.opcode_stop(),
-                    0x17 => self.opcode_rla(RegByte::A),
-                    0x0A => self.opcode_ld_memory_to_register(memory, RegByte::A, RegWord::Bc),
-                    0x0B => self.opcode_dec16(RegWord::Bc),
-                    0x0C => self.opcode_inc8(RegByte::C),
-                    0x0D => self.opcode_dec8(RegByte::C),
-                    0x0E => self.opcode_ld_byte_to_register(memory, RegByte::C),
-                    0x11 => self.opcode_ld_word_to_register(memory, RegWord::De),
-                    0x12 => self.opcode_ld_register_to_memory(memory, RegWord::De, RegByte::A),
-                    0x13 => self.opcode_inc16(RegWord::De),
-                    0x14 => self.opcode_inc8(RegByte::D),
-                    0x15 => self.opcode_dec8(RegByte::D),
-                    0x19 => self.opcode_add_register_word(RegWord::Hl, RegWord::De),
-                    0x18 => self.opcode_jr(memory),
-                    0x16 => self.opcode_ld_byte_to_register(memory, RegByte::D),
-                    0x1A => self.opcode_ld_memory_to_register(memory, RegByte::A, RegWord::De),
-                    0x1B => self.opcode_dec16(RegWord::De),
-                    0x1C => self.opcode_inc8(RegByte::E),
-                    0x1D => self.opcode_dec8(RegByte::E),
-                    0x1E => self.opcode_ld_byte_to_register(memory, RegByte::E),
-                    0x1F => self.opcode_rra(RegByte::A),
-                    0x20 => self.opcode_jr_nz(memory),
-                    0x21 => self.opcode_ld_word_to_register(memory, RegWord::Hl),
-                    0x22 => self.opcode_ldi_register_to_memory(memory, RegWord::Hl, RegByte::A),
-                    0x23 => self.opcode_inc16(RegWord::Hl),
-                    0x24 => self.opcode_inc8(RegByte::H),
-                    0x25 => self.opcode_dec8(RegByte::H),
-                    0x26 => self.opcode_ld_byte_to_register(memory, RegByte::H),
-                    0x27 => self.opcode_daa(RegByte::A),
-                    0x28 => self.opcode_jr_z(memory),
-                    0x29 => self.opcode_add_register_word(RegWord::Hl, RegWord::Hl),
-                    0x2A => self.opcode_ldi_memory_to_register(memory, RegByte::A, RegWord::Hl),
-                    0x2B => self.opcode_dec16(RegWord::Hl),
-                    0x2C => self.opcode_inc8(RegByte::L),
-                    0x2D => self.opcode_dec8(RegByte::L),
-                    0x2E => self.opcode_ld_byte_to_register(memory, RegByte::L),
-                    0x2F => self.opcode_cpl(RegByte::A),
-                    0x30 => self.opcode_jr_nc(memory),
-                    0x31 => self.opcode_ld_memory_to_stackptr(memory),
-                    0x32 => self.opcode_ldd_register_to_memory(memory, RegWord::Hl, RegByte::A),
-                    0x33 => self.opcode_inc_sp(),
-                    0x34 => self.opcode_inc8_memory(memory, RegWord::Hl),
-                    0x35 => self.opcode_dec_memory(memory, RegWord::Hl),
-                    0x36 => self.opcode_ld_byte_to_memory(memory, RegWord::Hl),
-                    0x37 => self.opcode_scf(),
-                    0x38 => self.opcode_jr_c(memory),
-                    0x39 => self.opcode_add_register_sp(RegWord::Hl),
-                    0x3A => self.opcode_ldd_memory_to_register(memory, RegByte::A, RegWord::Hl),
-                    0x3E => self.opcode_ld_byte_to_register(memory, RegByte::A),
-                    0x3B => self.opcode_dec_sp(),
-                    0x3C => self.opcode_inc8(RegByte::A),
-                    0x3D => self.opcode_dec8(RegByte::A),
-                    0x3F => self.opcode_ccf(),
-                    0x40 => self.opcode_ld_register_to_register(RegByte::B, RegByte::B),
-                    0x41 => self.opcode_ld_register_to_register(RegByte::B, RegByte::C),
-                    0x42 => self.opcode_ld_register_to_register(RegByte::B, RegByte::D),
-                    0x43 => self.opcode_ld_register_to_register(RegByte::B, RegByte::E),
-                    0x44 => self.opcode_ld_register_to_register(RegByte::B, RegByte::H),
-                    0x45 => self.opcode_ld_register_to_register(RegByte::B, RegByte::L),
-                    0x46 => self.opcode_ld_memory_to_register(memory, RegByte::B, RegWord::Hl),
-                    0x47 => self.opcode_ld_register_to_register(RegByte::B, RegByte::A),
-                    0x48 => self.opcode_ld_register_to_register(RegByte::C, RegByte::B),
-                    0x49 => self.opcode_ld_register_to_register(RegByte::C, RegByte::C),
-                    0x4A => self.opcode_ld_register_to_register(RegByte::C, RegByte::D),
-                    0x4B => self.opcode_ld_register_to_register(RegByte::C, RegByte::E),
-                    0x4C => self.opcode_ld_register_to_register(RegByte::C, RegByte::H),
-                    0x4D => self.opcode_ld_register_to_register(RegByte::C, RegByte::L),
-                    0x4E => self.opcode_ld_memory_to_register(memory, RegByte::C, RegWord::Hl),
-                    0x4F => self.opcode_ld_register_to_register(RegByte::C, RegByte::A),
-                    0x50 => self.opcode_ld_register_to_register(RegByte::D, RegByte::B),
-                    0x51 => self.opcode_ld_register_to_register(RegByte::D, RegByte::C),
-                    0x52 => self.opcode_ld_register_to_register(RegByte::D, RegByte::D),
-                    0x53 => self.opcode_ld_register_to_register(RegByte::D, RegByte::E),
-                    0x54 => self.opcode_ld_register_to_register(RegByte::D, RegByte::H),
-                    0x55 => self.opcode_ld_register_to_register(RegByte::D, RegByte::L),
-                    0x56 => self.opcode_ld_memory_to_register(memory, RegByte::D, RegWord::Hl),
-                    0x57 => self.opcode_ld_register_to_register(RegByte::D, RegByte::A),
-                    0x58 => self.opcode_ld_register_to_register(RegByte::E, RegByte::B),
-                    0x59 => self.opcode_ld_register_to_register(RegByte::E, RegByte::C),
-                    0x5A => self.opcode_ld_register_to_register(RegByte::E, RegByte::D),
-                    0x5B => self.opcode_ld_register_to_register(RegByte::E, RegByte::E),
-                    0x5C => self.opcode_ld_register_to_register(RegByte::E, RegByte::H),
-                    0x5D => self.opcode_ld_register_to_register(RegByte::E, RegByte::L),
-                    0x5E => self.opcode_ld_memory_to_register(memory, RegByte::E, RegWord::Hl),
-                    0x5F => self.opcode_ld_register_to_register(RegByte::E, RegByte::A),
-                    0x60 => self.opcode_ld_register_to_register(RegByte::H, RegByte::B),
-                    0x61 => self.opcode_ld_register_to_register(RegByte::H, RegByte::C),
-                    0x62 => self.opcode_ld_register_to_register(RegByte::H, RegByte::D),
-                    0x63 => self.opcode_ld_register_to_register(RegByte::H, RegByte::E),
-                    0x64 => self.opcode_ld_register_to_register(RegByte::H, RegByte::H),
-                    0x65 => self.opcode_ld_register_to_register(RegByte::H, RegByte::L),
-                    0x66 => self.opcode_ld_memory_to_register(memory, RegByte::H, RegWord::Hl),
-                    0x67 => self.opcode_ld_register_to_register(RegByte::H, RegByte::A),
-                    0x68 => self.opcode_ld_register_to_register(RegByte::L, RegByte::B),
-                    0x69 => self.opcode_ld_register_to_register(RegByte::L, RegByte::C),
-                    0x6A => self.opcode_ld_register_to_register(RegByte::L, RegByte::D),
-                    0x6B => self.opcode_ld_register_to_register(RegByte::L, RegByte::E),
-                    0x6C => self.opcode_ld_register_to_register(RegByte::L, RegByte::H),
-                    0x6D => self.opcode_ld_register_to_register(RegByte::L, RegByte::L),
-                    0x6E => self.opcode_ld_memory_to_register(memory, RegByte::L, RegWord::Hl),
-                    0x6F => self.opcode_ld_register_to_register(RegByte::L, RegByte::A),
-                    0x70 => self.opcode_ld_register_to_memory(memory, RegWord::Hl, RegByte::B),
-                    0x71 => self.opcode_ld_register_to_memory(memory, RegWord::Hl, RegByte::C),
-                    0x72 => self.opcode_ld_register_to_memory(memory, RegWord::Hl, RegByte::D),
-                    0x73 => self.opcode_ld_register_to_memory(memory, RegWord::Hl, RegByte::E),
-                    0x74 => self.opcode_ld_register_to_memory(memory, RegWord::Hl, RegByte::H),
-                    0x75 => self.opcode_ld_register_to_memory(memory, RegWord::Hl, RegByte::L),
-                    0x76 => self.opcode_halt(),
-                    0x77 => self.opcode_ld_register_to_memory(memory, RegWord::Hl, RegByte::A),
-                    0x78 => self.opcode_ld_register_to_register(RegByte::A, RegByte::B),
-                    0x79 => self.opcode_ld_register_to_register(RegByte::A, RegByte::C),
-                    0x7A => self.opcode_ld_register_to_register(RegByte::A, RegByte::D),
-                    0x7B => self.opcode_ld_register_to_register(RegByte::A, RegByte::E),
-                    0x7C => self.opcode_ld_register_to_register(RegByte::A, RegByte::H),
-                    0x7D => self.opcode_ld_register_to_register(RegByte::A, RegByte::L),
-                    0x7E => self.opcode_ld_memory_to_register(memory, RegByte::A, RegWord::Hl),
-                    0x7F => self.opcode_ld_register_to_register(RegByte::A, RegByte::A),
-                    0x80 => self.opcode_add_register_byte(RegByte::A, RegByte::B),
-                    0x81 => self.opcode_add_register_byte(RegByte::A, RegByte::C),
-                    0x82 => self.opcode_add_register_byte(RegByte::A, RegByte::D),
-                    0x83 => self.opcode_add_register_byte(RegByte::A, RegByte::E),
-                    0x84 => self.opcode_add_register_byte(RegByte::A, RegByte::H),
-                    0x85 => self.opcode_add_register_byte(RegByte::A, RegByte::L),
-                    0x86 => self.opcode_add_memory(memory, RegByte::A, RegWord::Hl),
-                    0x87 => self.opcode_add_register_byte(RegByte::A, RegByte::A),
-                    0x88 => self.opcode_adc(RegByte::A, RegByte::B),
-                    0x89 => self.opcode_adc(RegByte::A, RegByte::C),
-                    0x8A => self.opcode_adc(RegByte::A, RegByte::D),
-                    0x8B => self.opcode_adc(RegByte::A, RegByte::E),
-                    0x8C => self.opcode_adc(RegByte::A, RegByte::H),
-                    0x8D => self.opcode_adc(RegByte::A, RegByte::L),
-                    0x8E => self.opcode_adc_memory(memory, RegByte::A, RegWord::Hl),
-                    0x8F => self.opcode_adc(RegByte::A, RegByte::A),
-                    0x90 => self.opcode_sub(RegByte::A, RegByte::B),
-                    0x91 => self.opcode_sub(RegByte::A, RegByte::C),
-                    0x92 => self.opcode_sub(RegByte::A, RegByte::D),
-                    0x93 => self.opcode_sub(RegByte::A, RegByte::E),
-                    0x94 => self.opcode_sub(RegByte::A, RegByte::H),
-                    0x95 => self.opcode_sub(RegByte::A, RegByte::L),
-                    0x96 => self.opcode_sub_memory(memory, RegByte::A, RegWord::Hl),
-                    0x97 => self.opcode_sub(RegByte::A, RegByte::A),
-                    0x98 => self.opcode_sbc(RegByte::A, RegByte::B),
-                    0x99 => self.opcode_sbc(RegByte::A, RegByte::C),
-                    0x9A => self.opcode_sbc(RegByte::A, RegByte::D),
-                    0x9B => self.opcode_sbc(RegByte::A, RegByte::E),
-                    0x9C => self.opcode_sbc(RegByte::A, RegByte::H),
-                    0x9D => self.opcode_sbc(RegByte::A, RegByte::L),
-                    0x9E => self.opcode_sbc_memory(memory, RegByte::A, RegWord::Hl),
-                    0x9F => self.opcode_sbc(RegByte::A, RegByte::A),
-                    0xA0 => self.opcode_and(RegByte::A, RegByte::B),
-                    0xA1 => self.opcode_and(RegByte::A, RegByte::C),
-                    0xA2 => self.opcode_and(RegByte::A, RegByte::D),
-                    0xA3 => self.opcode_and(RegByte::A, RegByte::E),
-                    0xA4 => self.opcode_and(RegByte::A, RegByte::H),
-                    0xA5 => self.opcode_and(RegByte::A, RegByte::L),
-                    0xA6 => self.opcode_and_memory(memory, RegByte::A, RegWord::Hl),
-                    0xA7 => self.opcode_and(RegByte::A, RegByte::A),
-                    0xA8 => self.opcode_xor(RegByte::A, RegByte::B),
-                    0xA9 => self.opcode_xor(RegByte::A, RegByte::C),
-                    0xAA => self.opcode_xor(RegByte::A, RegByte::D),
-                    0xAB => self.opcode_xor(RegByte::A, RegByte::E),
-                    0xAC => self.opcode_xor(RegByte::A, RegByte::H),
-                    0xAD => self.opcode_xor(RegByte::A, RegByte::L),
-                    0xAE => self.opcode_xor_memory(memory, RegByte::A, RegWord::Hl),
-                    0xAF => self.opcode_xor(RegByte::A, RegByte::A),
-                    0xB0 => self.opcode_or(RegByte::A, RegByte::B),
-                    0xB1 => self.opcode_or(RegByte::A, RegByte::C),
-                    0xB2 => self.opcode_or(RegByte::A, RegByte::D),
-                    0xB3 => self.opcode_or(RegByte::A, RegByte::E),
-                    0xB4 => self.opcode_or(RegByte::A, RegByte::H),
-                    0xB5 => self.opcode_or(RegByte::A, RegByte::L),
-                    0xB6 => self.opcode_or_memory(memory, RegByte::A, RegWord::Hl),
-                    0xB7 => self.opcode_or(RegByte::A, RegByte::A),
-                    0xB8 => self.opcode_cp(RegByte::A, RegByte::B),
-                    0xB9 => self.opcode_cp(RegByte::A, RegByte::C),
-                    0xBA => self.opcode_cp(RegByte::A, RegByte::D),
-                    0xBB => self.opcode_cp(RegByte::A, RegByte::E),
-                    0xBC => self.opcode_cp(RegByte::A, RegByte::H),
-                    0xBE => self.opcode_cp_memory(memory, RegByte::A, RegWord::Hl),
-                    0xBD => self.opcode_cp(RegByte::A, RegByte::L),
-                    0xBF => self.opcode_cp(RegByte::A, RegByte::A),
-                    0xC0 => self.opcode_ret_nz(memory),
-                    0xC1 => self.opcode_pop(memory, RegWord::Bc),
-                    0xC2 => self.opcode_jp_nz(memory),
-                    0xC3 => self.opcode_jp(memory),
-                    0xC4 => self.opcode_call_nz(memory),
-                    0xC5 => self.opcode_push(memory, RegWord::Bc),
-                    0xC6 => self.opcode_add_byte(memory, RegByte::A),
-                    0xC8 => self.opcode_ret_z(memory),
-                    0xC7 => self.opcode_rst(memory, 0x0000),
-                    0xC9 => self.opcode_ret(memory),
-                    0xCA => self.opcode_jp_z(memory),
-                    0xCC => self.opcode_call_z(memory),
-                    0xCD => self.opcode_call(memory),
-                    0xCE => self.opcode_adc_byte(memory, RegByte::A),
-                    0xCF => self.opcode_rst(memory, 0x0008),
-                    0xD0 => self.opcode_ret_nc(memory),
-                    0xD1 => self.opcode_pop(memory, RegWord::De),
-                    0xD2 => self.opcode_jp_nc(memory),
-                    0xD4 => self.opcode_call_nc(memory),
-                    0xD5 => self.opcode_push(memory, RegWord::De),
-                    0xD6 => self.opcode_sub_byte(memory, RegByte::A),
-                    0xD7 => self.opcode_rst(memory, 0x0010),
-                    0xD8 => self.opcode_ret_c(memory),
-                    0xD9 => self.opcode_reti(memory),
-                    0xDA => self.opcode_jp_c(memory),
-                    0xDC => self.opcode_call_c(memory),
-                    0xDE => self.opcode_sbc_byte(memory, RegByte::A),
-                    0xDF => self.opcode_rst(memory, 0x0018),
-                    0xE0 => self.opcode_ldh_register_to_offset(memory, RegByte::A),
-                    0xE1 => self.opcode_pop(memory, RegWord::Hl),
-                    0xE2 => self.opcode_ldh_register_to_address(memory, RegByte::A),
-                    0xE5 => self.opcode_push(memory, RegWord::Hl),
-                    0xE6 => self.opcode_and_byte(memory, RegByte::A),
-                    0xE7 => self.opcode_rst(memory, 0x0020),
-                    0xE8 => self.opcode_add_sp_byte(memory),
-                    0xE9 => self.opcode_jp_hl(RegWord::Hl),
-                    0xEA => self.opcode_ld_register_to_address(memory, RegByte::A),
-                    0xEE => self.opcode_xor_byte(memory, RegByte::A),
-                    0xEF => self.opcode_rst(memory, 0x0028),
-                    0xF0 => self.opcode_ldh_offset_to_register(memory, RegByte::A),
-                    0xF1 => self.opcode_pop(memory, RegWord::Af),
-                    0xF2 => self.opcode_ldh_address_to_register(memory, RegByte::A),
-                    0xF3 => self.opcode_di(),
-                    0xF5 => self.opcode_push(memory, RegWord::Af),
-                    0xF6 => self.opcode_or_byte(memory, RegByte::A),
-                    0xF7 => self.opcode_rst(memory, 0x0030),
-                    0xF8 => self.opcode_add_sp_byte_to_register(memory, RegWord::Hl),
-                    0xF9 => self.opcode_ld_reg_to_stackptr(RegWord::Hl),
-                    0xFA => self.opcode_ld_address_to_register(memory, RegByte::A),
-                    0xFB => self.opcode_ei(),
-                    0xFE => self.opcode_cp_byte(memory, RegByte::A),
-                    0xFF => self.opcode_rst(memory, 0x0038),
-                    _ => panic!("Please implement the opcode {:X}", opcode),
-                }
+                decoder::decode(opcode)
+            };
+
+            if desc.opcode == Opcode::Byte {
+                self.locked_up_by = Some((opcode_pc, opcode));
+                return Err(CpuError::IllegalOpcode {
+                    pc: opcode_pc,
+                    opcode,
+                });
             }
+
+            self.execute(memory, desc);
         }
 
         if self.halt_mode_enabled {
             self.advance_clock(1);
         }
 
-        memory.update_timer(self.machine_cycles);
-        //self.timer.timer_tick(self.machine_cycles, memory);
-        //self.timer.tima_tick(self.machine_cycles, memory);
+        //NOTE: A DI right after EI cancels the scheduled enable
+        if enable_interrupts && self.interrupts_enable_scheduled {
+            self.interrupts_enabled = true;
+            self.interrupts_enable_scheduled = false;
+        }
+
+        //NOTE: A vram dma started by the instruction keeps the cpu waiting until it is done
+        let stalled_cycles = memory.take_stalled_cycles();
+        self.advance_clock(stalled_cycles);
+
+        //NOTE: Internal cycles at the end of the instruction haven't been seen by the components yet
+        memory.update(self.machine_cycles);
+
+        Ok(())
+    }
+
+    fn execute(&mut self, memory: &mut Memory, desc: &InstructionDesc) {
+        match (desc.opcode, desc.dest, desc.src) {
+            (Opcode::Nop, _, _) => self.opcode_nop(),
+            (Opcode::Stop, _, _) => self.opcode_stop(memory),
+            (Opcode::Halt, _, _) => self.opcode_halt(memory),
+            (Opcode::Di, _, _) => self.opcode_di(),
+            (Opcode::Ei, _, _) => self.opcode_ei(),
+            (Opcode::Rlca, _, _) => self.opcode_rlca(RegByte::A),
+            (Opcode::Rrca, _, _) => self.opcode_rrca(RegByte::A),
+            (Opcode::Rla, _, _) => self.opcode_rla(RegByte::A),
+            (Opcode::Rra, _, _) => self.opcode_rra(RegByte::A),
+            (Opcode::Daa, _, _) => self.opcode_daa(RegByte::A),
+            (Opcode::Cpl, _, _) => self.opcode_cpl(RegByte::A),
+            (Opcode::Scf, _, _) => self.opcode_scf(),
+            (Opcode::Ccf, _, _) => self.opcode_ccf(),
+
+            (Opcode::Load, Operand::Reg8(dest), Operand::Reg8(src)) => {
+                self.opcode_ld_register_to_register(dest, src)
+            }
+            (Opcode::Load, Operand::Reg8(dest), Operand::Imm8) => {
+                self.opcode_ld_byte_to_register(memory, dest)
+            }
+            (Opcode::Load, Operand::Reg8(dest), Operand::Indirect(src)) => {
+                self.opcode_ld_memory_to_register(memory, dest, src)
+            }
+            (Opcode::Load, Operand::Indirect(dest), Operand::Reg8(src)) => {
+                self.opcode_ld_register_to_memory(memory, dest, src)
+            }
+            (Opcode::Load, Operand::Indirect(dest), Operand::Imm8) => {
+                self.opcode_ld_byte_to_memory(memory, dest)
+            }
+            (Opcode::Load, Operand::Reg16(dest), Operand::Imm16) => {
+                self.opcode_ld_word_to_register(memory, dest)
+            }
+            (Opcode::Load, Operand::Sp, Operand::Imm16) => {
+                self.opcode_ld_memory_to_stackptr(memory)
+            }
+            (Opcode::Load, Operand::IndirectInc, Operand::Reg8(src)) => {
+                self.opcode_ldi_register_to_memory(memory, RegWord::Hl, src)
+            }
+            (Opcode::Load, Operand::IndirectDec, Operand::Reg8(src)) => {
+                self.opcode_ldd_register_to_memory(memory, RegWord::Hl, src)
+            }
+            (Opcode::Load, Operand::Reg8(dest), Operand::IndirectInc) => {
+                self.opcode_ldi_memory_to_register(memory, dest, RegWord::Hl)
+            }
+            (Opcode::Load, Operand::Reg8(dest), Operand::IndirectDec) => {
+                self.opcode_ldd_memory_to_register(memory, dest, RegWord::Hl)
+            }
+            (Opcode::Load, Operand::Address, Operand::Reg8(src)) => {
+                self.opcode_ld_register_to_address(memory, src)
+            }
+            (Opcode::Load, Operand::Reg8(dest), Operand::Address) => {
+                self.opcode_ld_address_to_register(memory, dest)
+            }
+            (Opcode::Load, Operand::Address, Operand::Sp) => self.opcode_ld_sp_to_address(memory),
+            (Opcode::Load, Operand::HighOffset, Operand::Reg8(src)) => {
+                self.opcode_ldh_register_to_offset(memory, src)
+            }
+            (Opcode::Load, Operand::Reg8(dest), Operand::HighOffset) => {
+                self.opcode_ldh_offset_to_register(memory, dest)
+            }
+            (Opcode::Load, Operand::HighC, Operand::Reg8(src)) => {
+                self.opcode_ldh_register_to_address(memory, src)
+            }
+            (Opcode::Load, Operand::Reg8(dest), Operand::HighC) => {
+                self.opcode_ldh_address_to_register(memory, dest)
+            }
+            (Opcode::Load, Operand::Reg16(dest), Operand::SpOffset) => {
+                self.opcode_add_sp_byte_to_register(memory, dest)
+            }
+            (Opcode::Load, Operand::Sp, Operand::Reg16(src)) => self.opcode_ld_reg_to_stackptr(src),
+
+            (Opcode::Inc, Operand::Reg8(dest), _) => self.opcode_inc8(dest),
+            (Opcode::Inc, Operand::Indirect(dest), _) => self.opcode_inc8_memory(memory, dest),
+            (Opcode::Inc, Operand::Reg16(dest), _) => self.opcode_inc16(dest),
+            (Opcode::Inc, Operand::Sp, _) => self.opcode_inc_sp(),
+            (Opcode::Dec, Operand::Reg8(dest), _) => self.opcode_dec8(dest),
+            (Opcode::Dec, Operand::Indirect(dest), _) => self.opcode_dec_memory(memory, dest),
+            (Opcode::Dec, Operand::Reg16(dest), _) => self.opcode_dec16(dest),
+            (Opcode::Dec, Operand::Sp, _) => self.opcode_dec_sp(),
+
+            (Opcode::Add, Operand::Reg8(dest), Operand::Reg8(src)) => {
+                self.opcode_add_register_byte(dest, src)
+            }
+            (Opcode::Add, Operand::Reg8(dest), Operand::Indirect(src)) => {
+                self.opcode_add_memory(memory, dest, src)
+            }
+            (Opcode::Add, Operand::Reg8(dest), Operand::Imm8) => self.opcode_add_byte(memory, dest),
+            (Opcode::Add, Operand::Reg16(dest), Operand::Reg16(src)) => {
+                self.opcode_add_register_word(dest, src)
+            }
+            (Opcode::Add, Operand::Reg16(dest), Operand::Sp) => self.opcode_add_register_sp(dest),
+            (Opcode::Add, Operand::Sp, Operand::SignedImm8) => self.opcode_add_sp_byte(memory),
+            (Opcode::Adc, Operand::Reg8(dest), Operand::Reg8(src)) => self.opcode_adc(dest, src),
+            (Opcode::Adc, Operand::Reg8(dest), Operand::Indirect(src)) => {
+                self.opcode_adc_memory(memory, dest, src)
+            }
+            (Opcode::Adc, Operand::Reg8(dest), Operand::Imm8) => self.opcode_adc_byte(memory, dest),
+            (Opcode::Sbc, Operand::Reg8(dest), Operand::Reg8(src)) => self.opcode_sbc(dest, src),
+            (Opcode::Sbc, Operand::Reg8(dest), Operand::Indirect(src)) => {
+                self.opcode_sbc_memory(memory, dest, src)
+            }
+            (Opcode::Sbc, Operand::Reg8(dest), Operand::Imm8) => self.opcode_sbc_byte(memory, dest),
+
+            //NOTE: These instructions always work on the accumulator, it's not part of the operands
+            (Opcode::Sub, _, Operand::Reg8(src)) => self.opcode_sub(RegByte::A, src),
+            (Opcode::Sub, _, Operand::Indirect(src)) => {
+                self.opcode_sub_memory(memory, RegByte::A, src)
+            }
+            (Opcode::Sub, _, Operand::Imm8) => self.opcode_sub_byte(memory, RegByte::A),
+            (Opcode::And, _, Operand::Reg8(src)) => self.opcode_and(RegByte::A, src),
+            (Opcode::And, _, Operand::Indirect(src)) => {
+                self.opcode_and_memory(memory, RegByte::A, src)
+            }
+            (Opcode::And, _, Operand::Imm8) => self.opcode_and_byte(memory, RegByte::A),
+            (Opcode::Xor, _, Operand::Reg8(src)) => self.opcode_xor(RegByte::A, src),
+            (Opcode::Xor, _, Operand::Indirect(src)) => {
+                self.opcode_xor_memory(memory, RegByte::A, src)
+            }
+            (Opcode::Xor, _, Operand::Imm8) => self.opcode_xor_byte(memory, RegByte::A),
+            (Opcode::Or, _, Operand::Reg8(src)) => self.opcode_or(RegByte::A, src),
+            (Opcode::Or, _, Operand::Indirect(src)) => {
+                self.opcode_or_memory(memory, RegByte::A, src)
+            }
+            (Opcode::Or, _, Operand::Imm8) => self.opcode_or_byte(memory, RegByte::A),
+            (Opcode::Cp, _, Operand::Reg8(src)) => self.opcode_cp(RegByte::A, src),
+            (Opcode::Cp, _, Operand::Indirect(src)) => {
+                self.opcode_cp_memory(memory, RegByte::A, src)
+            }
+            (Opcode::Cp, _, Operand::Imm8) => self.opcode_cp_byte(memory, RegByte::A),
+
+            (Opcode::Push, _, Operand::Reg16(src)) => self.opcode_push(memory, src),
+            (Opcode::Pop, Operand::Reg16(dest), _) => self.opcode_pop(memory, dest),
+
+            (Opcode::Jr, Operand::None, _) => self.opcode_jr(memory),
+            (Opcode::Jr, Operand::Condition(Flag::NotZero), _) => self.opcode_jr_nz(memory),
+            (Opcode::Jr, Operand::Condition(Flag::Zero), _) => self.opcode_jr_z(memory),
+            (Opcode::Jr, Operand::Condition(Flag::NotCarry), _) => self.opcode_jr_nc(memory),
+            (Opcode::Jr, Operand::Condition(Flag::Carry), _) => self.opcode_jr_c(memory),
+            (Opcode::Jp, Operand::None, Operand::Imm16) => self.opcode_jp(memory),
+            (Opcode::Jp, Operand::None, Operand::Indirect(src)) => self.opcode_jp_hl(src),
+            (Opcode::Jp, Operand::Condition(Flag::NotZero), _) => self.opcode_jp_nz(memory),
+            (Opcode::Jp, Operand::Condition(Flag::Zero), _) => self.opcode_jp_z(memory),
+            (Opcode::Jp, Operand::Condition(Flag::NotCarry), _) => self.opcode_jp_nc(memory),
+            (Opcode::Jp, Operand::Condition(Flag::Carry), _) => self.opcode_jp_c(memory),
+            (Opcode::Call, Operand::None, _) => self.opcode_call(memory),
+            (Opcode::Call, Operand::Condition(Flag::NotZero), _) => self.opcode_call_nz(memory),
+            (Opcode::Call, Operand::Condition(Flag::Zero), _) => self.opcode_call_z(memory),
+            (Opcode::Call, Operand::Condition(Flag::NotCarry), _) => self.opcode_call_nc(memory),
+            (Opcode::Call, Operand::Condition(Flag::Carry), _) => self.opcode_call_c(memory),
+            (Opcode::Ret, Operand::None, _) => self.opcode_ret(memory),
+            (Opcode::Ret, Operand::Condition(Flag::NotZero), _) => self.opcode_ret_nz(memory),
+            (Opcode::Ret, Operand::Condition(Flag::Zero), _) => self.opcode_ret_z(memory),
+            (Opcode::Ret, Operand::Condition(Flag::NotCarry), _) => self.opcode_ret_nc(memory),
+            (Opcode::Ret, Operand::Condition(Flag::Carry), _) => self.opcode_ret_c(memory),
+            (Opcode::Reti, _, _) => self.opcode_reti(memory),
+            (Opcode::Rst, _, Operand::Vector(vector)) => self.opcode_rst(memory, vector as u16),
+
+            (Opcode::Rlc, Operand::Reg8(dest), _) => self.opcode_rlc(dest),
+            (Opcode::Rlc, Operand::Indirect(dest), _) => self.opcode_rlc_memory(memory, dest),
+            (Opcode::Rrc, Operand::Reg8(dest), _) => self.opcode_rrc(dest),
+            (Opcode::Rrc, Operand::Indirect(dest), _) => self.opcode_rrc_memory(memory, dest),
+            (Opcode::Rl, Operand::Reg8(dest), _) => self.opcode_rl(dest),
+            (Opcode::Rl, Operand::Indirect(dest), _) => self.opcode_rl_memory(memory, dest),
+            (Opcode::Rr, Operand::Reg8(dest), _) => self.opcode_rr(dest),
+            (Opcode::Rr, Operand::Indirect(dest), _) => self.opcode_rr_memory(memory, dest),
+            (Opcode::Sla, Operand::Reg8(dest), _) => self.opcode_sla(dest),
+            (Opcode::Sla, Operand::Indirect(dest), _) => self.opcode_sla_memory(memory, dest),
+            (Opcode::Sra, Operand::Reg8(dest), _) => self.opcode_sra(dest),
+            (Opcode::Sra, Operand::Indirect(dest), _) => self.opcode_sra_memory(memory, dest),
+            (Opcode::Swap, Operand::Reg8(dest), _) => self.opcode_swap_register(dest),
+            (Opcode::Swap, Operand::Indirect(dest), _) => self.opcode_swap_memory(memory, dest),
+            (Opcode::Srl, Operand::Reg8(dest), _) => self.opcode_srl(dest),
+            (Opcode::Srl, Operand::Indirect(dest), _) => self.opcode_srl_memory(memory, dest),
+            (Opcode::Bit, Operand::Bit(bit_index), Operand::Reg8(src)) => {
+                self.opcode_bit(src, bit_index as usize)
+            }
+            (Opcode::Bit, Operand::Bit(bit_index), Operand::Indirect(src)) => {
+                self.opcode_bit_memory(memory, src, bit_index as usize)
+            }
+            (Opcode::Res, Operand::Bit(bit_index), Operand::Reg8(src)) => {
+                self.opcode_res(src, bit_index as usize)
+            }
+            (Opcode::Res, Operand::Bit(bit_index), Operand::Indirect(src)) => {
+                self.opcode_res_memory(memory, src, bit_index as usize)
+            }
+            (Opcode::Set, Operand::Bit(bit_index), Operand::Reg8(src)) => {
+                self.opcode_set(src, bit_index as usize)
+            }
+            (Opcode::Set, Operand::Bit(bit_index), Operand::Indirect(src)) => {
+                self.opcode_set_memory(memory, src, bit_index as usize)
+            }
+
+            _ => unreachable!("The decoder produced an unknown instruction {:?}", desc),
+        }
     }
 
-    fn advance_clock_update_timer(&mut self, elapsed: usize, mem: &mut Memory) {
-        //NOTE: Another hacky way of updating the timer...
+    //NOTE: Every memory access takes its own machine cycle, the components clocked by the system
+    //      clock (timer, ppu, dma) are brought up to that cycle before the access happens. Internal
+    //      cycles only advance the counter, the components catch up on the next access.
+    fn advance_clock_update_memory(&mut self, elapsed: usize, mem: &mut Memory) {
         self.machine_cycles = self.machine_cycles.overflowing_add(elapsed).0;
-        mem.update_timer(self.machine_cycles);
-        //self.timer.timer_tick(self.machine_cycles, mem);
-        //self.timer.tima_tick(self.machine_cycles, mem);
+        mem.update(self.machine_cycles);
     }
+
     fn advance_clock(&mut self, elapsed: usize) {
         self.machine_cycles = self.machine_cycles.overflowing_add(elapsed).0;
     }
 
     fn write_byte(&mut self, mem: &mut Memory, addr: u16, value: u8) {
-        self.advance_clock_update_timer(1, mem);
-        let event = mem.write(addr, value);
+        self.advance_clock_update_memory(1, mem);
+        mem.cpu_write(addr, value);
     }
 
-    fn fetch_word(&mut self, mem: &Memory) -> u16 {
-        let lo_byte = mem.read(self.pc);
-        self.pc += 1;
-        let hi_byte = mem.read(self.pc);
-        self.pc += 1;
-        self.advance_clock(2);
+    fn read_byte(&mut self, mem: &mut Memory, addr: u16) -> u8 {
+        self.advance_clock_update_memory(1, mem);
+        mem.cpu_read(addr)
+    }
+
+    fn fetch_word(&mut self, mem: &mut Memory) -> u16 {
+        let lo_byte = self.fetch_byte(mem);
+        let hi_byte = self.fetch_byte(mem);
 
         (hi_byte as u16) << 8 | lo_byte as u16
     }
 
-    fn read_byte(&mut self, mem: &mut Memory, addr: u16) -> u8 {
-        self.advance_clock_update_timer(1, mem);
-        let value = mem.read(addr);
+    fn fetch_opcode(&mut self, mem: &mut Memory) -> u8 {
+        if self.halt_bug_triggered {
+            //NOTE: The HALT bug makes the cpu fail to increment the pc after the opcode fetch, so
+            //      the byte after HALT gets read twice.
+            self.halt_bug_triggered = false;
 
-        value
+            return self.read_byte(mem, self.pc);
+        }
+
+        self.fetch_byte(mem)
     }
 
-    fn fetch_byte(&mut self, mem: &Memory) -> u8 {
-        let lo_byte = mem.read(self.pc);
+    fn fetch_byte(&mut self, mem: &mut Memory) -> u8 {
+        let value = self.read_byte(mem, self.pc);
         self.pc = self.pc.overflowing_add(1).0;
 
-        self.advance_clock(1);
-        lo_byte
+        value
     }
 
     fn opcode_ei(&mut self) {
-        self.interrupts_enabled = true;
+        if !self.interrupts_enabled {
+            self.interrupts_enable_scheduled = true;
+        }
     }
-    fn opcode_stop(&mut self) {
-        //TODO: This is opcode stopping the cpu until a interrupt is triggered ! Then execution is resumed
-        //      currently this is all information i have need to dig further to see how thats
-        //      supposed to work !
+    fn opcode_stop(&mut self, mem: &mut Memory) {
+        let buttons_held = mem.read(JOYPAD_REGISTER) & 0x0F != 0x0F;
+
+        if buttons_held {
+            let if_table = mem.read(INTERRUPT_FLAG_REGISTER);
+            let ie_table = mem.read(INTERRUPT_ENABLE_REGISTER);
+
+            //NOTE: With a button held STOP never enters stop mode. Without a pending interrupt
+            //      it behaves like a two byte HALT, otherwise it's a one byte NOP.
+            if if_table & ie_table & 0x1F == 0x00 {
+                self.fetch_byte(mem);
+                self.halt_mode_enabled = true;
+            }
+
+            return;
+        }
+
+        //NOTE: STOP is encoded as 0x10 0x00, the second byte gets skipped
+        self.fetch_byte(mem);
+        mem.write(DIV_CLOCK_REGISTER, 0x00);
+
+        if mem.is_cgb_mode() && mem.is_speed_switch_armed() {
+            mem.switch_speed();
+
+            //NOTE: The cpu pauses for 2050 machine cycles while the clock switches over, DIV is
+            //      not running during that time.
+            self.advance_clock_update_memory(2050, mem);
+            mem.write(DIV_CLOCK_REGISTER, 0x00);
+            return;
+        }
+
+        self.stop_mode_enabled = true;
     }
 
-    fn opcode_halt(&mut self) {
-        //TODO: According to another doc i found this instruction is the one i described above in
-        //opcode stop ?!
+    fn opcode_halt(&mut self, mem: &Memory) {
+        let if_table = mem.read(INTERRUPT_FLAG_REGISTER);
+        let ie_table = mem.read(INTERRUPT_ENABLE_REGISTER);
+        let pending = if_table & ie_table & 0x1F;
+
+        if !self.interrupts_enabled && pending != 0x00 {
+            //NOTE: With IME cleared and an interrupt already pending HALT is never entered,
+            //      instead the DMG runs into the "HALT bug".
+            self.halt_bug_triggered = true;
+            return;
+        }
+
         self.halt_mode_enabled = true;
     }
 
@@ -750,7 +533,7 @@ impl Cpu {
         self.regs.write_value8_to(RegByte::F, flags);
     }
 
-    fn opcode_add_sp_byte(&mut self, mem: &Memory) {
+    fn opcode_add_sp_byte(&mut self, mem: &mut Memory) {
         let flags = self.regs.read_value8_from(RegByte::F);
 
         let a = self.sp;
@@ -776,9 +559,10 @@ impl Cpu {
 
         self.sp = result.0 as u16;
         self.regs.write_value8_to(RegByte::F, flags);
+        self.advance_clock(2);
     }
 
-    fn opcode_add_sp_byte_to_register(&mut self, mem: &Memory, dest: RegWord) {
+    fn opcode_add_sp_byte_to_register(&mut self, mem: &mut Memory, dest: RegWord) {
         let flags = self.regs.read_value8_from(RegByte::F);
 
         let a = self.sp;
@@ -804,16 +588,20 @@ impl Cpu {
 
         self.regs.write_value16_to(dest, result.0 as u16);
         self.regs.write_value8_to(RegByte::F, flags);
+        self.advance_clock(1);
     }
 
     fn opcode_nop(&mut self) {}
 
     fn opcode_di(&mut self) {
         self.interrupts_enabled = false;
+        self.interrupts_enable_scheduled = false;
     }
 
     fn opcode_inc_sp(&mut self) {
         self.sp = self.sp.overflowing_add(1).0;
+
+        self.advance_clock(1);
     }
     fn opcode_ld_reg_to_stackptr(&mut self, src: RegWord) {
         let value = self.regs.read_value16_from(src);
@@ -916,6 +704,7 @@ impl Cpu {
         let result = alu::inc_16(value);
 
         self.regs.write_value16_to(dest, result);
+        self.advance_clock(1);
     }
 
     fn opcode_dec8(&mut self, dest: RegByte) {
@@ -935,6 +724,7 @@ impl Cpu {
         let result = alu::dec_16(value);
 
         self.regs.write_value16_to(dest, result);
+        self.advance_clock(1);
     }
 
     fn opcode_dec_sp(&mut self) {
@@ -943,6 +733,7 @@ impl Cpu {
         let result = alu::dec_16(value);
 
         self.sp = result;
+        self.advance_clock(1);
     }
 
     fn opcode_dec_memory(&mut self, mem: &mut Memory, dest: RegWord) {
@@ -1118,6 +909,7 @@ impl Cpu {
 
         self.regs.write_value16_to(dest, result);
         self.regs.write_value8_to(RegByte::F, flags);
+        self.advance_clock(1);
     }
 
     fn opcode_add_register_sp(&mut self, dest: RegWord) {
@@ -1130,6 +922,7 @@ impl Cpu {
 
         self.regs.write_value16_to(dest, result);
         self.regs.write_value8_to(RegByte::F, flags);
+        self.advance_clock(1);
     }
 
     fn opcode_add_register_byte(&mut self, dest: RegByte, src: RegByte) {
@@ -1260,13 +1053,13 @@ impl Cpu {
         self.regs.write_value8_to(dest, value);
     }
 
-    fn opcode_ld_byte_to_register(&mut self, mem: &Memory, dest: RegByte) {
+    fn opcode_ld_byte_to_register(&mut self, mem: &mut Memory, dest: RegByte) {
         let byte = self.fetch_byte(mem);
 
         self.regs.write_value8_to(dest, byte);
     }
 
-    fn opcode_ld_word_to_register(&mut self, mem: &Memory, dest: RegWord) {
+    fn opcode_ld_word_to_register(&mut self, mem: &mut Memory, dest: RegWord) {
         let word = self.fetch_word(mem);
 
         self.regs.write_value16_to(dest, word);
@@ -2155,3 +1948,626 @@ impl Cpu {
         self.regs.write_value8_to(RegByte::F, flags);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::harness::{self, Verdict};
+    use crate::joypad::Button;
+    use crate::memory::{TAC_CLOCK_REGISTER, TIMA_CLOCK_REGISTER};
+    use crate::ppu::{LCDC_REGISTER, LY_REGISTER};
+    use crate::{disassembler::AssemblyDesc, memory::KEY1_REGISTER};
+    use std::path::{Path, PathBuf};
+
+    fn setup_interrupt_test(if_value: u8, ie_value: u8) -> (Cpu, Memory) {
+        let mut cpu = Cpu::default();
+        let mut mem = Memory::default();
+        //NOTE: Fills the interrupt vectors with NOPs
+        mem.load_cartridge(&[0x00; 0x8000]).unwrap();
+
+        cpu.pc = 0xC000;
+        cpu.interrupts_enabled = true;
+        mem.write(INTERRUPT_FLAG_REGISTER, if_value);
+        mem.write(INTERRUPT_ENABLE_REGISTER, ie_value);
+
+        (cpu, mem)
+    }
+
+    #[test]
+    fn test_interrupt_dispatch_vectors() {
+        let sources = [
+            (Interrupt::VBlank, 0x40),
+            (Interrupt::LcdStat, 0x48),
+            (Interrupt::Timer, 0x50),
+            (Interrupt::Serial, 0x58),
+            (Interrupt::Joypad, 0x60),
+        ];
+
+        for (interrupt, vector) in sources {
+            let (mut cpu, mut mem) = setup_interrupt_test(interrupt.mask(), 0x1F);
+
+            cpu.handle_interrupts(&mut mem);
+
+            assert_eq!(cpu.pc, vector);
+            assert_eq!(cpu.sp, 0xFFFC);
+            assert_eq!(mem.read(0xFFFC), 0x00);
+            assert_eq!(mem.read(0xFFFD), 0xC0);
+            assert_eq!(mem.read(INTERRUPT_FLAG_REGISTER) & 0x1F, 0x00);
+            assert_eq!(cpu.machine_cycles, 5);
+        }
+    }
+
+    #[test]
+    fn test_dispatch_keeps_requests_raised_during_the_push() {
+        let (mut cpu, mut mem) = setup_interrupt_test(Interrupt::VBlank.mask(), 0x05);
+        mem.write(TIMA_CLOCK_REGISTER, 0xFF);
+        mem.write(TAC_CLOCK_REGISTER, 0x05);
+        cpu.machine_cycles = 2;
+        mem.update(cpu.machine_cycles);
+
+        cpu.handle_interrupts(&mut mem);
+
+        //NOTE: The timer overflows and reloads while the program counter gets pushed
+        assert_eq!(cpu.pc, 0x40);
+        assert_eq!(
+            mem.read(INTERRUPT_FLAG_REGISTER) & 0x1F,
+            Interrupt::Timer.mask()
+        );
+    }
+
+    #[test]
+    fn test_interrupt_dispatch_priority() {
+        let (mut cpu, mut mem) = setup_interrupt_test(0x1F, 0x1E);
+
+        cpu.handle_interrupts(&mut mem);
+
+        //NOTE: VBlank is requested but not enabled so the lcd stat interrupt has to win
+        assert_eq!(cpu.pc, 0x48);
+        assert_eq!(mem.read(INTERRUPT_FLAG_REGISTER) & 0x1F, 0x1D);
+    }
+
+    #[test]
+    fn test_interrupt_not_enabled() {
+        let (mut cpu, mut mem) = setup_interrupt_test(0x04, 0x00);
+
+        cpu.handle_interrupts(&mut mem);
+
+        assert_eq!(cpu.pc, 0xC000);
+        assert_eq!(cpu.machine_cycles, 0);
+        assert_eq!(mem.read(INTERRUPT_FLAG_REGISTER) & 0x1F, 0x04);
+    }
+
+    fn load_program(mem: &mut Memory, addr: u16, program: &[u8]) {
+        for (i, byte) in program.iter().enumerate() {
+            mem.write(addr + i as u16, *byte);
+        }
+    }
+
+    #[test]
+    fn test_interrupt_dispatch_clears_ime() {
+        let (mut cpu, mut mem) = setup_interrupt_test(0x01, 0x01);
+
+        cpu.handle_interrupts(&mut mem);
+
+        assert_eq!(cpu.pc, 0x40);
+        assert!(!cpu.interrupts_enabled);
+    }
+
+    #[test]
+    fn test_ei_is_delayed_by_one_instruction() {
+        let (mut cpu, mut mem) = setup_interrupt_test(0x04, 0x04);
+        cpu.interrupts_enabled = false;
+
+        //NOTE: EI, NOP, NOP
+        load_program(&mut mem, 0xC000, &[0xFB, 0x00, 0x00]);
+
+        cpu.cycle(&mut mem).unwrap();
+        assert!(!cpu.interrupts_enabled);
+        assert_eq!(cpu.pc, 0xC001);
+
+        //NOTE: The instruction right after EI still runs before the interrupt gets serviced
+        cpu.cycle(&mut mem).unwrap();
+        assert!(cpu.interrupts_enabled);
+        assert_eq!(cpu.pc, 0xC002);
+
+        cpu.cycle(&mut mem).unwrap();
+        assert_eq!(cpu.sp, 0xFFFC);
+        assert_eq!(mem.read(0xFFFC), 0x02);
+        assert!(!cpu.interrupts_enabled);
+    }
+
+    #[test]
+    fn test_di_cancels_scheduled_ei() {
+        let (mut cpu, mut mem) = setup_interrupt_test(0x00, 0x00);
+        cpu.interrupts_enabled = false;
+
+        //NOTE: EI, DI, NOP
+        load_program(&mut mem, 0xC000, &[0xFB, 0xF3, 0x00]);
+
+        cpu.cycle(&mut mem).unwrap();
+        cpu.cycle(&mut mem).unwrap();
+        cpu.cycle(&mut mem).unwrap();
+
+        assert!(!cpu.interrupts_enabled);
+    }
+
+    #[test]
+    fn test_reti_enables_ime() {
+        let (mut cpu, mut mem) = setup_interrupt_test(0x00, 0x00);
+        cpu.interrupts_enabled = false;
+        cpu.sp = 0xDFFE;
+
+        mem.write(0xDFFE, 0x34);
+        mem.write(0xDFFF, 0x12);
+        load_program(&mut mem, 0xC000, &[0xD9]);
+
+        cpu.cycle(&mut mem).unwrap();
+
+        assert_eq!(cpu.pc, 0x1234);
+        assert!(cpu.interrupts_enabled);
+    }
+
+    #[test]
+    fn test_halt_bug() {
+        let (mut cpu, mut mem) = setup_interrupt_test(0x04, 0x04);
+        cpu.interrupts_enabled = false;
+
+        //NOTE: HALT, INC A
+        load_program(&mut mem, 0xC000, &[0x76, 0x3C]);
+        cpu.regs.write_value8_to(RegByte::A, 0x00);
+
+        cpu.cycle(&mut mem).unwrap();
+        assert!(!cpu.halt_mode_enabled);
+
+        //NOTE: INC A is fetched twice because the pc did not advance after the first fetch
+        cpu.cycle(&mut mem).unwrap();
+        assert_eq!(cpu.pc, 0xC001);
+        cpu.cycle(&mut mem).unwrap();
+        assert_eq!(cpu.pc, 0xC002);
+
+        assert_eq!(cpu.regs.read_value8_from(RegByte::A), 0x02);
+    }
+
+    #[test]
+    fn test_halt_wakes_up_without_ime() {
+        let (mut cpu, mut mem) = setup_interrupt_test(0x00, 0x04);
+        cpu.interrupts_enabled = false;
+
+        //NOTE: HALT, NOP
+        load_program(&mut mem, 0xC000, &[0x76, 0x00]);
+
+        cpu.cycle(&mut mem).unwrap();
+        assert!(cpu.halt_mode_enabled);
+
+        cpu.cycle(&mut mem).unwrap();
+        assert!(cpu.halt_mode_enabled);
+        assert_eq!(cpu.pc, 0xC001);
+
+        mem.request_interrupt(Interrupt::Timer);
+
+        //NOTE: The cpu leaves halt mode but does not jump to the interrupt vector
+        cpu.cycle(&mut mem).unwrap();
+        assert!(!cpu.halt_mode_enabled);
+        assert_eq!(cpu.pc, 0xC002);
+    }
+
+    #[test]
+    fn test_stop_resets_div_and_halts_the_clock() {
+        let (mut cpu, mut mem) = setup_interrupt_test(0x00, 0x00);
+
+        //NOTE: STOP, NOP
+        load_program(&mut mem, 0xC000, &[0x10, 0x00, 0x00]);
+        mem.write(LCDC_REGISTER, 0x80);
+        cpu.machine_cycles = 0x400;
+        mem.update(cpu.machine_cycles);
+        assert_ne!(mem.read(DIV_CLOCK_REGISTER), 0x00);
+
+        cpu.cycle(&mut mem).unwrap();
+        assert!(cpu.stop_mode_enabled);
+        assert_eq!(cpu.pc, 0xC002);
+        assert_eq!(mem.read(DIV_CLOCK_REGISTER), 0x00);
+
+        //NOTE: The cycles keep counting but DIV and the lcd stand still
+        let cycles = cpu.machine_cycles;
+        let ly = mem.read(LY_REGISTER);
+        for _ in 0..0x400 {
+            cpu.cycle(&mut mem).unwrap();
+        }
+
+        assert_eq!(cpu.pc, 0xC002);
+        assert_eq!(cpu.machine_cycles, cycles + 0x400);
+        assert_eq!(mem.read(DIV_CLOCK_REGISTER), 0x00);
+        assert_eq!(mem.read(LY_REGISTER), ly);
+
+        //NOTE: A pressed button wakes the cpu up and the clock continues from where it stopped
+        mem.write(JOYPAD_REGISTER, 0x10);
+        mem.get_joypad_mut().press(Button::A);
+        cpu.cycle(&mut mem).unwrap();
+
+        assert!(!cpu.stop_mode_enabled);
+        assert_eq!(cpu.pc, 0xC003);
+        assert_eq!(mem.read(DIV_CLOCK_REGISTER), 0x00);
+    }
+
+    #[test]
+    fn test_stop_switches_speed_in_cgb_mode() {
+        let (mut cpu, mut mem) = setup_interrupt_test(0x00, 0x00);
+
+        let mut cartridge = vec![0x00; 0x150];
+        cartridge[0x143] = 0x80;
+        mem.load_cartridge(&cartridge).unwrap();
+        load_program(&mut mem, 0xC000, &[0x10, 0x00, 0x00]);
+
+        mem.write(KEY1_REGISTER, 0x01);
+        assert_eq!(mem.read(KEY1_REGISTER), 0x7F);
+
+        cpu.cycle(&mut mem).unwrap();
+
+        assert!(!cpu.stop_mode_enabled);
+        assert!(mem.is_double_speed());
+        assert_eq!(mem.read(KEY1_REGISTER), 0xFE);
+        assert_eq!(cpu.pc, 0xC002);
+    }
+
+    #[test]
+    fn test_key1_unmapped_in_dmg_mode() {
+        let (_, mut mem) = setup_interrupt_test(0x00, 0x00);
+
+        mem.write(KEY1_REGISTER, 0x01);
+
+        assert_eq!(mem.read(KEY1_REGISTER), 0xFF);
+        assert!(!mem.is_speed_switch_armed());
+    }
+
+    #[test]
+    fn test_illegal_opcode_locks_up_the_cpu() {
+        let (mut cpu, mut mem) = setup_interrupt_test(0x00, 0x00);
+
+        //NOTE: NOP, 0xD3 (illegal), NOP
+        load_program(&mut mem, 0xC000, &[0x00, 0xD3, 0x00]);
+
+        assert_eq!(cpu.cycle(&mut mem), Ok(()));
+        assert_eq!(
+            cpu.cycle(&mut mem),
+            Err(CpuError::IllegalOpcode {
+                pc: 0xC001,
+                opcode: 0xD3
+            })
+        );
+
+        let cycles = cpu.machine_cycles;
+
+        assert_eq!(
+            cpu.cycle(&mut mem),
+            Err(CpuError::LockedUp {
+                pc: 0xC001,
+                opcode: 0xD3
+            })
+        );
+        assert_eq!(cpu.machine_cycles, cycles);
+    }
+
+    #[test]
+    fn test_all_illegal_opcodes_are_reported() {
+        let illegal_opcodes = [
+            0xD3, 0xDB, 0xDD, 0xE3, 0xE4, 0xEB, 0xEC, 0xED, 0xF4, 0xFC, 0xFD,
+        ];
+
+        for opcode in illegal_opcodes {
+            let (mut cpu, mut mem) = setup_interrupt_test(0x00, 0x00);
+            load_program(&mut mem, 0xC000, &[opcode]);
+
+            assert_eq!(
+                cpu.cycle(&mut mem),
+                Err(CpuError::IllegalOpcode { pc: 0xC000, opcode })
+            );
+        }
+    }
+
+    fn is_control_flow(opcode: Opcode) -> bool {
+        matches!(
+            opcode,
+            Opcode::Jr
+                | Opcode::Jp
+                | Opcode::Call
+                | Opcode::Ret
+                | Opcode::Reti
+                | Opcode::Rst
+                | Opcode::Stop
+                | Opcode::Halt
+        )
+    }
+
+    #[test]
+    fn test_cpu_and_disassembler_agree_on_all_opcodes() {
+        for prefixed in [false, true] {
+            for opcode in 0x00..=0xFFu8 {
+                //NOTE: The prefix byte itself is covered by the prefixed half of the table
+                if !prefixed && opcode == 0xCB {
+                    continue;
+                }
+
+                let (program, desc) = if prefixed {
+                    ([0xCB, opcode, 0x00], decoder::decode_prefixed(opcode))
+                } else {
+                    ([opcode, 0x00, 0x00], decoder::decode(opcode))
+                };
+
+                let mut mem = Memory::default();
+                load_program(&mut mem, 0xC000, &program);
+
+                let assembly = AssemblyDesc::disassemble(0xC000, &mem.get_mem_snapshot());
+                assert_eq!(
+                    assembly.opcode, desc.opcode,
+                    "{:?} {:02X}",
+                    prefixed, opcode
+                );
+
+                if desc.opcode == Opcode::Byte {
+                    assert_eq!(assembly.size, 1);
+                    continue;
+                }
+                assert_eq!(assembly.size, desc.length, "{:?} {:02X}", prefixed, opcode);
+
+                if is_control_flow(desc.opcode) {
+                    continue;
+                }
+
+                let mut cpu = Cpu {
+                    pc: 0xC000,
+                    sp: 0xDFF0,
+                    ..Default::default()
+                };
+                cpu.regs.write_value16_to(RegWord::Hl, 0xD000);
+                cpu.cycle(&mut mem).unwrap();
+
+                assert_eq!(
+                    cpu.pc,
+                    0xC000 + desc.length as u16,
+                    "{:?} {:02X}",
+                    prefixed,
+                    opcode
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_memory_access_sees_the_timer_of_its_own_m_cycle() {
+        //NOTE: LD A,($FF04) fetches three bytes and reads DIV in its fourth machine cycle, DIV
+        //      increments every 64 machine cycles.
+        for (start, expected_div) in [(59, 0x00), (60, 0x01)] {
+            let mut cpu = Cpu {
+                pc: 0xC000,
+                machine_cycles: start,
+                ..Default::default()
+            };
+            let mut mem = Memory::default();
+            load_program(&mut mem, 0xC000, &[0xFA, 0x04, 0xFF]);
+            mem.update(cpu.machine_cycles);
+
+            cpu.cycle(&mut mem).unwrap();
+
+            assert_eq!(cpu.regs.read_value8_from(RegByte::A), expected_div);
+            assert_eq!(cpu.machine_cycles, start + 4);
+        }
+    }
+
+    //NOTE: Reference machine cycles taken from the tables in blargg's instr_timing test, a zero
+    //      marks an opcode that isn't timed (illegal, STOP, HALT and the CB prefix).
+    #[rustfmt::skip]
+    const REFERENCE_CYCLES: [usize; 256] = [
+        1, 3, 2, 2, 1, 1, 2, 1, 5, 2, 2, 2, 1, 1, 2, 1,
+        0, 3, 2, 2, 1, 1, 2, 1, 3, 2, 2, 2, 1, 1, 2, 1,
+        2, 3, 2, 2, 1, 1, 2, 1, 2, 2, 2, 2, 1, 1, 2, 1,
+        2, 3, 2, 2, 3, 3, 3, 1, 2, 2, 2, 2, 1, 1, 2, 1,
+        1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
+        1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
+        1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
+        2, 2, 2, 2, 2, 2, 0, 2, 1, 1, 1, 1, 1, 1, 2, 1,
+        1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
+        1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
+        1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
+        1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
+        2, 3, 3, 4, 3, 4, 2, 4, 2, 4, 3, 0, 3, 6, 2, 4,
+        2, 3, 3, 0, 3, 4, 2, 4, 2, 4, 3, 0, 3, 0, 2, 4,
+        3, 3, 2, 0, 0, 4, 2, 4, 4, 1, 4, 0, 0, 0, 2, 4,
+        3, 3, 2, 1, 0, 4, 2, 4, 3, 2, 4, 1, 0, 0, 2, 4,
+    ];
+
+    #[rustfmt::skip]
+    const REFERENCE_BRANCH_CYCLES: [(u8, usize); 16] = [
+        (0x20, 3), (0x28, 3), (0x30, 3), (0x38, 3),
+        (0xC2, 4), (0xCA, 4), (0xD2, 4), (0xDA, 4),
+        (0xC4, 6), (0xCC, 6), (0xD4, 6), (0xDC, 6),
+        (0xC0, 5), (0xC8, 5), (0xD0, 5), (0xD8, 5),
+    ];
+
+    fn reference_prefixed_cycles(opcode: u8) -> usize {
+        match (opcode & 0x07, opcode >> 6) {
+            (0x06, 0x01) => 3,
+            (0x06, _) => 4,
+            _ => 2,
+        }
+    }
+
+    fn measure_cycles(program: &[u8], flags: u8) -> usize {
+        let mut cpu = Cpu {
+            pc: 0xC000,
+            sp: 0xDFF0,
+            ..Default::default()
+        };
+        let mut mem = Memory::default();
+        load_program(&mut mem, 0xC000, program);
+        cpu.regs.write_value16_to(RegWord::Hl, 0xD000);
+        cpu.regs.write_value8_to(RegByte::F, flags);
+
+        cpu.cycle(&mut mem).unwrap();
+
+        cpu.machine_cycles
+    }
+
+    #[test]
+    fn test_instruction_timing_matches_reference() {
+        for opcode in 0x00..=0xFFu8 {
+            let reference = REFERENCE_CYCLES[opcode as usize];
+            let desc = decoder::decode(opcode);
+
+            if reference == 0 {
+                continue;
+            }
+
+            let branch_reference = REFERENCE_BRANCH_CYCLES
+                .iter()
+                .find(|(branch_opcode, _)| *branch_opcode == opcode)
+                .map_or(reference, |(_, cycles)| *cycles);
+
+            assert_eq!(desc.cycles as usize, reference, "table {:02X}", opcode);
+            assert_eq!(
+                desc.branch_cycles as usize, branch_reference,
+                "table {:02X}",
+                opcode
+            );
+
+            //NOTE: With all flags cleared NZ/NC branches are taken, with all flags set Z/C are
+            let taken_without_flags = matches!(
+                desc.dest,
+                Operand::Condition(Flag::NotZero) | Operand::Condition(Flag::NotCarry)
+            );
+            let (no_flags, all_flags) = if taken_without_flags {
+                (branch_reference, reference)
+            } else {
+                (reference, branch_reference)
+            };
+
+            let program = [opcode, 0x00, 0x00];
+            assert_eq!(
+                measure_cycles(&program, 0x00),
+                no_flags,
+                "cpu {:02X}",
+                opcode
+            );
+            assert_eq!(
+                measure_cycles(&program, 0xF0),
+                all_flags,
+                "cpu {:02X}",
+                opcode
+            );
+        }
+    }
+
+    #[test]
+    fn test_prefixed_instruction_timing_matches_reference() {
+        for opcode in 0x00..=0xFFu8 {
+            let reference = reference_prefixed_cycles(opcode);
+            let desc = decoder::decode_prefixed(opcode);
+
+            assert_eq!(desc.cycles as usize, reference, "table CB {:02X}", opcode);
+            assert_eq!(
+                desc.branch_cycles as usize, reference,
+                "table CB {:02X}",
+                opcode
+            );
+            assert_eq!(
+                measure_cycles(&[0xCB, opcode], 0x00),
+                reference,
+                "cpu CB {:02X}",
+                opcode
+            );
+        }
+    }
+
+    const INSTR_TIMING_ROM: &str = "test_roms/blarggs/instr_timing/instr_timing.gb";
+
+    #[test]
+    #[ignore = "needs the blargg instr_timing rom in test_roms/"]
+    fn test_blargg_instr_timing() {
+        let report = harness::run_rom(Path::new(INSTR_TIMING_ROM), 10_000_000);
+        assert_eq!(report.verdict, Verdict::Passed, "{}", report.details);
+    }
+
+    const CPU_INSTRS_ROMS: &str = "test_roms/blarggs/cpu_instrs/individual";
+
+    #[test]
+    #[ignore = "needs the blargg cpu_instrs roms in test_roms/"]
+    fn test_blargg_cpu_instrs() {
+        let roms = harness::find_roms(Path::new(CPU_INSTRS_ROMS)).expect(CPU_INSTRS_ROMS);
+
+        let reports = harness::run_roms(&roms, harness::DEFAULT_CYCLE_LIMIT);
+        assert!(
+            harness::all_passed(&reports),
+            "{}",
+            harness::summary_table(&reports)
+        );
+    }
+
+    #[test]
+    #[ignore = "needs the mooneye timer roms in test_roms/"]
+    fn test_mooneye_timer() {
+        let roms: Vec<_> = [
+            "div_write",
+            "rapid_toggle",
+            "tim00",
+            "tim00_div_trigger",
+            "tim01",
+            "tim01_div_trigger",
+            "tim10",
+            "tim10_div_trigger",
+            "tim11",
+            "tim11_div_trigger",
+            "tima_reload",
+            "tima_write_reloading",
+            "tma_write_reloading",
+        ]
+        .iter()
+        .map(|name| {
+            PathBuf::from(format!(
+                "test_roms/mooneye-tests/acceptance/timer/{}.gb",
+                name
+            ))
+        })
+        .collect();
+
+        let reports = harness::run_roms(&roms, 10_000_000);
+        assert!(
+            harness::all_passed(&reports),
+            "{}",
+            harness::summary_table(&reports)
+        );
+    }
+
+    #[test]
+    fn test_post_boot_registers() {
+        let mut mem = Memory::default();
+        let mut cartridge = vec![0x00; 0x150];
+        cartridge[HEADER_CHECKSUM_ADDRESS as usize] = 0x42;
+        mem.load_cartridge(&cartridge).unwrap();
+
+        let cpu = Cpu::post_boot(Model::Dmg, &mem);
+
+        assert_eq!(cpu.regs.read_value16_from(RegWord::Af), 0x01B0);
+        assert_eq!(cpu.regs.read_value16_from(RegWord::Bc), 0x0013);
+        assert_eq!(cpu.regs.read_value16_from(RegWord::De), 0x00D8);
+        assert_eq!(cpu.regs.read_value16_from(RegWord::Hl), 0x014D);
+        assert_eq!(cpu.sp, 0xFFFE);
+        assert_eq!(cpu.pc, 0x0100);
+    }
+
+    #[test]
+    fn test_boot_rom_hands_over_to_the_cartridge() {
+        let mut mem = Memory::default();
+        mem.load_cartridge(&[0x00; 0x200]).unwrap();
+        //NOTE: LD A,$01; LDH ($50),A; JP $0100 placed right before the cartridge entry point
+        let mut boot_rom = vec![0x00; 0x100];
+        boot_rom[0xF7..0xFC].copy_from_slice(&[0x3E, 0x01, 0xE0, 0x50, 0xC3]);
+        boot_rom[0xFC..0xFE].copy_from_slice(&[0x00, 0x01]);
+        mem.load_boot_rom(&boot_rom);
+
+        let mut cpu = Cpu::power_on();
+        while cpu.pc != 0x0100 {
+            cpu.cycle(&mut mem).unwrap();
+        }
+
+        assert!(!mem.is_boot_rom_mapped());
+    }
+}
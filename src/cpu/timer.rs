@@ -1,153 +1,265 @@
-use crate::memory::Memory;
+use std::ops::RangeInclusive;
 
-trait TimerTick {
-    fn tick(&mut self, t_cycles: usize, reset: usize) -> RaiseInterruptFlag;
-}
+use crate::memory::bus::IoDevice;
+use crate::memory::{
+    DIV_CLOCK_REGISTER, TAC_CLOCK_REGISTER, TIMA_CLOCK_REGISTER, TMA_CLOCK_REGISTER,
+};
 
-trait DmaRead {
-    fn dma_read(&self) -> u8;
-}
-
-trait DmaWrite {
-    fn dma_write(&mut self, addr: u16, value: u8);
-}
+//NOTE: The bit of the system counter that clocks TIMA for each clock select of TAC
+const TAC_COUNTER_BITS: [u16; 4] = [9, 3, 5, 7];
+const TAC_ENABLE: u8 = 0x04;
 
 pub enum RaiseInterruptFlag {
     CustomTimerOverflowed,
     NoChanges,
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum TimaState {
+    Counting,
+    //NOTE: TIMA overflowed in this machine cycle and reads as zero. Writing TIMA now cancels the
+    //      reload and the interrupt.
+    Overflowed,
+    //NOTE: TMA got copied into TIMA in this machine cycle. Writes to TIMA are lost, writes to TMA
+    //      end up in TIMA as well.
+    Reloading,
+}
+
+//NOTE: The timer hangs off a 16 bit system counter that counts every t-cycle, DIV is its upper
+//      byte. TIMA doesn't count cycles but the falling edges of one counter bit ANDed with the
+//      enable bit of TAC, so resetting DIV or changing TAC can increment it as well.
 #[derive(Debug)]
 pub struct TimerController {
-    div: DivRegister,
-    tima: TimaRegister,
+    counter: u16,
+    tima: u8,
+    tma: u8,
+    tac: u8,
+    state: TimaState,
 
-    t_cycles: usize,
     last_update: usize,
 }
 
 impl TimerController {
     pub fn new() -> Self {
         Self {
-            div: DivRegister::new(),
-            tima: TimaRegister::new(),
+            counter: 0x0000,
+            tima: 0x00,
+            tma: 0x00,
+            tac: 0x00,
+            state: TimaState::Counting,
 
-            t_cycles: 0x00,
             last_update: 0x00,
         }
     }
 
+    //NOTE: Any write to DIV resets the whole counter
     pub fn timer_reset(&mut self) {
-        self.div.internal_timer = 0;
-        self.tima.last_tima_update = 0x00;
+        self.set_counter(0x0000);
     }
 
-    pub fn read(&self, addr: u16) -> u8 {
-        match addr {
-            0xFF04 => (self.div.internal_timer >> 8) as u8,
-            0xFF05 => self.tima.tima_reg,
-            0xFF06 => self.tima.tma_reg,
-            0xFF07 => self.tima.tac_reg,
-            _ => panic!("I/O Address out of timer range !"),
-        }
+    //NOTE: Only for the post boot state, unlike a reset this doesn't clock TIMA
+    pub fn set_div_counter(&mut self, value: u16) {
+        self.counter = value;
     }
 
-    pub fn write(&mut self, addr: u16, value: u8) {
-        match addr {
-            0xFF05 => self.tima.tima_reg = value,
-            0xFF06 => self.tima.tma_reg = value,
-            0xFF07 => self.tima.tac_reg = value,
-            _ => panic!("Invalid address for the timer register"),
-        }
+    //NOTE: The full system counter, DIV only shows its upper byte
+    pub fn get_div_counter(&self) -> u16 {
+        self.counter
+    }
+
+    //NOTE: The system counter doesn't run in stop mode, the time passes without a single step
+    pub fn skip(&mut self, m_cycles: usize) {
+        self.last_update = m_cycles;
     }
 
+    //NOTE: Brings the timer up to the given machine cycle, the counter advances by four t-cycles
+    //      per machine cycle in both speeds.
     pub fn update(&mut self, m_cycles: usize) -> RaiseInterruptFlag {
-        self.t_cycles = m_cycles * 4;
-        let _ = self.div.tick(self.t_cycles - self.last_update, 0x00);
-        let timer_overflow = self.tima.tick(self.div.internal_timer, 0x00);
-        self.last_update = self.t_cycles;
+        let elapsed = m_cycles.saturating_sub(self.last_update);
+        self.last_update = m_cycles;
+
+        let mut interrupt_requested = false;
+        for _ in 0..elapsed {
+            interrupt_requested |= self.step();
+        }
+
+        if !interrupt_requested {
+            return RaiseInterruptFlag::NoChanges;
+        }
 
-        timer_overflow
+        RaiseInterruptFlag::CustomTimerOverflowed
     }
-}
 
-#[derive(Debug)]
-struct DivRegister {
-    internal_timer: usize,
-}
+    //NOTE: One machine cycle, the reload and the interrupt happen one cycle after the overflow
+    fn step(&mut self) -> bool {
+        let reloaded = match self.state {
+            TimaState::Overflowed => {
+                self.tima = self.tma;
+                self.state = TimaState::Reloading;
+                true
+            }
+            TimaState::Reloading => {
+                self.state = TimaState::Counting;
+                false
+            }
+            TimaState::Counting => false,
+        };
 
-impl DivRegister {
-    fn new() -> Self {
-        Self { internal_timer: 0 }
+        self.set_counter(self.counter.wrapping_add(4));
+
+        reloaded
     }
-}
 
-impl TimerTick for DivRegister {
-    fn tick(&mut self, t_cycles: usize, reset: usize) -> RaiseInterruptFlag {
-        self.internal_timer += t_cycles;
-        RaiseInterruptFlag::NoChanges
+    fn timer_signal(&self) -> bool {
+        let bit = TAC_COUNTER_BITS[(self.tac & 0x03) as usize];
+
+        self.tac & TAC_ENABLE != 0 && self.counter & (1 << bit) != 0
     }
-}
 
-#[derive(Debug)]
-pub struct TimaRegister {
-    tima_reg: u8,
-    last_tima_update: usize,
+    fn set_counter(&mut self, counter: u16) {
+        let signal = self.timer_signal();
+        self.counter = counter;
+        self.detect_falling_edge(signal);
+    }
+
+    fn write_tac(&mut self, value: u8) {
+        let signal = self.timer_signal();
+        self.tac = value & 0x07;
+        self.detect_falling_edge(signal);
+    }
 
-    tac_reg: u8,
-    tma_reg: u8,
+    fn detect_falling_edge(&mut self, signal: bool) {
+        if signal && !self.timer_signal() {
+            self.increment_tima();
+        }
+    }
+
+    fn increment_tima(&mut self) {
+        let (value, overflowed) = self.tima.overflowing_add(1);
+        self.tima = value;
+
+        if overflowed {
+            self.state = TimaState::Overflowed;
+        }
+    }
+
+    fn write_tima(&mut self, value: u8) {
+        match self.state {
+            TimaState::Overflowed => {
+                self.tima = value;
+                self.state = TimaState::Counting;
+            }
+            TimaState::Reloading => (),
+            TimaState::Counting => self.tima = value,
+        }
+    }
+
+    fn write_tma(&mut self, value: u8) {
+        self.tma = value;
+
+        if self.state == TimaState::Reloading {
+            self.tima = value;
+        }
+    }
 }
 
-impl TimaRegister {
-    const TAC_DIVIDER_TABLE: [usize; 4] = [1024, 16, 64, 256];
-    const TAC_REGISTER_ADDRESS: usize = 0xFF07;
-    const TIMA_REGISTER_ADDRESS: usize = 0xFF05;
+impl IoDevice for TimerController {
+    fn address_ranges(&self) -> &'static [RangeInclusive<u16>] {
+        &[DIV_CLOCK_REGISTER..=TAC_CLOCK_REGISTER]
+    }
 
-    fn new() -> Self {
-        Self {
-            tima_reg: 0x00,
-            last_tima_update: 0x00,
+    fn read(&self, addr: u16) -> u8 {
+        match addr {
+            DIV_CLOCK_REGISTER => (self.counter >> 8) as u8,
+            TIMA_CLOCK_REGISTER => self.tima,
+            TMA_CLOCK_REGISTER => self.tma,
+            //NOTE: Only the lower three bits of TAC exist, the others read as 1
+            TAC_CLOCK_REGISTER => 0xF8 | self.tac,
+            _ => panic!("I/O Address out of timer range !"),
+        }
+    }
 
-            tac_reg: 0x00,
-            tma_reg: 0x00,
+    fn write(&mut self, addr: u16, value: u8) {
+        match addr {
+            DIV_CLOCK_REGISTER => self.timer_reset(),
+            TIMA_CLOCK_REGISTER => self.write_tima(value),
+            TMA_CLOCK_REGISTER => self.write_tma(value),
+            TAC_CLOCK_REGISTER => self.write_tac(value),
+            _ => panic!("Invalid address for the timer register"),
         }
     }
 }
 
-impl TimerTick for TimaRegister {
-    fn tick(&mut self, timer: usize, reset: usize) -> RaiseInterruptFlag {
-        let enabled = (self.tac_reg >> 2) & 0x01 == 0x01;
+#[cfg(test)]
+mod test {
+    use super::*;
 
-        let mut interrupt_requested = false;
-        if enabled {
-            let div_index = (self.tac_reg & 0x03) as usize;
-            let timer_resolution = Self::TAC_DIVIDER_TABLE[div_index];
+    fn overflowed(flag: RaiseInterruptFlag) -> bool {
+        matches!(flag, RaiseInterruptFlag::CustomTimerOverflowed)
+    }
 
-            let mut elapsed = timer - self.last_tima_update;
+    #[test]
+    fn test_tima_counts_falling_edges() {
+        let mut timer = TimerController::new();
+        timer.write(TAC_CLOCK_REGISTER, 0x05);
 
-            while elapsed >= timer_resolution {
-                let (new_value, overflowed) = self.tima_reg.overflowing_add(1);
-                self.tima_reg = new_value;
+        //NOTE: Bit 3 falls every 16 t-cycles, four machine cycles
+        timer.update(3);
+        assert_eq!(timer.read(TIMA_CLOCK_REGISTER), 0x00);
+        timer.update(4);
+        assert_eq!(timer.read(TIMA_CLOCK_REGISTER), 0x01);
+        timer.update(64);
+        assert_eq!(timer.read(TIMA_CLOCK_REGISTER), 0x10);
+    }
 
-                elapsed -= timer_resolution;
+    #[test]
+    fn test_div_and_tac_writes_clock_tima() {
+        let mut timer = TimerController::new();
+        timer.write(TAC_CLOCK_REGISTER, 0x05);
+        timer.update(2);
 
-                if overflowed {
-                    //TODO: We need to set the if flags here ! in order to fire any
-                    //interrupt
-                    self.tima_reg = self.tma_reg;
-                    //NOTE: Not sure if we should handle the request before we starting to increase
-                    //the timer again but let's try it this way first...
-                    interrupt_requested = true;
-                }
+        //NOTE: Bit 3 is set, resetting the counter is a falling edge
+        timer.write(DIV_CLOCK_REGISTER, 0x00);
+        assert_eq!(timer.read(TIMA_CLOCK_REGISTER), 0x01);
 
-                self.last_tima_update = timer - elapsed;
-            }
-        }
+        //NOTE: So is turning the timer off or picking a bit that is cleared
+        timer.update(4);
+        timer.write(TAC_CLOCK_REGISTER, 0x01);
+        assert_eq!(timer.read(TIMA_CLOCK_REGISTER), 0x02);
+        timer.write(TAC_CLOCK_REGISTER, 0x05);
+        timer.write(TAC_CLOCK_REGISTER, 0x06);
+        assert_eq!(timer.read(TIMA_CLOCK_REGISTER), 0x03);
+    }
 
-        if !interrupt_requested {
-            return RaiseInterruptFlag::NoChanges;
-        }
+    #[test]
+    fn test_reload_is_delayed_by_one_cycle() {
+        let mut timer = TimerController::new();
+        timer.write(TMA_CLOCK_REGISTER, 0x23);
+        timer.write(TIMA_CLOCK_REGISTER, 0xFF);
+        timer.write(TAC_CLOCK_REGISTER, 0x05);
 
-        RaiseInterruptFlag::CustomTimerOverflowed
+        assert!(!overflowed(timer.update(4)));
+        assert_eq!(timer.read(TIMA_CLOCK_REGISTER), 0x00);
+        assert!(overflowed(timer.update(5)));
+        assert_eq!(timer.read(TIMA_CLOCK_REGISTER), 0x23);
+
+        //NOTE: The write in the reload cycle gets lost, TMA wins
+        timer.write(TIMA_CLOCK_REGISTER, 0x42);
+        assert_eq!(timer.read(TIMA_CLOCK_REGISTER), 0x23);
+        timer.write(TMA_CLOCK_REGISTER, 0x56);
+        assert_eq!(timer.read(TIMA_CLOCK_REGISTER), 0x56);
+    }
+
+    #[test]
+    fn test_tima_write_cancels_the_reload() {
+        let mut timer = TimerController::new();
+        timer.write(TMA_CLOCK_REGISTER, 0x23);
+        timer.write(TIMA_CLOCK_REGISTER, 0xFF);
+        timer.write(TAC_CLOCK_REGISTER, 0x05);
+
+        timer.update(4);
+        timer.write(TIMA_CLOCK_REGISTER, 0x42);
+        assert!(!overflowed(timer.update(5)));
+        assert_eq!(timer.read(TIMA_CLOCK_REGISTER), 0x42);
     }
 }
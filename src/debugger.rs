@@ -1,14 +1,26 @@
-use std::{collections::HashMap, collections::HashSet, fs::File, io::Read};
+use std::{
+    collections::HashMap,
+    collections::HashSet,
+    fs::File,
+    io::{ErrorKind, Read},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
 
 use egui::Color32;
 
 use crate::{
+    cartridge::{rtc::RtcMode, CartridgeError},
     cpu::{
         register::{RegByte, RegWord},
-        Cpu,
+        Cpu, CpuError,
     },
     disassembler::AssemblyDesc,
+    joypad::Button,
     memory::Memory,
+    model::Model,
+    ppu::{Renderer, LY_REGISTER},
+    serial::link::SerialLink,
 };
 
 //NOTE:  Gameboy Memory Map:
@@ -19,12 +31,27 @@ use crate::{
 //0xA000    0xBFFF   8Kb    External Memory
 //0xC000    0xCFFF   4Kb    Working Ram
 //0xD000    0xDFFF   4Kb    Working Ram switchable
-//0xE000    0xFDFF   8Kb    Mirror of 0xC000 -> 0xDFFF
+//0xE000    0xFDFF   8Kb    Mirror of 0xC000 -> 0xDDFF
+//0xFE00    0xFE9F  160b    Object Attribute Memory
+//0xFEA0    0xFEFF   96b    Unusable
+//0xFF00    0xFF7F  128b    I/O Registers
+//0xFF80    0xFFFE  127b    High Ram
+//0xFFFF    0xFFFF    1b    Interrupt Enable Register
+
+const SAVE_INTERVAL: Duration = Duration::from_secs(5);
 
 pub struct Debugger {
     cpu: Cpu,
     memory: Memory,
     breakpoints: HashSet<u16>,
+    cpu_error: Option<CpuError>,
+    cartridge_warnings: Vec<CartridgeError>,
+    model: Model,
+    //NOTE: Only battery backed cartridges get a .sav file
+    save_path: Option<PathBuf>,
+    last_save: Instant,
+    //NOTE: Where audio recordings go, defaults to a .wav file next to the rom
+    recording_path: PathBuf,
 }
 
 impl Debugger {
@@ -33,15 +60,153 @@ impl Debugger {
             cpu: Cpu::default(),
             memory: Memory::default(),
             breakpoints: HashSet::new(),
+            cpu_error: None,
+            cartridge_warnings: Vec::new(),
+            model: Model::Dmg,
+            save_path: None,
+            last_save: Instant::now(),
+            recording_path: PathBuf::from("recording.wav"),
         }
     }
 
-    pub fn load_cartridge(&mut self, path: &str) {
-        let mut file = File::open(path).unwrap();
+    pub fn set_rtc_mode(&mut self, rtc_mode: RtcMode) {
+        self.memory.set_rtc_mode(rtc_mode);
+    }
+
+    pub fn set_ppu_renderer(&mut self, renderer: Renderer) {
+        self.memory.set_ppu_renderer(renderer);
+    }
+
+    //NOTE: Broken checksums don't stop us from running the cartridge, they only get reported
+    pub fn load_cartridge(&mut self, path: &str) -> Result<(), CartridgeError> {
         let mut buffer = Vec::new();
 
-        file.read_to_end(&mut buffer).unwrap();
-        self.memory.load_cartridge(&buffer);
+        File::open(path)
+            .and_then(|mut file| file.read_to_end(&mut buffer))
+            .map_err(|error| CartridgeError::Unreadable(error.kind()))?;
+
+        self.memory.load_cartridge(&buffer)?;
+        self.cartridge_warnings = self
+            .memory
+            .get_cartridge_header()
+            .map(|header| header.verify_checksums(&buffer))
+            .unwrap_or_default();
+
+        self.recording_path = Path::new(path).with_extension("wav");
+
+        self.save_path = None;
+        if self.memory.get_cartridge_mut().has_battery() {
+            let save_path = Path::new(path).with_extension("sav");
+
+            match std::fs::read(&save_path) {
+                Ok(data) => self.memory.get_cartridge_mut().load_save_data(&data),
+                Err(error) if error.kind() == ErrorKind::NotFound => (),
+                Err(error) => return Err(CartridgeError::UnreadableSave(error.kind())),
+            }
+
+            self.save_path = Some(save_path);
+        }
+
+        Ok(())
+    }
+
+    //NOTE: Writes the external ram into the .sav file next to the rom, but only if the game wrote
+    //      into it since the last time.
+    pub fn save(&mut self) -> std::io::Result<()> {
+        self.last_save = Instant::now();
+
+        let Some(path) = &self.save_path else {
+            return Ok(());
+        };
+
+        let cartridge = self.memory.get_cartridge_mut();
+        if !cartridge.is_ram_dirty() {
+            return Ok(());
+        }
+
+        std::fs::write(path, cartridge.save_data())
+    }
+
+    pub fn save_periodically(&mut self) -> std::io::Result<()> {
+        if self.last_save.elapsed() < SAVE_INTERVAL {
+            return Ok(());
+        }
+
+        self.save()
+    }
+
+    pub fn get_cartridge_warnings(&self) -> &[CartridgeError] {
+        &self.cartridge_warnings
+    }
+
+    pub fn get_cartridge_header(&self) -> Vec<(&'static str, String)> {
+        let Some(header) = self.memory.get_cartridge_header() else {
+            return Vec::new();
+        };
+
+        vec![
+            ("TITLE:", header.title.clone()),
+            (
+                "MANUFACTURER:",
+                header.manufacturer_code.clone().unwrap_or_default(),
+            ),
+            ("CGB:", format!("{}", header.cgb_support)),
+            ("SGB:", format!("{}", header.sgb_support)),
+            (
+                "TYPE:",
+                format!(
+                    "{:02X} {}",
+                    header.cartridge_type,
+                    header.cartridge_type_name()
+                ),
+            ),
+            ("BATTERY:", format!("{}", header.has_battery())),
+            ("TIMER:", format!("{}", header.has_timer())),
+            ("ROM SIZE:", format!("{} KiB", header.rom_size / 1024)),
+            ("RAM SIZE:", format!("{} KiB", header.ram_size / 1024)),
+            ("DESTINATION:", format!("{}", header.destination)),
+            ("VERSION:", format!("{:02X}", header.version)),
+            (
+                "HEADER CHECKSUM:",
+                format!("{:02X}", header.header_checksum),
+            ),
+            (
+                "GLOBAL CHECKSUM:",
+                format!("{:04X}", header.global_checksum),
+            ),
+        ]
+    }
+
+    //NOTE: Without an explicit model the cartridge decides, cgb cartridges run on a cgb and all the
+    //      others on a dmg. Without a boot rom we start right at the cartridge entry point.
+    pub fn boot(
+        &mut self,
+        model: Option<Model>,
+        boot_rom_path: Option<&str>,
+    ) -> std::io::Result<()> {
+        self.model = model.unwrap_or(if self.memory.is_cgb_mode() {
+            Model::Cgb
+        } else {
+            Model::Dmg
+        });
+
+        match boot_rom_path {
+            Some(path) => {
+                let mut file = File::open(path)?;
+                let mut buffer = Vec::new();
+
+                file.read_to_end(&mut buffer)?;
+                self.memory.set_model(self.model);
+                self.memory.load_boot_rom(&buffer);
+                self.cpu = Cpu::power_on();
+            }
+            None => {
+                self.memory.apply_post_boot_state(self.model);
+                self.cpu = Cpu::post_boot(self.model, &self.memory);
+            }
+        }
+
+        Ok(())
     }
 
     fn generate_hexdump(instr: &AssemblyDesc, memory: &[u8]) -> [Option<u8>; 3] {
@@ -64,14 +229,14 @@ impl Debugger {
     ) {
         disassembly_cache.clear();
         let mut start = 0x0000;
-        let memory = self.memory.get_mem_slice();
+        let memory = self.memory.get_mem_snapshot();
 
         while start < 0xFFFF {
-            let instruction = AssemblyDesc::disassemble(start, memory);
+            let instruction = AssemblyDesc::disassemble(start, &memory);
 
             start += instruction.size as u16;
 
-            disassembly_cache.push((Self::generate_hexdump(&instruction, memory), instruction));
+            disassembly_cache.push((Self::generate_hexdump(&instruction, &memory), instruction));
             disassembly_map.insert(instruction.offset, disassembly_cache.len() - 1);
         }
     }
@@ -103,6 +268,14 @@ impl Debugger {
         }
     }
 
+    pub fn press(&mut self, button: Button) {
+        self.memory.get_joypad_mut().press(button);
+    }
+
+    pub fn release(&mut self, button: Button) {
+        self.memory.get_joypad_mut().release(button);
+    }
+
     pub fn is_registered_breakpoint(&self, offset: u16) -> bool {
         if self.breakpoints.contains(&offset) {
             true
@@ -153,6 +326,76 @@ impl Debugger {
     pub fn get_timer_reset(&self) -> String {
         format!("{:03}", self.memory.read(0xFF06))
     }
+
+    pub fn get_model(&self) -> String {
+        if self.memory.is_boot_rom_mapped() {
+            format!("{} (BOOT ROM)", self.model)
+        } else {
+            format!("{}", self.model)
+        }
+    }
+
+    pub fn get_cpu_speed(&self) -> String {
+        if self.memory.is_double_speed() {
+            String::from("DOUBLE")
+        } else {
+            String::from("NORMAL")
+        }
+    }
+
+    pub fn get_lcd_line(&self) -> String {
+        format!("{:03}", self.memory.read(LY_REGISTER))
+    }
+
+    pub fn get_lcd_mode(&self) -> String {
+        format!("{}", self.memory.get_ppu().get_mode())
+    }
+
+    pub fn get_frame_count(&self) -> String {
+        format!("{}", self.memory.get_ppu().get_frame_count())
+    }
+
+    pub fn get_framebuffer(&self) -> &[u8] {
+        self.memory.get_ppu().get_framebuffer()
+    }
+
+    pub fn get_serial_output(&self) -> &str {
+        self.memory.get_serial_output()
+    }
+
+    pub fn set_serial_link(&mut self, link: Box<dyn SerialLink>) {
+        self.memory.set_serial_link(link);
+    }
+
+    pub fn take_audio_samples(&mut self) -> Vec<[f32; 2]> {
+        self.memory.take_audio_samples()
+    }
+
+    pub fn set_recording_path(&mut self, path: PathBuf) {
+        self.recording_path = path;
+    }
+
+    pub fn get_recording_path(&self) -> &Path {
+        &self.recording_path
+    }
+
+    //NOTE: `split_channels` writes a track for every channel next to the mix
+    pub fn start_recording(&mut self, split_channels: bool) {
+        self.memory.get_apu_mut().start_capture(split_channels);
+    }
+
+    //NOTE: Writes the recording and returns the files it ended up in, nothing if there was none
+    pub fn stop_recording(&mut self) -> std::io::Result<Vec<PathBuf>> {
+        match self.memory.get_apu_mut().stop_capture() {
+            Some(capture) => capture.save(&self.recording_path),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.memory.get_apu().is_capturing()
+    }
+
     pub fn run(&mut self) {
         //FIXME: If there is no breakpoint this loop is a infinite one and will
         //       not return to the caller.
@@ -164,12 +407,62 @@ impl Debugger {
                 break;
             }
 
-            self.cpu.cycle(&mut self.memory);
+            if self.step().is_err() {
+                break;
+            }
+        }
+    }
+
+    //NOTE: Runs until `done` says so, it gets asked before every instruction and also sees the dots
+    //      the lcd went through so far. Those keep counting while the lcd is turned off and don't
+    //      speed up in double speed mode, so the cpu gets twice the cycles per dot there.
+    //      An instance on the other end of the link cable gets stepped along so neither of them
+    //      runs ahead of the other. If that one locks up it stays where it is and its error is
+    //      left in its `get_cpu_error`, this instance keeps running.
+    pub fn run_until(
+        &mut self,
+        mut linked: Option<&mut Debugger>,
+        mut done: impl FnMut(&Debugger, usize) -> bool,
+    ) -> Result<(), CpuError> {
+        let mut dots = 0;
+
+        while !done(self, dots) {
+            let machine_cycles = self.cpu.machine_cycles;
+            self.step()?;
+
+            if let Some(other) = linked.as_deref_mut() {
+                while other.cpu_error.is_none()
+                    && other.cpu.machine_cycles < self.cpu.machine_cycles
+                {
+                    if other.step().is_err() {
+                        break;
+                    }
+                }
+            }
+
+            let elapsed = self.cpu.machine_cycles - machine_cycles;
+            dots += if self.memory.is_double_speed() {
+                elapsed * 2
+            } else {
+                elapsed * 4
+            };
         }
+
+        Ok(())
+    }
+
+    pub fn step(&mut self) -> Result<(), CpuError> {
+        let result = self.cpu.cycle(&mut self.memory);
+
+        if let Err(error) = result {
+            self.cpu_error = Some(error);
+        }
+
+        result
     }
 
-    pub fn step(&mut self) {
-        self.cpu.cycle(&mut self.memory);
+    pub fn get_cpu_error(&self) -> Option<CpuError> {
+        self.cpu_error
     }
 
     pub fn get_program_counter(&self) -> usize {
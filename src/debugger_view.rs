@@ -1,6 +1,9 @@
 use crate::{
+    apu::SAMPLE_RATE,
     cpu::register::{RegByte, RegWord},
     debugger::Debugger,
+    joypad::Button,
+    ppu::{SCREEN_HEIGHT, SCREEN_WIDTH},
 };
 use eframe::egui;
 use egui::{Align, Color32, RichText};
@@ -9,6 +12,21 @@ use std::collections::HashMap;
 
 use crate::disassembler::AssemblyDesc;
 
+//NOTE: The letters the debugger itself uses (s, r, b, d) are left out
+const BUTTON_KEYS: [(egui::Key, Button); 8] = [
+    (egui::Key::ArrowRight, Button::Right),
+    (egui::Key::ArrowLeft, Button::Left),
+    (egui::Key::ArrowUp, Button::Up),
+    (egui::Key::ArrowDown, Button::Down),
+    (egui::Key::X, Button::A),
+    (egui::Key::Z, Button::B),
+    (egui::Key::C, Button::Select),
+    (egui::Key::Space, Button::Start),
+];
+
+//NOTE: The audio window shows the last frame worth of samples, one frame is roughly 1/60 second
+const SCOPE_FRAMES: usize = SAMPLE_RATE as usize / 60;
+
 //#[derive(Default)]
 pub struct DebuggerView {
     debugger: Debugger,
@@ -16,24 +34,37 @@ pub struct DebuggerView {
     disassembly: Vec<([Option<u8>; 3], AssemblyDesc)>,
     font_size: f32,
     selected_index: Option<usize>,
+    screen: egui::TextureHandle,
+    audio: Vec<[f32; 2]>,
+    split_channels: bool,
+    recording_status: Option<String>,
 }
 
 impl DebuggerView {
-    pub fn new(cc: &eframe::CreationContext<'_>, cartridge: &str) -> Self {
+    pub fn new(cc: &eframe::CreationContext<'_>, debugger: Debugger) -> Self {
         cc.egui_ctx.set_visuals(egui::Visuals {
             dark_mode: true,
             ..egui::Visuals::default()
         });
 
+        let screen = cc.egui_ctx.load_texture(
+            "screen",
+            egui::ColorImage::new([SCREEN_WIDTH, SCREEN_HEIGHT], Color32::WHITE),
+            egui::TextureOptions::NEAREST,
+        );
+
         let mut view = Self {
-            debugger: Debugger::new(),
+            debugger,
             disassembly_map: HashMap::new(),
             disassembly: vec![],
             font_size: 18.0,
             selected_index: None,
+            screen,
+            audio: vec![],
+            split_channels: false,
+            recording_status: None,
         };
 
-        view.debugger.load_cartridge(cartridge);
         view.debugger
             .disassemble(&mut view.disassembly, &mut view.disassembly_map);
 
@@ -55,12 +86,90 @@ impl DebuggerView {
             .size(self.font_size)
             .strong()
     }
+
+    //NOTE: Keeps the newest samples around so the scope doesn't go blank while the cpu is paused
+    fn update_audio(&mut self) {
+        self.audio.extend(self.debugger.take_audio_samples());
+
+        if self.audio.len() > SCOPE_FRAMES {
+            self.audio.drain(..self.audio.len() - SCOPE_FRAMES);
+        }
+    }
+
+    fn toggle_recording(&mut self) {
+        if !self.debugger.is_recording() {
+            self.debugger.start_recording(self.split_channels);
+            self.recording_status = None;
+            return;
+        }
+
+        self.recording_status = Some(match self.debugger.stop_recording() {
+            Ok(files) => format!("Wrote {} file(s)", files.len()),
+            Err(error) => format!("Could not write the recording: {}", error),
+        });
+    }
+
+    fn draw_recording_controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let recording = self.debugger.is_recording();
+            let label = if recording { "Stop" } else { "Record" };
+
+            if ui.button(label).clicked() {
+                self.toggle_recording();
+            }
+
+            ui.add_enabled(
+                !recording,
+                egui::Checkbox::new(&mut self.split_channels, "Track per channel"),
+            );
+            ui.label(self.debugger.get_recording_path().display().to_string());
+        });
+
+        if let Some(status) = &self.recording_status {
+            ui.label(status);
+        }
+    }
+
+    //NOTE: Draws the left channel in the upper half of the scope and the right one below it
+    fn draw_audio_scope(&self, ui: &mut egui::Ui) {
+        let (response, painter) =
+            ui.allocate_painter(egui::vec2(400.0, 160.0), egui::Sense::hover());
+        let rect = response.rect;
+        painter.rect_filled(rect, 0.0, Color32::BLACK);
+
+        for (channel, color) in [(0, Color32::LIGHT_GREEN), (1, Color32::LIGHT_BLUE)] {
+            let center = rect.top() + rect.height() * (channel as f32 * 2.0 + 1.0) / 4.0;
+            let points = self
+                .audio
+                .iter()
+                .enumerate()
+                .map(|(index, frame)| {
+                    let x = rect.left() + rect.width() * index as f32 / SCOPE_FRAMES as f32;
+                    let y = center - frame[channel] * rect.height() / 4.0;
+                    egui::pos2(x, y)
+                })
+                .collect();
+
+            painter.add(egui::Shape::line(points, egui::Stroke::new(1.0, color)));
+        }
+    }
 }
 
 impl eframe::App for DebuggerView {
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        if let Err(error) = self.debugger.save() {
+            println!("Could not write the save file: {}", error);
+        }
+
+        if let Err(error) = self.debugger.stop_recording() {
+            println!("Could not write the recording: {}", error);
+        }
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         if ctx.input(|i| i.key_pressed(egui::Key::S)) {
-            self.debugger.step();
+            //NOTE: Errors are kept by the debugger and shown in the status pane
+            let _ = self.debugger.step();
         }
 
         if ctx.input(|i| i.key_pressed(egui::Key::R)) {
@@ -80,10 +189,35 @@ impl eframe::App for DebuggerView {
                 .disassemble(&mut self.disassembly, &mut self.disassembly_map);
         }
 
+        for (key, button) in BUTTON_KEYS {
+            if ctx.input(|i| i.key_pressed(key)) {
+                self.debugger.press(button);
+            }
+
+            if ctx.input(|i| i.key_released(key)) {
+                self.debugger.release(button);
+            }
+        }
+
+        if let Err(error) = self.debugger.save_periodically() {
+            println!("Could not write the save file: {}", error);
+        }
+
         //Rigth panel will hold the current status of the cpu !
         egui::SidePanel::right("cpu_status_pane")
             .min_width(400.0)
             .show(ctx, |ui| {
+                if let Some(error) = self.debugger.get_cpu_error() {
+                    ui.label(
+                        egui::RichText::new(format!("{}", error))
+                            .monospace()
+                            .color(Color32::RED)
+                            .size(self.font_size)
+                            .strong(),
+                    );
+                    ui.separator();
+                }
+
                 egui::Grid::new("Register State").show(ui, |ui| {
                     let register_label_af = self.generate_register_labels("AF:");
                     let register_label_bc = self.generate_register_labels("BC:");
@@ -107,6 +241,11 @@ impl eframe::App for DebuggerView {
                     let custom_timer = self.generate_register_labels("CUSTOM TIMER:");
                     let tac_control = self.generate_register_labels("TICK RATE:");
                     let custom_timer_reset = self.generate_register_labels("RESET VALUE:");
+                    let cpu_speed = self.generate_register_labels("CPU SPEED:");
+                    let model = self.generate_register_labels("MODEL:");
+                    let lcd_line = self.generate_register_labels("LY:");
+                    let lcd_mode = self.generate_register_labels("LCD MODE:");
+                    let frames = self.generate_register_labels("FRAMES:");
 
                     ui.label(register_label_af);
                     ui.label(self.generate_register_value_labels(
@@ -216,9 +355,70 @@ impl eframe::App for DebuggerView {
                     ui.label(custom_timer_reset);
                     let timer_reset_value = &self.debugger.get_timer_reset();
                     ui.label(self.generate_register_value_labels(&timer_reset_value));
+                    ui.end_row();
+                    ui.end_row();
+
+                    ui.label(cpu_speed);
+                    ui.label(self.generate_register_value_labels(&self.debugger.get_cpu_speed()));
+                    ui.end_row();
+
+                    ui.label(model);
+                    ui.label(self.generate_register_value_labels(&self.debugger.get_model()));
+                    ui.end_row();
+                    ui.end_row();
+
+                    ui.label(lcd_line);
+                    ui.label(self.generate_register_value_labels(&self.debugger.get_lcd_line()));
+                    ui.end_row();
+
+                    ui.label(lcd_mode);
+                    ui.label(self.generate_register_value_labels(&self.debugger.get_lcd_mode()));
+                    ui.end_row();
+
+                    ui.label(frames);
+                    ui.label(self.generate_register_value_labels(&self.debugger.get_frame_count()));
+                });
+
+                ui.separator();
+                egui::Grid::new("Cartridge Header").show(ui, |ui| {
+                    for (label, value) in self.debugger.get_cartridge_header() {
+                        ui.label(self.generate_register_labels(label));
+                        ui.label(self.generate_register_value_labels(&value));
+                        ui.end_row();
+                    }
                 });
+
+                for warning in self.debugger.get_cartridge_warnings() {
+                    ui.label(
+                        egui::RichText::new(format!("{}", warning))
+                            .monospace()
+                            .color(Color32::RED)
+                            .size(self.font_size),
+                    );
+                }
             });
 
+        self.screen.set(
+            egui::ColorImage::from_rgb(
+                [SCREEN_WIDTH, SCREEN_HEIGHT],
+                self.debugger.get_framebuffer(),
+            ),
+            egui::TextureOptions::NEAREST,
+        );
+
+        egui::Window::new("Screen").show(ctx, |ui| {
+            ui.image(egui::load::SizedTexture::new(
+                self.screen.id(),
+                egui::vec2(SCREEN_WIDTH as f32 * 2.0, SCREEN_HEIGHT as f32 * 2.0),
+            ));
+        });
+
+        self.update_audio();
+        egui::Window::new("Audio").show(ctx, |ui| {
+            self.draw_recording_controls(ui);
+            self.draw_audio_scope(ui);
+        });
+
         egui::TopBottomPanel::bottom("call stack")
             .min_height(240.0)
             .show(ctx, |ui| {
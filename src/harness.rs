@@ -0,0 +1,368 @@
+use std::fmt::Display;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::cpu::register::RegByte;
+use crate::cpu::Cpu;
+use crate::headless::SerialWatch;
+use crate::memory::Memory;
+use crate::model::Model;
+use crate::serial::link::Disconnected;
+
+//NOTE: A minute of emulated time, the longest blargg roms need about half of that
+pub const DEFAULT_CYCLE_LIMIT: usize = 60 * 1_048_576;
+
+//NOTE: The mooneye roms load these into B, C, D, E, H and L once they passed and 0x42 into all of
+//      them if they failed, either way they end with `LD B, B`
+const FIBONACCI: [u8; 6] = [0x03, 0x05, 0x08, 0x0D, 0x15, 0x22];
+const LD_B_B: u8 = 0x40;
+
+//NOTE: The blargg roms that can't rely on the serial port write 0x80 to 0xA000 while they run and
+//      the result code once they are done, the signature tells that the value is valid. A text
+//      with the details follows at 0xA004.
+const BLARGG_RESULT: u16 = 0xA000;
+const BLARGG_SIGNATURE: [u8; 3] = [0xDE, 0xB0, 0x61];
+const BLARGG_TEXT: u16 = 0xA004;
+const BLARGG_RUNNING: u8 = 0x80;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Suite {
+    Blargg,
+    Mooneye,
+}
+
+impl Suite {
+    //NOTE: The mooneye roms sit in a folder named after the suite, everything else counts as blargg
+    pub fn of(path: &Path) -> Suite {
+        let is_mooneye = path
+            .components()
+            .any(|part| part.as_os_str().to_string_lossy().contains("mooneye"));
+
+        if is_mooneye {
+            Suite::Mooneye
+        } else {
+            Suite::Blargg
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Verdict {
+    Passed,
+    Failed,
+    TimedOut,
+    //NOTE: The rom didn't load or the cpu ran into an illegal opcode
+    Error,
+}
+
+impl Display for Verdict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Verdict::Passed => write!(f, "PASSED"),
+            Verdict::Failed => write!(f, "FAILED"),
+            Verdict::TimedOut => write!(f, "TIMEOUT"),
+            Verdict::Error => write!(f, "ERROR"),
+        }
+    }
+}
+
+pub struct RomReport {
+    pub path: PathBuf,
+    pub verdict: Verdict,
+    pub machine_cycles: usize,
+    //NOTE: What the rom reported, the serial output or the registers
+    pub details: String,
+}
+
+//NOTE: Every .gb and .gbc file below `dir`, sorted so the table stays in the same order
+pub fn find_roms(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut roms = Vec::new();
+
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            roms.extend(find_roms(&path)?);
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension == "gb" || extension == "gbc")
+        {
+            roms.push(path);
+        }
+    }
+
+    roms.sort();
+    Ok(roms)
+}
+
+pub fn run_roms(paths: &[PathBuf], cycle_limit: usize) -> Vec<RomReport> {
+    paths
+        .iter()
+        .map(|path| run_rom(path, cycle_limit))
+        .collect()
+}
+
+pub fn run_rom(path: &Path, cycle_limit: usize) -> RomReport {
+    let report = |verdict, machine_cycles, details| RomReport {
+        path: path.to_path_buf(),
+        verdict,
+        machine_cycles,
+        details,
+    };
+
+    let mut mem = Memory::default();
+    let loaded = std::fs::read(path)
+        .map_err(|error| error.to_string())
+        .and_then(|rom| mem.load_cartridge(&rom).map_err(|error| error.to_string()));
+    if let Err(error) = loaded {
+        return report(Verdict::Error, 0, error);
+    }
+
+    let model = model_from_name(path).unwrap_or(if mem.is_cgb_mode() {
+        Model::Cgb
+    } else {
+        Model::Dmg
+    });
+    mem.set_serial_link(Box::new(Disconnected));
+    mem.apply_post_boot_state(model);
+    let mut cpu = Cpu::post_boot(model, &mem);
+
+    //NOTE: Every step takes at least a machine cycle, even in HALT or STOP, so the limit bounds the
+    //      steps as well. A rom that never wakes up again still ends up as a timeout.
+    let suite = Suite::of(path);
+    let mut serial_watch = SerialWatch::new("Passed".len() - 1);
+    for _ in 0..cycle_limit {
+        if cpu.machine_cycles >= cycle_limit {
+            break;
+        }
+
+        let result = match suite {
+            Suite::Blargg => blargg_result(&mem, &mut serial_watch),
+            Suite::Mooneye => mooneye_result(&cpu, &mem),
+        };
+
+        if let Some((verdict, details)) = result {
+            return report(verdict, cpu.machine_cycles, details);
+        }
+
+        if let Err(error) = cpu.cycle(&mut mem) {
+            return report(Verdict::Error, cpu.machine_cycles, error.to_string());
+        }
+    }
+
+    report(
+        Verdict::TimedOut,
+        cpu.machine_cycles,
+        mem.get_serial_output().to_string(),
+    )
+}
+
+fn blargg_result(mem: &Memory, serial_watch: &mut SerialWatch) -> Option<(Verdict, String)> {
+    let output = mem.get_serial_output();
+    if let Some(new_output) = serial_watch.get_new_output(output) {
+        if new_output.contains("Passed") {
+            return Some((Verdict::Passed, output.to_string()));
+        }
+
+        if new_output.contains("Failed") {
+            return Some((Verdict::Failed, output.to_string()));
+        }
+    }
+
+    let signature = [1, 2, 3].map(|offset| mem.read(BLARGG_RESULT + offset));
+    let result = mem.read(BLARGG_RESULT);
+    if signature != BLARGG_SIGNATURE || result == BLARGG_RUNNING {
+        return None;
+    }
+
+    let text: Vec<u8> = (BLARGG_TEXT..0xC000)
+        .map(|addr| mem.read(addr))
+        .take_while(|&byte| byte != 0x00)
+        .collect();
+    let verdict = if result == 0x00 {
+        Verdict::Passed
+    } else {
+        Verdict::Failed
+    };
+
+    Some((verdict, String::from_utf8_lossy(&text).into_owned()))
+}
+
+fn mooneye_result(cpu: &Cpu, mem: &Memory) -> Option<(Verdict, String)> {
+    if mem.read(cpu.pc) != LD_B_B {
+        return None;
+    }
+
+    let registers = [
+        RegByte::B,
+        RegByte::C,
+        RegByte::D,
+        RegByte::E,
+        RegByte::H,
+        RegByte::L,
+    ]
+    .map(|reg| cpu.regs.read_value8_from(reg));
+    let verdict = if registers == FIBONACCI {
+        Verdict::Passed
+    } else {
+        Verdict::Failed
+    };
+
+    let details = format!(
+        "B: {:02X} C: {:02X} D: {:02X} E: {:02X} H: {:02X} L: {:02X}",
+        registers[0], registers[1], registers[2], registers[3], registers[4], registers[5]
+    );
+
+    Some((verdict, details))
+}
+
+//NOTE: The mooneye roms end their name with the models they were written for, like
+//      `boot_regs-mgb.gb`. Without one the cartridge decides just like in the debugger.
+fn model_from_name(path: &Path) -> Option<Model> {
+    let stem = path.file_stem()?.to_string_lossy();
+    let (_, models) = stem.rsplit_once('-')?;
+
+    match models {
+        "dmg0" => Some(Model::Dmg0),
+        "mgb" => Some(Model::Mgb),
+        "sgb" | "sgb2" | "S" => Some(Model::Sgb),
+        "cgb" | "cgbABCDE" | "C" => Some(Model::Cgb),
+        _ => None,
+    }
+}
+
+pub fn all_passed(reports: &[RomReport]) -> bool {
+    reports
+        .iter()
+        .all(|report| report.verdict == Verdict::Passed)
+}
+
+//NOTE: One line per rom, the ones that didn't pass get the last line of what they reported
+pub fn summary_table(reports: &[RomReport]) -> String {
+    let width = reports
+        .iter()
+        .map(|report| report.path.display().to_string().len())
+        .max()
+        .unwrap_or(0)
+        .max("ROM".len());
+
+    let mut table = format!(
+        "{:<width$}  {:<7}  {:>12}  DETAILS\n",
+        "ROM", "RESULT", "CYCLES"
+    );
+
+    for report in reports {
+        let details = match report.verdict {
+            Verdict::Passed => "",
+            _ => report
+                .details
+                .lines()
+                .rfind(|line| !line.trim().is_empty())
+                .unwrap_or(""),
+        };
+
+        let line = format!(
+            "{:<width$}  {:<7}  {:>12}  {}",
+            report.path.display(),
+            report.verdict.to_string(),
+            report.machine_cycles,
+            details.trim()
+        );
+        table.push_str(line.trim_end());
+        table.push('\n');
+    }
+
+    let passed = reports
+        .iter()
+        .filter(|report| report.verdict == Verdict::Passed)
+        .count();
+    table.push_str(&format!("{} of {} passed\n", passed, reports.len()));
+
+    table
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::{temp_path, write_rom};
+
+    #[test]
+    fn test_detects_the_results() {
+        //NOTE: LD B,3 LD C,5 LD D,8 LD E,13 LD H,21 LD L,34 LD B,B
+        let mut mooneye = vec![0x06, 0x03, 0x0E, 0x05, 0x16, 0x08, 0x1E, 0x0D];
+        mooneye.extend_from_slice(&[0x26, 0x15, 0x2E, 0x22, 0x40]);
+        let dir = temp_path("mooneye");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("fibonacci.gb");
+        write_rom(&path, &mooneye);
+
+        let report = run_rom(&path, 1000);
+        assert_eq!(report.verdict, Verdict::Passed);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        //NOTE: Sends 'F' and waits for it forever, that is neither passed nor failed
+        let serial = [0x3E, 0x46, 0xE0, 0x01, 0x3E, 0x81, 0xE0, 0x02, 0x18, 0xFE];
+        let path = temp_path("serial.gb");
+        write_rom(&path, &serial);
+        let report = run_rom(&path, 1000);
+        assert_eq!(report.verdict, Verdict::TimedOut);
+        assert_eq!(report.details, "F");
+        std::fs::remove_file(&path).unwrap();
+
+        let report = run_rom(Path::new("gboyrust_missing.gb"), 1000);
+        assert_eq!(report.verdict, Verdict::Error);
+    }
+
+    #[test]
+    fn test_stopped_rom_times_out() {
+        //NOTE: STOP without a button held, followed by a loop it never gets to
+        let path = temp_path("stop.gb");
+        write_rom(&path, &[0x10, 0x00, 0x18, 0xFE]);
+
+        let reports = run_roms(std::slice::from_ref(&path), 1000);
+        assert_eq!(reports[0].verdict, Verdict::TimedOut);
+        assert!(reports[0].machine_cycles >= 1000);
+        assert!(summary_table(&reports).ends_with("0 of 1 passed\n"));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_summary_table() {
+        let reports = [
+            RomReport {
+                path: PathBuf::from("first.gb"),
+                verdict: Verdict::Passed,
+                machine_cycles: 1234,
+                details: String::new(),
+            },
+            RomReport {
+                path: PathBuf::from("second_rom.gb"),
+                verdict: Verdict::Failed,
+                machine_cycles: 5678,
+                details: String::from("second_rom\n\nWhat went wrong\nLast line\n"),
+            },
+        ];
+
+        let table = summary_table(&reports);
+        let lines: Vec<_> = table.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[1].starts_with("first.gb       PASSED"));
+        assert!(lines[2].ends_with("5678  Last line"));
+        assert_eq!(lines[3], "1 of 2 passed");
+        assert!(!all_passed(&reports));
+    }
+
+    #[test]
+    fn test_model_from_name() {
+        assert_eq!(
+            model_from_name(Path::new("boot_regs-mgb.gb")),
+            Some(Model::Mgb)
+        );
+        assert_eq!(
+            model_from_name(Path::new("boot_hwio-C.gb")),
+            Some(Model::Cgb)
+        );
+        assert_eq!(model_from_name(Path::new("sources-GS.gb")), None);
+        assert_eq!(model_from_name(Path::new("tim00.gb")), None);
+    }
+}
@@ -0,0 +1,316 @@
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+
+use crate::cpu::register::RegWord;
+use crate::cpu::CpuError;
+use crate::debugger::Debugger;
+use crate::ppu::{DOTS_PER_FRAME, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+//NOTE: What the process exits with, scripts running the emulator without a display check these
+pub const EXIT_SUCCESS: u8 = 0;
+//NOTE: The frame or cycle limit ran out before the breakpoint or the serial output showed up
+pub const EXIT_TIMEOUT: u8 = 1;
+//NOTE: Invalid arguments or a rom that doesn't load
+pub const EXIT_USAGE: u8 = 2;
+pub const EXIT_CPU_ERROR: u8 = 3;
+
+//NOTE: The frames and the cycles limit how long a run takes, the breakpoints and the serial
+//      output are what it waits for. Whatever happens first ends the run.
+#[derive(Default)]
+pub struct HeadlessOptions {
+    pub frames: Option<usize>,
+    pub cycles: Option<usize>,
+    pub breakpoints: Vec<u16>,
+    pub serial_output: Option<String>,
+    pub screenshot: Option<PathBuf>,
+}
+
+impl HeadlessOptions {
+    //NOTE: Without any of these the run would never end
+    pub fn has_stop_condition(&self) -> bool {
+        self.frames.is_some() || self.cycles.is_some() || self.waits_for_event()
+    }
+
+    fn waits_for_event(&self) -> bool {
+        !self.breakpoints.is_empty() || self.serial_output.is_some()
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Outcome {
+    LimitReached,
+    Breakpoint(u16),
+    SerialOutput,
+    CpuError(CpuError),
+}
+
+impl Outcome {
+    pub fn exit_code(&self, options: &HeadlessOptions) -> u8 {
+        match self {
+            Outcome::LimitReached if options.waits_for_event() => EXIT_TIMEOUT,
+            Outcome::CpuError(_) => EXIT_CPU_ERROR,
+            _ => EXIT_SUCCESS,
+        }
+    }
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Outcome::LimitReached => write!(f, "Reached the frame or cycle limit"),
+            Outcome::Breakpoint(addr) => write!(f, "Hit the breakpoint at {:04X}", addr),
+            Outcome::SerialOutput => write!(f, "Found the serial output"),
+            Outcome::CpuError(error) => write!(f, "The cpu stopped: {}", error),
+        }
+    }
+}
+
+//NOTE: The serial output only ever grows, so looking for text in it before every instruction
+//      only needs the bytes that came in since the last look. The last `overlap` bytes that were
+//      searched already go along, so text that came in over several looks still turns up.
+pub struct SerialWatch {
+    searched: usize,
+    overlap: usize,
+}
+
+impl SerialWatch {
+    //NOTE: `overlap` should be one less than the length of the longest text that gets looked for
+    pub fn new(overlap: usize) -> Self {
+        Self {
+            searched: 0,
+            overlap,
+        }
+    }
+
+    //NOTE: Nothing if not a single byte came in since the last look
+    pub fn get_new_output<'a>(&mut self, output: &'a str) -> Option<&'a str> {
+        if output.len() == self.searched {
+            return None;
+        }
+
+        let mut start = self.searched.saturating_sub(self.overlap);
+        while !output.is_char_boundary(start) {
+            start -= 1;
+        }
+
+        self.searched = output.len();
+        Some(&output[start..])
+    }
+}
+
+//NOTE: A linked instance only gets stepped along, the options only look at `debugger`
+pub fn run(
+    debugger: &mut Debugger,
+    linked: Option<&mut Debugger>,
+    options: &HeadlessOptions,
+) -> Outcome {
+    for &addr in &options.breakpoints {
+        if !debugger.is_registered_breakpoint(addr) {
+            debugger.toggle_breakpoint(addr);
+        }
+    }
+
+    let start_cycles = debugger.get_machine_cycles();
+    let mut outcome = Outcome::LimitReached;
+    let mut serial_watch = options
+        .serial_output
+        .as_ref()
+        .map(|expected| (expected, SerialWatch::new(expected.len().saturating_sub(1))));
+
+    let result = debugger.run_until(linked, |debugger, dots| {
+        let pc = debugger.get_program_counter() as u16;
+        if debugger.is_registered_breakpoint(pc) {
+            outcome = Outcome::Breakpoint(pc);
+            return true;
+        }
+
+        if let Some((expected, watch)) = &mut serial_watch {
+            if watch
+                .get_new_output(debugger.get_serial_output())
+                .is_some_and(|output| output.contains(expected.as_str()))
+            {
+                outcome = Outcome::SerialOutput;
+                return true;
+            }
+        }
+
+        let cycles = debugger.get_machine_cycles() - start_cycles;
+        options
+            .frames
+            .is_some_and(|frames| dots >= frames * DOTS_PER_FRAME)
+            || options.cycles.is_some_and(|limit| cycles >= limit)
+    });
+
+    match result {
+        Ok(()) => outcome,
+        Err(error) => Outcome::CpuError(error),
+    }
+}
+
+pub fn dump_state(debugger: &Debugger) -> String {
+    format!(
+        "AF: {}  BC: {}  DE: {}  HL: {}  SP: {}  PC: {}\n\
+         Machine cycles: {}  Frames drawn: {}\n\
+         Serial output:\n{}",
+        debugger.get_register_word(RegWord::Af),
+        debugger.get_register_word(RegWord::Bc),
+        debugger.get_register_word(RegWord::De),
+        debugger.get_register_word(RegWord::Hl),
+        debugger.get_sp_string(),
+        debugger.get_pc_string(),
+        debugger.get_machine_cycles(),
+        debugger.get_frame_count(),
+        debugger.get_serial_output(),
+    )
+}
+
+//NOTE: The framebuffer as it is right now, the ppu keeps it in rgb already
+pub fn write_screenshot(path: &Path, framebuffer: &[u8]) -> io::Result<()> {
+    let writer = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(writer, SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    encoder.write_header()?.write_image_data(framebuffer)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::{temp_path, write_rom};
+
+    //NOTE: Sends 'O' over the serial port and spins at 0x0108 afterwards
+    const SERIAL_PROGRAM: [u8; 10] = [0x3E, 0x4F, 0xE0, 0x01, 0x3E, 0x81, 0xE0, 0x02, 0x18, 0xFE];
+    //NOTE: STOP without a button held, nothing ever wakes it up
+    const STOP_PROGRAM: [u8; 4] = [0x10, 0x00, 0x18, 0xFE];
+
+    fn booted(path: &Path) -> Debugger {
+        let mut debugger = Debugger::new();
+        debugger.load_cartridge(path.to_str().unwrap()).unwrap();
+        debugger.boot(None, None).unwrap();
+        debugger
+    }
+
+    #[test]
+    fn test_stop_conditions() {
+        let path = temp_path("headless_stop.gb");
+        write_rom(&path, &SERIAL_PROGRAM);
+
+        let options = HeadlessOptions {
+            cycles: Some(1000),
+            serial_output: Some(String::from("O")),
+            ..Default::default()
+        };
+        let mut debugger = booted(&path);
+        let outcome = run(&mut debugger, None, &options);
+        assert_eq!(outcome, Outcome::SerialOutput);
+        assert_eq!(outcome.exit_code(&options), EXIT_SUCCESS);
+
+        let options = HeadlessOptions {
+            frames: Some(1),
+            breakpoints: vec![0x0108],
+            ..Default::default()
+        };
+        let mut debugger = booted(&path);
+        assert_eq!(
+            run(&mut debugger, None, &options),
+            Outcome::Breakpoint(0x0108)
+        );
+        assert!(dump_state(&debugger).contains("PC: 0108"));
+
+        //NOTE: Waiting for output that never comes runs into the limit
+        let options = HeadlessOptions {
+            cycles: Some(1000),
+            serial_output: Some(String::from("Passed")),
+            ..Default::default()
+        };
+        let mut debugger = booted(&path);
+        let outcome = run(&mut debugger, None, &options);
+        assert_eq!(outcome, Outcome::LimitReached);
+        assert_eq!(outcome.exit_code(&options), EXIT_TIMEOUT);
+        assert!(debugger.get_machine_cycles() >= 1000);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_limits_end_a_stopped_cpu() {
+        let stop_path = temp_path("headless_stopped.gb");
+        write_rom(&stop_path, &STOP_PROGRAM);
+        let serial_path = temp_path("headless_linked.gb");
+        write_rom(&serial_path, &SERIAL_PROGRAM);
+
+        for options in [
+            HeadlessOptions {
+                cycles: Some(1000),
+                ..Default::default()
+            },
+            HeadlessOptions {
+                frames: Some(1),
+                ..Default::default()
+            },
+        ] {
+            let mut debugger = booted(&stop_path);
+            assert_eq!(run(&mut debugger, None, &options), Outcome::LimitReached);
+            assert_eq!(debugger.get_program_counter(), 0x0102);
+        }
+
+        //NOTE: The linked instance sitting in STOP doesn't hold up the other one either
+        let options = HeadlessOptions {
+            cycles: Some(1000),
+            ..Default::default()
+        };
+        let mut debugger = booted(&serial_path);
+        let mut linked = booted(&stop_path);
+        assert_eq!(
+            run(&mut debugger, Some(&mut linked), &options),
+            Outcome::LimitReached
+        );
+        assert!(linked.get_machine_cycles() >= 1000);
+
+        std::fs::remove_file(&stop_path).unwrap();
+        std::fs::remove_file(&serial_path).unwrap();
+    }
+
+    #[test]
+    fn test_serial_watch_only_returns_new_output() {
+        let mut watch = SerialWatch::new("Passed".len() - 1);
+
+        assert_eq!(watch.get_new_output(""), None);
+        assert_eq!(
+            watch.get_new_output("cpu_instrs\nPas"),
+            Some("cpu_instrs\nPas")
+        );
+        assert_eq!(watch.get_new_output("cpu_instrs\nPas"), None);
+        assert_eq!(
+            watch.get_new_output("cpu_instrs\nPassed"),
+            Some("s\nPassed")
+        );
+
+        //NOTE: The overlap never starts in the middle of a character
+        let mut watch = SerialWatch::new(1);
+        watch.get_new_output("ab\u{e9}");
+        assert_eq!(watch.get_new_output("ab\u{e9}c"), Some("\u{e9}c"));
+    }
+
+    #[test]
+    fn test_writes_the_screenshot() {
+        let rom_path = temp_path("headless_screenshot.gb");
+        write_rom(&rom_path, &SERIAL_PROGRAM);
+        let debugger = booted(&rom_path);
+        let path = temp_path("headless.png");
+        write_screenshot(&path, debugger.get_framebuffer()).unwrap();
+
+        let decoder = png::Decoder::new(File::open(&path).unwrap());
+        let reader = decoder.read_info().unwrap();
+        assert_eq!(reader.info().width, SCREEN_WIDTH as u32);
+        assert_eq!(reader.info().height, SCREEN_HEIGHT as u32);
+
+        std::fs::remove_file(&rom_path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+}
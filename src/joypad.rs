@@ -0,0 +1,172 @@
+use std::ops::RangeInclusive;
+
+use crate::memory::bus::IoDevice;
+
+pub const JOYPAD_REGISTER: u16 = 0xFF00;
+
+const SELECT_ACTION: u8 = 0x20;
+const SELECT_DIRECTION: u8 = 0x10;
+const SELECT_BITS: u8 = SELECT_ACTION | SELECT_DIRECTION;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Button {
+    Right,
+    Left,
+    Up,
+    Down,
+    A,
+    B,
+    Select,
+    Start,
+}
+
+impl Button {
+    //NOTE: Both rows share the lower four bits of the register, the select bits pick the row
+    fn mask(self) -> u8 {
+        match self {
+            Button::Right | Button::A => 0x01,
+            Button::Left | Button::B => 0x02,
+            Button::Up | Button::Select => 0x04,
+            Button::Down | Button::Start => 0x08,
+        }
+    }
+
+    fn is_direction(self) -> bool {
+        matches!(
+            self,
+            Button::Right | Button::Left | Button::Up | Button::Down
+        )
+    }
+}
+
+//NOTE: The buttons pull their input line low while they are pressed. Every input line going from
+//      high to low requests the joypad interrupt, no matter if a button or a write to the select
+//      bits caused it.
+pub struct Joypad {
+    select: u8,
+    directions: u8,
+    actions: u8,
+    interrupt_requested: bool,
+}
+
+impl Default for Joypad {
+    fn default() -> Self {
+        Self {
+            select: SELECT_BITS,
+            directions: 0x00,
+            actions: 0x00,
+            interrupt_requested: false,
+        }
+    }
+}
+
+impl Joypad {
+    pub fn press(&mut self, button: Button) {
+        self.update_lines(|joypad| *joypad.row(button) |= button.mask());
+    }
+
+    pub fn release(&mut self, button: Button) {
+        self.update_lines(|joypad| *joypad.row(button) &= !button.mask());
+    }
+
+    pub fn take_interrupt(&mut self) -> bool {
+        std::mem::take(&mut self.interrupt_requested)
+    }
+
+    fn row(&mut self, button: Button) -> &mut u8 {
+        if button.is_direction() {
+            &mut self.directions
+        } else {
+            &mut self.actions
+        }
+    }
+
+    fn update_lines(&mut self, change: impl FnOnce(&mut Self)) {
+        let before = self.input_lines();
+        change(self);
+
+        if before & !self.input_lines() != 0x00 {
+            self.interrupt_requested = true;
+        }
+    }
+
+    //NOTE: A cleared select bit connects its row to the input lines, so a line reads zero if a
+    //      pressed button of any selected row sits on it.
+    fn input_lines(&self) -> u8 {
+        let mut pressed = 0x00;
+
+        if self.select & SELECT_DIRECTION == 0 {
+            pressed |= self.directions;
+        }
+
+        if self.select & SELECT_ACTION == 0 {
+            pressed |= self.actions;
+        }
+
+        !pressed & 0x0F
+    }
+}
+
+impl IoDevice for Joypad {
+    fn address_ranges(&self) -> &'static [RangeInclusive<u16>] {
+        &[JOYPAD_REGISTER..=JOYPAD_REGISTER]
+    }
+
+    fn read(&self, _addr: u16) -> u8 {
+        0xC0 | self.select | self.input_lines()
+    }
+
+    fn write(&mut self, _addr: u16, value: u8) {
+        self.update_lines(|joypad| joypad.select = value & SELECT_BITS);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_select_rows() {
+        let mut joypad = Joypad::default();
+        joypad.press(Button::Start);
+        joypad.press(Button::Left);
+        assert_eq!(joypad.read(JOYPAD_REGISTER), 0xFF);
+
+        joypad.write(JOYPAD_REGISTER, SELECT_DIRECTION);
+        assert_eq!(joypad.read(JOYPAD_REGISTER), 0xD7);
+
+        joypad.write(JOYPAD_REGISTER, SELECT_ACTION);
+        assert_eq!(joypad.read(JOYPAD_REGISTER), 0xED);
+
+        joypad.write(JOYPAD_REGISTER, 0x00);
+        assert_eq!(joypad.read(JOYPAD_REGISTER), 0xC5);
+
+        joypad.release(Button::Start);
+        assert_eq!(joypad.read(JOYPAD_REGISTER), 0xCD);
+    }
+
+    #[test]
+    fn test_interrupt_on_falling_edge() {
+        let mut joypad = Joypad::default();
+
+        //NOTE: No row is selected, the lines stay high
+        joypad.press(Button::A);
+        assert!(!joypad.take_interrupt());
+
+        joypad.write(JOYPAD_REGISTER, SELECT_DIRECTION);
+        assert!(joypad.take_interrupt());
+
+        //NOTE: The line is already low, a second button on it doesn't change anything
+        joypad.write(JOYPAD_REGISTER, 0x00);
+        joypad.press(Button::Right);
+        assert!(!joypad.take_interrupt());
+
+        joypad.release(Button::A);
+        joypad.release(Button::Right);
+        assert!(!joypad.take_interrupt());
+
+        joypad.press(Button::Down);
+        assert!(joypad.take_interrupt());
+        assert!(!joypad.take_interrupt());
+    }
+}
@@ -1,45 +1,196 @@
+mod apu;
 mod assembler;
+mod cartridge;
 mod cpu;
 mod debugger;
 mod debugger_view;
 mod disassembler;
+mod harness;
+mod headless;
+mod joypad;
 mod memory;
+mod model;
+mod ppu;
+mod serial;
+#[cfg(test)]
+mod test_util;
 
 use std::env;
 use std::fs::File;
 use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
+use cartridge::rtc::RtcMode;
+use debugger::Debugger;
 use debugger_view::DebuggerView;
+use headless::{HeadlessOptions, EXIT_USAGE};
+use model::Model;
+use ppu::Renderer;
+use serial::link::{CableEnd, Disconnected, LoopbackLink, SerialLink, StdoutLink};
 
-fn main() {
-    let mut args = env::args();
+const USAGE: &str = "Usage: gboyrust [headless] <rompath> [--model dmg0|dmg|mgb|sgb|cgb] [--boot-rom <path>] [--rtc host|emulated] [--ppu scanline|fifo] [--wav <path>] [--wav-tracks mixed|split] [--serial stdout|loopback|none]\nHeadless only: [--frames <count>] [--cycles <count>] [--break <address>] [--until-serial <text>] [--screenshot <path>] [--link <rompath>]\n       gboyrust test-roms <directory> [--cycles <count>]";
 
-    if args.len() < 2 {
-        println!("Usage: gboyrust <rompath>");
-        return;
+fn main() -> ExitCode {
+    let mut args = env::args().skip(1).peekable();
+
+    //NOTE: Runs every test rom below a directory and prints which ones passed
+    if args.next_if(|arg| arg == "test-roms").is_some() {
+        return run_test_roms(args);
+    }
+
+    //NOTE: Runs without the debugger window until one of the stop conditions is met
+    let mut headless = args.next_if(|arg| arg == "headless").is_some();
+
+    let Some(rom_path) = args.next() else {
+        println!("{}", USAGE);
+        return ExitCode::from(EXIT_USAGE);
+    };
+
+    let mut model = None;
+    let mut boot_rom_path = None;
+    let mut rtc_mode = RtcMode::Host;
+    let mut renderer = Renderer::Scanline;
+    let mut wav_path = None;
+    let mut split_channels = false;
+    let mut options = HeadlessOptions::default();
+    //NOTE: Without a window the serial output ends up in the final dump, so nothing gets printed
+    //      while the game runs unless asked for
+    let mut serial_link: Option<Box<dyn SerialLink>> = None;
+    //NOTE: A second instance on the other end of the link cable, only without the window
+    let mut linked_rom_path = None;
+
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--model", Some(name)) => match Model::from_name(&name) {
+                Some(selected) => model = Some(selected),
+                None => {
+                    println!("Unknown model {}\n{}", name, USAGE);
+                    return ExitCode::from(EXIT_USAGE);
+                }
+            },
+            ("--boot-rom", Some(path)) => boot_rom_path = Some(path),
+            ("--ppu", Some(name)) => match Renderer::from_name(&name) {
+                Some(selected) => renderer = selected,
+                None => {
+                    println!("Unknown ppu renderer {}\n{}", name, USAGE);
+                    return ExitCode::from(EXIT_USAGE);
+                }
+            },
+            ("--rtc", Some(name)) => match RtcMode::from_name(&name) {
+                Some(selected) => rtc_mode = selected,
+                None => {
+                    println!("Unknown rtc mode {}\n{}", name, USAGE);
+                    return ExitCode::from(EXIT_USAGE);
+                }
+            },
+            ("--wav", Some(path)) => wav_path = Some(PathBuf::from(path)),
+            ("--wav-tracks", Some(tracks)) => match tracks.as_str() {
+                "mixed" => split_channels = false,
+                "split" => split_channels = true,
+                _ => {
+                    println!("Unknown wav tracks {}\n{}", tracks, USAGE);
+                    return ExitCode::from(EXIT_USAGE);
+                }
+            },
+            ("--frames", Some(count)) => match count.parse::<usize>() {
+                Ok(count) => options.frames = Some(count),
+                Err(_) => {
+                    println!("Invalid frame count {}\n{}", count, USAGE);
+                    return ExitCode::from(EXIT_USAGE);
+                }
+            },
+            ("--cycles", Some(count)) => match count.parse::<usize>() {
+                Ok(count) => options.cycles = Some(count),
+                Err(_) => {
+                    println!("Invalid cycle count {}\n{}", count, USAGE);
+                    return ExitCode::from(EXIT_USAGE);
+                }
+            },
+            ("--break", Some(addr)) => match parse_address(&addr) {
+                Some(addr) => options.breakpoints.push(addr),
+                None => {
+                    println!("Invalid breakpoint address {}\n{}", addr, USAGE);
+                    return ExitCode::from(EXIT_USAGE);
+                }
+            },
+            ("--until-serial", Some(text)) => options.serial_output = Some(text),
+            ("--screenshot", Some(path)) => options.screenshot = Some(PathBuf::from(path)),
+            ("--serial", Some(name)) => match name.as_str() {
+                "stdout" => serial_link = Some(Box::new(StdoutLink)),
+                "loopback" => serial_link = Some(Box::new(LoopbackLink)),
+                "none" => serial_link = Some(Box::new(Disconnected)),
+                _ => {
+                    println!("Unknown serial link {}\n{}", name, USAGE);
+                    return ExitCode::from(EXIT_USAGE);
+                }
+            },
+            ("--link", Some(path)) => linked_rom_path = Some(path),
+            _ => {
+                println!("{}", USAGE);
+                return ExitCode::from(EXIT_USAGE);
+            }
+        }
+    }
+
+    //NOTE: A frame count alone used to be how to run without the window, it still works that way
+    headless |= options.frames.is_some();
+
+    if headless && !options.has_stop_condition() {
+        println!(
+            "headless needs --frames, --cycles, --break or --until-serial to know when to stop\n{}",
+            USAGE
+        );
+        return ExitCode::from(EXIT_USAGE);
+    }
+
+    let headless_only =
+        options.has_stop_condition() || options.screenshot.is_some() || linked_rom_path.is_some();
+    if !headless && headless_only {
+        println!("These options only work headless\n{}", USAGE);
+        return ExitCode::from(EXIT_USAGE);
+    }
+
+    let start =
+        |path: &str| start_debugger(path, model, boot_rom_path.as_deref(), rtc_mode, renderer);
+
+    let Some(mut debugger) = start(&rom_path) else {
+        return ExitCode::from(EXIT_USAGE);
+    };
+
+    let mut linked = None;
+    if let Some(path) = linked_rom_path {
+        let Some(mut other) = start(&path) else {
+            return ExitCode::from(EXIT_USAGE);
+        };
+
+        let (cable, other_cable) = CableEnd::pair();
+        serial_link = Some(Box::new(cable));
+        other.set_serial_link(Box::new(other_cable));
+        linked = Some(other);
+    }
+
+    let serial_link = serial_link.unwrap_or_else(|| -> Box<dyn SerialLink> {
+        if headless {
+            Box::new(Disconnected)
+        } else {
+            Box::new(StdoutLink)
+        }
+    });
+    debugger.set_serial_link(serial_link);
+
+    if let Some(path) = wav_path {
+        debugger.set_recording_path(path);
+        debugger.start_recording(split_channels);
     }
 
-    let rom_path = args.nth(1).unwrap();
+    if headless {
+        return run_headless(debugger, linked, &options);
+    }
 
     let native_options = eframe::NativeOptions {
         ..eframe::NativeOptions::default()
     };
-    //let path = "test_roms/mooneye-tests/acceptance/bits/reg_f.gb";
-    //let path = "test_roms/cpu_instrs/individual/01-special.gb"; // PASSED (Not quite sure the daa
-    //opcode seems to be tricky)
-
-    //let path = "test_roms/cpu_instrs/individual/02-interrupts.gb";
-    //let path = "test_roms/cpu_instrs/individual/03-op sp,hl.gb"; //PASSED !
-    //let path = "test_roms/cpu_instrs/individual/04-op r,imm.gb"; //PASSED !
-    //let path = "test_roms/cpu_instrs/individual/05-op rp.gb"; //PASSED !
-    //let path = "test_roms/cpu_instrs/individual/06-ld r,r.gb"; //PASSED !
-    //let path = "test_roms/cpu_instrs/individual/07-jr,jp,call,ret,rst.gb"; //PASSED !
-    //let path = "test_roms/cpu_instrs/individual/08-misc instrs.gb"; //PASSED !
-    //let path = "test_roms/cpu_instrs/individual/09-op r,r.gb"; //PASSED !
-    //let path = "test_roms/cpu_instrs/individual/10-bit ops.gb"; //PASSED !
-    //let path = "test_roms/cpu_instrs/individual/11-op a,(hl).gb"; //PASSED !
-    //let path = "test_roms/dmg_boot.bin";
-
     //let mut file = File::open(path).unwrap();
     //let mut buffer = Vec::new();
 
@@ -56,7 +207,7 @@ fn main() {
             //let mut buffer = Vec::new();
 
             //file.read_to_end(&mut buffer).unwrap();
-            Box::new(DebuggerView::new(cc, &rom_path))
+            Box::new(DebuggerView::new(cc, debugger))
         }),
     );
     //let mut cpu = Cpu::default();
@@ -64,4 +215,135 @@ fn main() {
     //loop {
     //cpu.cycle(&mut mem);
     //}
+
+    ExitCode::SUCCESS
+}
+
+fn start_debugger(
+    rom_path: &str,
+    model: Option<Model>,
+    boot_rom_path: Option<&str>,
+    rtc_mode: RtcMode,
+    renderer: Renderer,
+) -> Option<Debugger> {
+    let mut debugger = Debugger::new();
+    debugger.set_rtc_mode(rtc_mode);
+    debugger.set_ppu_renderer(renderer);
+
+    if let Err(error) = debugger.load_cartridge(rom_path) {
+        println!("{}: {}", rom_path, error);
+        return None;
+    }
+
+    for warning in debugger.get_cartridge_warnings() {
+        println!("{}: {}", rom_path, warning);
+    }
+
+    if let Err(error) = debugger.boot(model, boot_rom_path) {
+        println!("Could not load the boot rom: {}", error);
+        return None;
+    }
+
+    Some(debugger)
+}
+
+//NOTE: Runs the emulation without the debugger window and prints where it ended up. The
+//      screenshot, the recording and the save file get written once it stopped.
+fn run_headless(
+    mut debugger: Debugger,
+    mut linked: Option<Debugger>,
+    options: &HeadlessOptions,
+) -> ExitCode {
+    let outcome = headless::run(&mut debugger, linked.as_mut(), options);
+    println!("{}", outcome);
+    println!("{}", headless::dump_state(&debugger));
+
+    if let Some(path) = &options.screenshot {
+        match headless::write_screenshot(path, debugger.get_framebuffer()) {
+            Ok(()) => println!("Wrote {}", path.display()),
+            Err(error) => println!("Could not write the screenshot: {}", error),
+        }
+    }
+
+    if let Some(error) = linked.as_ref().and_then(|other| other.get_cpu_error()) {
+        println!("The cpu of the linked instance stopped: {:?}", error);
+    }
+
+    if let Some(Err(error)) = linked.as_mut().map(|other| other.save()) {
+        println!(
+            "Could not write the save file of the linked instance: {}",
+            error
+        );
+    }
+
+    match debugger.stop_recording() {
+        Ok(files) => {
+            for file in files {
+                println!("Wrote {}", file.display());
+            }
+        }
+        Err(error) => println!("Could not write the recording: {}", error),
+    }
+
+    if let Err(error) = debugger.save() {
+        println!("Could not write the save file: {}", error);
+    }
+
+    ExitCode::from(outcome.exit_code(options))
+}
+
+//NOTE: Exits with 0 only if every rom passed, so it can run as a regression check
+fn run_test_roms(mut args: impl Iterator<Item = String>) -> ExitCode {
+    let Some(dir) = args.next() else {
+        println!("{}", USAGE);
+        return ExitCode::from(EXIT_USAGE);
+    };
+
+    let mut cycle_limit = harness::DEFAULT_CYCLE_LIMIT;
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--cycles", Some(count)) => match count.parse::<usize>() {
+                Ok(count) => cycle_limit = count,
+                Err(_) => {
+                    println!("Invalid cycle count {}\n{}", count, USAGE);
+                    return ExitCode::from(EXIT_USAGE);
+                }
+            },
+            _ => {
+                println!("{}", USAGE);
+                return ExitCode::from(EXIT_USAGE);
+            }
+        }
+    }
+
+    let roms = match harness::find_roms(Path::new(&dir)) {
+        Ok(roms) if !roms.is_empty() => roms,
+        Ok(_) => {
+            println!("No test roms found in {}", dir);
+            return ExitCode::from(EXIT_USAGE);
+        }
+        Err(error) => {
+            println!("{}: {}", dir, error);
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+
+    let reports = harness::run_roms(&roms, cycle_limit);
+    print!("{}", harness::summary_table(&reports));
+
+    if harness::all_passed(&reports) {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+//NOTE: Addresses are given in hex, with or without a 0x or $ in front
+fn parse_address(text: &str) -> Option<u16> {
+    let digits = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix('$'))
+        .unwrap_or(text);
+
+    u16::from_str_radix(digits, 16).ok()
 }
@@ -0,0 +1,73 @@
+use std::ops::RangeInclusive;
+
+//NOTE: Nothing drives the data bus when the cpu reads an address no device answers to, it floats
+//      high and the read returns 0xFF
+pub const OPEN_BUS: u8 = 0xFF;
+
+//NOTE: A component that owns a part of the address space. The memory only routes the addresses
+//      from `address_ranges` to it, so a device never has to deal with anything else.
+pub trait IoDevice {
+    fn address_ranges(&self) -> &'static [RangeInclusive<u16>];
+    fn read(&self, addr: u16) -> u8;
+    fn write(&mut self, addr: u16, value: u8);
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Device {
+    Cartridge,
+    Ppu,
+    Joypad,
+    Serial,
+    Timer,
+    Apu,
+    OamDma,
+    Hdma,
+    //NOTE: The ram and the registers that reach into several components, the memory handles
+    //      those itself
+    Internal,
+}
+
+//NOTE: Knows which device owns every address, unmapped addresses have no owner
+pub struct IoBus {
+    owners: Vec<Option<Device>>,
+}
+
+impl Default for IoBus {
+    fn default() -> Self {
+        Self {
+            owners: vec![None; 0x10000],
+        }
+    }
+}
+
+impl IoBus {
+    //NOTE: A later registration takes the addresses over from an earlier one
+    pub fn register(&mut self, device: Device, ranges: &[RangeInclusive<u16>]) {
+        for range in ranges {
+            for addr in range.clone() {
+                self.owners[addr as usize] = Some(device);
+            }
+        }
+    }
+
+    pub fn owner(&self, addr: u16) -> Option<Device> {
+        self.owners[addr as usize]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_register_takes_addresses_over() {
+        let mut bus = IoBus::default();
+        bus.register(Device::Timer, &[0xFF04..=0xFF07]);
+        bus.register(Device::Internal, &[0xFF04..=0xFF04]);
+
+        assert_eq!(bus.owner(0xFF03), None);
+        assert_eq!(bus.owner(0xFF04), Some(Device::Internal));
+        assert_eq!(bus.owner(0xFF07), Some(Device::Timer));
+        assert_eq!(bus.owner(0xFF08), None);
+    }
+}
@@ -0,0 +1,108 @@
+use std::ops::RangeInclusive;
+
+use super::bus::IoDevice;
+
+pub const DMA_REGISTER: u16 = 0xFF46;
+
+const DMA_TRANSFER_LENGTH: u16 = 0xA0;
+//NOTE: The transfer only starts copying two machine cycles after the write to the register
+const STARTUP_CYCLES: u8 = 2;
+
+//NOTE: The oam dma copies 160 bytes from XX00 - XX9F into the oam, one byte per machine cycle. While
+//      it runs the cpu can't use the oam and the bus the transfer reads from.
+#[derive(Default, Debug)]
+pub struct OamDma {
+    register: u8,
+    //NOTE: A new transfer that is still starting up, a running one keeps going in the meantime
+    starting: Option<(u16, u8)>,
+    active: Option<(u16, u16)>,
+}
+
+impl OamDma {
+    //NOTE: The address the transfer read from in the current machine cycle
+    pub fn source(&self) -> Option<u16> {
+        self.active
+            .map(|(source, index)| source + index.saturating_sub(1))
+    }
+
+    //NOTE: Advances the transfer by one machine cycle and returns the source address and the oam
+    //      offset of the byte that has to be copied in this cycle.
+    pub fn step(&mut self) -> Option<(u16, u16)> {
+        if let Some((source, delay)) = self.starting {
+            if delay > 1 {
+                self.starting = Some((source, delay - 1));
+            } else {
+                self.starting = None;
+                self.active = Some((source, 0));
+            }
+        }
+
+        let (source, index) = self.active?;
+
+        if index == DMA_TRANSFER_LENGTH {
+            self.active = None;
+            return None;
+        }
+
+        self.active = Some((source, index + 1));
+
+        Some((source + index, index))
+    }
+}
+
+//NOTE: Writing the register starts a new transfer
+impl IoDevice for OamDma {
+    fn address_ranges(&self) -> &'static [RangeInclusive<u16>] {
+        &[DMA_REGISTER..=DMA_REGISTER]
+    }
+
+    fn read(&self, _addr: u16) -> u8 {
+        self.register
+    }
+
+    fn write(&mut self, _addr: u16, value: u8) {
+        self.register = value;
+        self.starting = Some(((value as u16) << 8, STARTUP_CYCLES));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn run(dma: &mut OamDma, cycles: usize) -> Vec<(u16, u16)> {
+        (0..cycles).filter_map(|_| dma.step()).collect()
+    }
+
+    #[test]
+    fn test_transfer_timing() {
+        let mut dma = OamDma::default();
+        dma.write(DMA_REGISTER, 0xC1);
+
+        assert_eq!(dma.read(DMA_REGISTER), 0xC1);
+        assert_eq!(dma.step(), None);
+        assert_eq!(dma.source(), None);
+
+        assert_eq!(dma.step(), Some((0xC100, 0x00)));
+        assert_eq!(dma.source(), Some(0xC100));
+
+        let transfers = run(&mut dma, 159);
+        assert_eq!(transfers.last(), Some(&(0xC19F, 0x9F)));
+        assert_eq!(dma.source(), Some(0xC19F));
+
+        assert_eq!(dma.step(), None);
+        assert_eq!(dma.source(), None);
+    }
+
+    #[test]
+    fn test_restart_keeps_the_old_transfer_running() {
+        let mut dma = OamDma::default();
+        dma.write(DMA_REGISTER, 0xC1);
+        run(&mut dma, 12);
+
+        dma.write(DMA_REGISTER, 0xD0);
+        assert_eq!(dma.step(), Some((0xC10B, 0x0B)));
+        assert_eq!(dma.step(), Some((0xD000, 0x00)));
+        assert_eq!(dma.source(), Some(0xD000));
+    }
+}
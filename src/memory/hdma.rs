@@ -0,0 +1,131 @@
+use std::ops::RangeInclusive;
+
+use super::bus::IoDevice;
+
+pub const HDMA1_REGISTER: u16 = 0xFF51;
+pub const HDMA2_REGISTER: u16 = 0xFF52;
+pub const HDMA3_REGISTER: u16 = 0xFF53;
+pub const HDMA4_REGISTER: u16 = 0xFF54;
+pub const HDMA5_REGISTER: u16 = 0xFF55;
+
+pub const HDMA_BLOCK_SIZE: u16 = 0x10;
+const HBLANK_MODE: u8 = 0x80;
+
+//NOTE: The cgb vram dma copies blocks of 16 bytes into the vram. A general purpose transfer copies
+//      everything at once, a hblank transfer copies one block at the start of every hblank.
+#[derive(Default, Debug)]
+pub struct Hdma {
+    source: u16,
+    destination: u16,
+    //NOTE: Blocks left to copy, the length register holds this value minus one
+    blocks: u8,
+    active: bool,
+    hblank: bool,
+}
+
+impl Hdma {
+    //NOTE: Starts a new transfer, or stops a running hblank transfer if bit 7 is cleared. Returns
+    //      true if the transfer has to be copied in one go.
+    pub fn start(&mut self, value: u8) -> bool {
+        if self.active && self.hblank && value & HBLANK_MODE == 0 {
+            self.active = false;
+            return false;
+        }
+
+        self.blocks = (value & 0x7F) + 1;
+        self.active = true;
+        self.hblank = value & HBLANK_MODE != 0;
+
+        !self.hblank
+    }
+
+    pub fn is_hblank_active(&self) -> bool {
+        self.active && self.hblank
+    }
+
+    //NOTE: Source and destination of the next block, the destination wraps around inside the vram
+    pub fn next_block(&mut self) -> Option<(u16, u16)> {
+        if !self.active {
+            return None;
+        }
+
+        let block = (self.source, 0x8000 | self.destination);
+
+        self.source = self.source.wrapping_add(HDMA_BLOCK_SIZE);
+        self.destination = (self.destination + HDMA_BLOCK_SIZE) & 0x1FF0;
+        self.blocks -= 1;
+        self.active = self.blocks > 0;
+
+        Some(block)
+    }
+}
+
+//NOTE: Only there in cgb mode
+impl IoDevice for Hdma {
+    fn address_ranges(&self) -> &'static [RangeInclusive<u16>] {
+        &[HDMA1_REGISTER..=HDMA5_REGISTER]
+    }
+
+    //NOTE: Only the length register can be read back, the address registers read as 0xFF
+    fn read(&self, addr: u16) -> u8 {
+        match addr {
+            HDMA5_REGISTER => {
+                let stopped = if self.active { 0x00 } else { 0x80 };
+                stopped | (self.blocks.wrapping_sub(1) & 0x7F)
+            }
+            _ => 0xFF,
+        }
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        match addr {
+            HDMA1_REGISTER => self.source = (self.source & 0x00FF) | (value as u16) << 8,
+            HDMA2_REGISTER => self.source = (self.source & 0xFF00) | (value & 0xF0) as u16,
+            HDMA3_REGISTER => {
+                self.destination = (self.destination & 0x00FF) | ((value & 0x1F) as u16) << 8
+            }
+            HDMA4_REGISTER => {
+                self.destination = (self.destination & 0xFF00) | (value & 0xF0) as u16
+            }
+            //NOTE: Starting a transfer needs the memory, it takes this register over
+            _ => panic!("Invalid address for the hdma registers"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_hblank_transfer_can_be_stopped() {
+        let mut hdma = Hdma::default();
+        hdma.write(HDMA1_REGISTER, 0xC1);
+        hdma.write(HDMA2_REGISTER, 0x2F);
+        hdma.write(HDMA3_REGISTER, 0xFF);
+        hdma.write(HDMA4_REGISTER, 0xF0);
+
+        assert!(!hdma.start(HBLANK_MODE | 0x02));
+        assert_eq!(hdma.read(HDMA5_REGISTER), 0x02);
+
+        assert_eq!(hdma.next_block(), Some((0xC120, 0x9FF0)));
+        assert_eq!(hdma.next_block(), Some((0xC130, 0x8000)));
+        assert_eq!(hdma.read(HDMA5_REGISTER), 0x00);
+
+        assert!(!hdma.start(0x00));
+        assert!(!hdma.is_hblank_active());
+        assert_eq!(hdma.read(HDMA5_REGISTER), 0x80);
+        assert_eq!(hdma.next_block(), None);
+    }
+
+    #[test]
+    fn test_general_purpose_transfer() {
+        let mut hdma = Hdma::default();
+        assert!(hdma.start(0x01));
+
+        assert_eq!(hdma.next_block(), Some((0x0000, 0x8000)));
+        assert_eq!(hdma.next_block(), Some((0x0010, 0x8010)));
+        assert_eq!(hdma.next_block(), None);
+        assert_eq!(hdma.read(HDMA5_REGISTER), 0xFF);
+    }
+}
@@ -1,93 +1,1033 @@
+use std::ops::RangeInclusive;
+
+use crate::apu::Apu;
+use crate::cartridge::header::{CartridgeHeader, CgbSupport};
+use crate::cartridge::rtc::RtcMode;
+use crate::cartridge::{Cartridge, CartridgeError};
+use crate::cpu::interrupt::{Interrupt, INTERRUPT_FLAG_REGISTER};
 use crate::cpu::timer::RaiseInterruptFlag;
 use crate::cpu::timer::TimerController;
+use crate::joypad::Joypad;
+use crate::model::Model;
+use crate::ppu::{Ppu, PpuMode, Renderer};
+use crate::serial::link::SerialLink;
+use crate::serial::Serial;
+
+use self::bus::{Device, IoBus, IoDevice, OPEN_BUS};
+use self::dma::OamDma;
+use self::hdma::{Hdma, HDMA5_REGISTER, HDMA_BLOCK_SIZE};
+
+pub mod bus;
+mod dma;
+mod hdma;
 
 pub const DIV_CLOCK_REGISTER: u16 = 0xFF04;
 pub const TIMA_CLOCK_REGISTER: u16 = 0xFF05;
 pub const TMA_CLOCK_REGISTER: u16 = 0xFF06;
 pub const TAC_CLOCK_REGISTER: u16 = 0xFF07;
 
+pub const KEY1_REGISTER: u16 = 0xFF4D;
+
+pub const SVBK_REGISTER: u16 = 0xFF70;
+
+pub const BOOT_ROM_DISABLE_REGISTER: u16 = 0xFF50;
+pub const HEADER_CHECKSUM_ADDRESS: u16 = 0x014D;
+//NOTE: The infrared port of the cgb, nothing is connected to it so it keeps what the game wrote
+const RP_REGISTER: u16 = 0xFF56;
+
+//NOTE: The ram and the registers the memory takes care of itself, the interrupt enable register
+//      sits at the end of the high ram
+const INTERNAL_RANGES: [RangeInclusive<u16>; 5] = [
+    0xC000..=0xFDFF,
+    0xFEA0..=0xFEFF,
+    INTERRUPT_FLAG_REGISTER..=INTERRUPT_FLAG_REGISTER,
+    BOOT_ROM_DISABLE_REGISTER..=BOOT_ROM_DISABLE_REGISTER,
+    0xFF80..=0xFFFF,
+];
+const CGB_INTERNAL_RANGES: [RangeInclusive<u16>; 3] = [
+    KEY1_REGISTER..=KEY1_REGISTER,
+    RP_REGISTER..=RP_REGISTER,
+    SVBK_REGISTER..=SVBK_REGISTER,
+];
+
+//NOTE: Size of one work ram bank, the cgb has eight of them
+const WRAM_BANK_SIZE: usize = 0x1000;
+//NOTE: Machine cycles the cpu stands still for every block the vram dma copies
+const HDMA_BLOCK_CYCLES: usize = 8;
+
+#[derive(PartialEq, Eq)]
+enum Bus {
+    External,
+    Video,
+    Oam,
+}
+
+impl Bus {
+    //NOTE: The io registers and the high ram sit on the cpu's own bus, the dma never touches it
+    fn of(addr: u16) -> Option<Bus> {
+        match addr {
+            0x8000..=0x9FFF => Some(Bus::Video),
+            0xFE00..=0xFEFF => Some(Bus::Oam),
+            0xFF00..=0xFFFF => None,
+            _ => Some(Bus::External),
+        }
+    }
+}
+
 pub struct Memory {
     bytes: [u8; 0x10000],
+    wram: [u8; WRAM_BANK_SIZE * 8],
+    wram_bank: usize,
+    cartridge: Cartridge,
     timer_controller: TimerController,
+    ppu: Ppu,
+    dma: OamDma,
+    hdma: Hdma,
+    joypad: Joypad,
+    serial: Serial,
+    apu: Apu,
+    bus: IoBus,
+    //NOTE: Machine cycles the cpu has to skip because a dma transfer blocked it
+    stalled_cycles: usize,
+    boot_rom: Option<Vec<u8>>,
+    rtc_mode: RtcMode,
+    last_update: usize,
+
+    cgb_mode: bool,
+    double_speed: bool,
+    speed_switch_armed: bool,
 }
 
 impl Default for Memory {
     fn default() -> Self {
-        let mut default = Self {
+        let mut memory = Self {
             bytes: [0x00; 0x10000],
+            wram: [0x00; WRAM_BANK_SIZE * 8],
+            wram_bank: 1,
+            cartridge: Cartridge::default(),
             timer_controller: TimerController::new(),
-        };
+            ppu: Ppu::default(),
+            dma: OamDma::default(),
+            hdma: Hdma::default(),
+            joypad: Joypad::default(),
+            serial: Serial::default(),
+            apu: Apu::default(),
+            bus: IoBus::default(),
+            stalled_cycles: 0,
+            boot_rom: None,
+            rtc_mode: RtcMode::Host,
+            last_update: 0,
 
-        //TODO: This is just for testing need to fix this to the right values at a later point !
-        default.bytes[TAC_CLOCK_REGISTER as usize] = 0xF8;
-        default.bytes[0xFF44] = 0xFF;
-        default.bytes[0xFF02] = 0xFF;
+            cgb_mode: false,
+            double_speed: false,
+            speed_switch_armed: false,
+        };
 
-        default
+        memory.map_devices();
+        memory
     }
 }
 
 impl Memory {
-    pub fn get_mem_slice(&self) -> &[u8] {
-        &self.bytes[0..]
+    //NOTE: Every device claims the addresses it answers to, the ones nobody claims are open bus.
+    //      Has to run again whenever cgb mode changes as some devices only exist on the cgb.
+    fn map_devices(&mut self) {
+        let mut bus = IoBus::default();
+        bus.register(Device::Internal, &INTERNAL_RANGES);
+
+        let mut devices = vec![
+            Device::Cartridge,
+            Device::Ppu,
+            Device::Joypad,
+            Device::Serial,
+            Device::Timer,
+            Device::Apu,
+            Device::OamDma,
+        ];
+        if self.cgb_mode {
+            devices.push(Device::Hdma);
+        }
+
+        for device in devices {
+            bus.register(device, self.io_device(device).address_ranges());
+        }
+
+        //NOTE: Resetting DIV clocks the frame sequencer and starting a vram dma copies from the
+        //      whole address space, both reach into other components
+        bus.register(Device::Internal, &[DIV_CLOCK_REGISTER..=DIV_CLOCK_REGISTER]);
+        if self.cgb_mode {
+            bus.register(Device::Internal, &[HDMA5_REGISTER..=HDMA5_REGISTER]);
+            bus.register(Device::Internal, &CGB_INTERNAL_RANGES);
+        }
+
+        self.bus = bus;
+    }
+
+    fn io_device(&self, device: Device) -> &dyn IoDevice {
+        match device {
+            Device::Cartridge => &self.cartridge,
+            Device::Ppu => &self.ppu,
+            Device::Joypad => &self.joypad,
+            Device::Serial => &self.serial,
+            Device::Timer => &self.timer_controller,
+            Device::Apu => &self.apu,
+            Device::OamDma => &self.dma,
+            Device::Hdma => &self.hdma,
+            Device::Internal => unreachable!("The memory handles its own addresses"),
+        }
+    }
+
+    fn io_device_mut(&mut self, device: Device) -> &mut dyn IoDevice {
+        match device {
+            Device::Cartridge => &mut self.cartridge,
+            Device::Ppu => &mut self.ppu,
+            Device::Joypad => &mut self.joypad,
+            Device::Serial => &mut self.serial,
+            Device::Timer => &mut self.timer_controller,
+            Device::Apu => &mut self.apu,
+            Device::OamDma => &mut self.dma,
+            Device::Hdma => &mut self.hdma,
+            Device::Internal => unreachable!("The memory handles its own addresses"),
+        }
+    }
+
+    //NOTE: The whole address space the way the cpu sees it right now, banked areas included
+    pub fn get_mem_snapshot(&self) -> Vec<u8> {
+        (0x0000..=0xFFFF).map(|addr| self.read(addr)).collect()
+    }
+
+    //NOTE: Brings every component driven by the system clock up to the given machine cycle
+    pub fn update(&mut self, m_cycles: usize) {
+        let div_counter = self.timer_controller.get_div_counter();
+        self.update_timer(m_cycles);
+        self.update_frame_sequencer(div_counter);
+
+        //NOTE: The lcd and the cartridge clock don't speed up in double speed mode, they only see
+        //      half of the t-cycles.
+        let elapsed = m_cycles.saturating_sub(self.last_update);
+        let t_cycles = if self.double_speed {
+            elapsed * 2
+        } else {
+            elapsed * 4
+        };
+        self.last_update = m_cycles;
+
+        //NOTE: The dma runs off the cpu clock, it gets twice as fast in double speed mode
+        self.update_dma(elapsed);
+        self.serial.update(elapsed);
+        self.update_ppu(t_cycles);
+        self.cartridge.tick(t_cycles);
+        self.apu.update(t_cycles);
+
+        if self.serial.take_interrupt() {
+            self.request_interrupt(Interrupt::Serial);
+        }
+
+        if self.joypad.take_interrupt() {
+            self.request_interrupt(Interrupt::Joypad);
+        }
+    }
+
+    fn update_ppu(&mut self, t_cycles: usize) {
+        let interrupts = self.ppu.update(t_cycles);
+
+        if self.ppu.take_hblank_started() && self.hdma.is_hblank_active() {
+            self.copy_hdma_block();
+        }
+
+        if interrupts != 0x00 {
+            let if_reg = self.read(INTERRUPT_FLAG_REGISTER);
+            self.write(INTERRUPT_FLAG_REGISTER, if_reg | interrupts);
+        }
+    }
+
+    //NOTE: The frame sequencer of the apu steps on every falling edge of bit 4 of DIV, in double
+    //      speed mode it uses bit 5 so it keeps running at 512 Hz.
+    fn frame_sequencer_bit(&self) -> usize {
+        if self.double_speed {
+            13
+        } else {
+            12
+        }
+    }
+
+    //NOTE: The counter wraps around, only the bits above the clocking one count the edges
+    fn update_frame_sequencer(&mut self, div_counter: u16) {
+        let period_shift = self.frame_sequencer_bit() + 1;
+        let edges = (self.timer_controller.get_div_counter() >> period_shift)
+            .wrapping_sub(div_counter >> period_shift)
+            & (u16::MAX >> period_shift);
+
+        for _ in 0..edges {
+            self.apu.clock_frame_sequencer();
+        }
+    }
+
+    //NOTE: Resetting DIV while the bit is set is a falling edge as well
+    fn reset_div(&mut self) {
+        if self.timer_controller.get_div_counter() & (1 << self.frame_sequencer_bit()) != 0 {
+            self.apu.clock_frame_sequencer();
+        }
+
+        self.timer_controller.timer_reset();
+    }
+
+    fn update_dma(&mut self, m_cycles: usize) {
+        for _ in 0..m_cycles {
+            if let Some((source, offset)) = self.dma.step() {
+                let value = self.read(Self::dma_source(source));
+                self.ppu.write_oam(0xFE00 + offset, value);
+            }
+        }
+    }
+
+    //NOTE: Sources above 0xDFFF don't reach the oam or the io registers, they read the work ram
+    //      just like the echo ram does.
+    fn dma_source(addr: u16) -> u16 {
+        if addr >= 0xE000 {
+            addr - 0x2000
+        } else {
+            addr
+        }
+    }
+
+    fn start_hdma(&mut self, value: u8) {
+        if self.hdma.start(value) {
+            while self.copy_hdma_block() {}
+        } else if self.hdma.is_hblank_active() && self.ppu.get_mode() == PpuMode::HBlank {
+            //NOTE: Started inside of a hblank or with the lcd turned off the first block gets
+            //      copied right away
+            self.copy_hdma_block();
+        }
+    }
+
+    //NOTE: The destination is in the vram bank that is currently selected
+    fn copy_hdma_block(&mut self) -> bool {
+        let Some((source, destination)) = self.hdma.next_block() else {
+            return false;
+        };
+
+        for offset in 0..HDMA_BLOCK_SIZE {
+            let value = self.read(source.wrapping_add(offset));
+            self.ppu.write_vram(destination + offset, value);
+        }
+
+        //NOTE: The transfer takes the same time in both speeds, in double speed that is twice the
+        //      amount of machine cycles
+        self.stalled_cycles += if self.double_speed {
+            HDMA_BLOCK_CYCLES * 2
+        } else {
+            HDMA_BLOCK_CYCLES
+        };
+
+        true
+    }
+
+    //NOTE: The cpu doesn't run while the vram dma copies, it has to skip these cycles
+    pub fn take_stalled_cycles(&mut self) -> usize {
+        std::mem::take(&mut self.stalled_cycles)
     }
 
-    pub fn update_timer(&mut self, m_cycles: usize) {
+    fn update_timer(&mut self, m_cycles: usize) {
         let timer_overflow = self.timer_controller.update(m_cycles);
 
-        match timer_overflow {
-            RaiseInterruptFlag::CustomTimerOverflowed => self.set_timer_ie(),
-            _ => (),
+        if let RaiseInterruptFlag::CustomTimerOverflowed = timer_overflow {
+            self.request_interrupt(Interrupt::Timer);
         }
     }
 
-    fn set_timer_ie(&mut self) {
-        let if_reg = self.read(0xFF0F);
-        let if_reg = if_reg | (0x01 << 2);
-        self.write(0xFF0F, if_reg);
+    //NOTE: In stop mode nothing runs, the components only take note that the time went by so they
+    //      don't catch up on it once the cpu wakes up again
+    pub fn skip_stopped(&mut self, m_cycles: usize) {
+        self.timer_controller.skip(m_cycles);
+        self.last_update = m_cycles;
+    }
+
+    pub fn request_interrupt(&mut self, interrupt: Interrupt) {
+        let if_reg = self.read(INTERRUPT_FLAG_REGISTER);
+        let if_reg = if_reg | interrupt.mask();
+        self.write(INTERRUPT_FLAG_REGISTER, if_reg);
+    }
+
+    pub fn load_cartridge(&mut self, bytes: &[u8]) -> Result<(), CartridgeError> {
+        self.cartridge = Cartridge::from_bytes(bytes, self.rtc_mode)?;
+        self.cgb_mode = self
+            .cartridge
+            .header()
+            .is_some_and(|header| header.cgb_support != CgbSupport::None);
+        self.ppu.set_cgb_mode(self.cgb_mode);
+        self.serial.set_cgb_mode(self.cgb_mode);
+        self.map_devices();
+
+        Ok(())
+    }
+
+    pub fn get_cartridge_header(&self) -> Option<&CartridgeHeader> {
+        self.cartridge.header()
+    }
+
+    pub fn get_cartridge_mut(&mut self) -> &mut Cartridge {
+        &mut self.cartridge
+    }
+
+    //NOTE: The buttons are pressed and released from the outside, the interrupt gets requested with
+    //      the next update
+    pub fn get_joypad_mut(&mut self) -> &mut Joypad {
+        &mut self.joypad
+    }
+
+    pub fn set_ppu_renderer(&mut self, renderer: Renderer) {
+        self.ppu.set_renderer(renderer);
     }
 
-    pub fn load_cartridge(&mut self, bytes: &[u8]) {
-        for (i, byte) in bytes.iter().enumerate() {
-            self.bytes[i] = *byte;
+    //NOTE: Has to be picked before the cartridge gets loaded
+    pub fn set_rtc_mode(&mut self, rtc_mode: RtcMode) {
+        self.rtc_mode = rtc_mode;
+    }
+
+    pub fn get_ppu(&self) -> &Ppu {
+        &self.ppu
+    }
+
+    //NOTE: The stereo frames the apu produced since the last call, at `apu::SAMPLE_RATE`
+    pub fn take_audio_samples(&mut self) -> Vec<[f32; 2]> {
+        self.apu.take_samples()
+    }
+
+    pub fn get_apu(&self) -> &Apu {
+        &self.apu
+    }
+
+    pub fn get_apu_mut(&mut self) -> &mut Apu {
+        &mut self.apu
+    }
+
+    //NOTE: Everything the game sent over the serial port, the blargg test roms report their results
+    //      this way.
+    pub fn get_serial_output(&self) -> &str {
+        self.serial.get_output()
+    }
+
+    pub fn set_serial_link(&mut self, link: Box<dyn SerialLink>) {
+        self.serial.set_link(link);
+    }
+
+    //NOTE: Only a cgb runs cartridges in cgb mode, the other models ignore the cgb flag
+    pub fn set_model(&mut self, model: Model) {
+        if model != Model::Cgb {
+            self.cgb_mode = false;
+            self.ppu.set_cgb_mode(false);
+            self.serial.set_cgb_mode(false);
+            self.map_devices();
+        }
+    }
+
+    //NOTE: Skipping the boot rom means we have to leave the io registers the way it would have
+    pub fn apply_post_boot_state(&mut self, model: Model) {
+        self.set_model(model);
+
+        for (addr, value) in model.post_boot_io() {
+            self.write(addr, value);
+        }
+
+        self.timer_controller
+            .set_div_counter(model.post_boot_div_counter());
+    }
+
+    //NOTE: The boot rom overlays the cartridge until the game writes to 0xFF50. The cgb boot rom
+    //      is bigger and leaves a hole at 0x0100 - 0x01FF so the cartridge header stays visible.
+    pub fn load_boot_rom(&mut self, bytes: &[u8]) {
+        self.boot_rom = Some(bytes.to_vec());
+    }
+
+    pub fn is_boot_rom_mapped(&self) -> bool {
+        self.boot_rom.is_some()
+    }
+
+    fn read_boot_rom(&self, addr: u16) -> Option<u8> {
+        let boot_rom = self.boot_rom.as_ref()?;
+
+        if (0x0100..0x0200).contains(&addr) {
+            return None;
+        }
+
+        boot_rom.get(addr as usize).copied()
+    }
+
+    pub fn is_cgb_mode(&self) -> bool {
+        self.cgb_mode
+    }
+
+    //NOTE: In double speed mode the timer keeps running off the cpu clock, the lcd however does
+    //      not speed up and only sees half of the machine cycles.
+    pub fn is_double_speed(&self) -> bool {
+        self.double_speed
+    }
+
+    pub fn is_speed_switch_armed(&self) -> bool {
+        self.speed_switch_armed
+    }
+
+    pub fn switch_speed(&mut self) {
+        self.double_speed = !self.double_speed;
+        self.speed_switch_armed = false;
+    }
+
+    //NOTE: Accesses of the cpu have to go through the dma bus conflicts, the debugger and the
+    //      components use `read` and `write` which always see the real memory.
+    pub fn cpu_read(&self, addr: u16) -> u8 {
+        self.dma_conflict(addr).unwrap_or_else(|| self.read(addr))
+    }
+
+    pub fn cpu_write(&mut self, addr: u16, value: u8) {
+        if self.dma_conflict(addr).is_none() {
+            self.write(addr, value);
+        }
+    }
+
+    //NOTE: While the dma runs the oam reads as 0xFF. Reads from the bus the dma is using return the
+    //      byte that is being copied and writes to it get lost. Only the io registers and the high
+    //      ram are safe, which is why games run their dma routine from the high ram.
+    fn dma_conflict(&self, addr: u16) -> Option<u8> {
+        let source = Self::dma_source(self.dma.source()?);
+
+        match (Bus::of(addr)?, Bus::of(source)?) {
+            (Bus::Oam, _) => Some(0xFF),
+            (bus, source_bus) if bus == source_bus => Some(self.read(source)),
+            _ => None,
         }
     }
 
     pub fn read(&self, addr: u16) -> u8 {
-        match addr {
-            0xFF04..=0xFF07 => self.timer_controller.read(addr),
-            _ => self.read_generic(addr),
+        if let Some(value) = self.read_boot_rom(addr) {
+            return value;
+        }
+
+        match self.bus.owner(addr) {
+            Some(Device::Internal) => self.read_internal(addr),
+            Some(device) => self.io_device(device).read(addr),
+            None => OPEN_BUS,
         }
     }
 
     pub fn write(&mut self, addr: u16, value: u8) {
+        match self.bus.owner(addr) {
+            Some(Device::Internal) => self.write_internal(addr, value),
+            Some(device) => self.io_device_mut(device).write(addr, value),
+            None => (),
+        }
+    }
+
+    fn read_internal(&self, addr: u16) -> u8 {
         match addr {
-            0xFF02 => self.serial_write_debug(value),
-            0xFF04 => self.timer_controller.timer_reset(),
-            0xFF05 | 0xFF06 | 0xFF07 => self.timer_controller.write(addr, value),
-            _ => self.write_generic(addr, value),
-        };
-        /*if addr == 0xFF02 && value == 0x81 {
-            let byte = char::from(self.bytes[0xFF01]);
-            print!("{}", byte);
+            0xC000..=0xFDFF => self.wram[self.wram_offset(addr)],
+            0xFEA0..=0xFEFF => self.read_unusable(),
+            INTERRUPT_FLAG_REGISTER => 0xE0 | self.bytes[addr as usize],
+            DIV_CLOCK_REGISTER => self.timer_controller.read(addr),
+            HDMA5_REGISTER => self.hdma.read(addr),
+            KEY1_REGISTER => self.read_key1(),
+            //NOTE: The bit in the middle is the receiver, it reads as 1 as long as no light arrives
+            RP_REGISTER => 0x3E | self.bytes[addr as usize],
+            SVBK_REGISTER => 0xF8 | self.wram_bank as u8,
+            //NOTE: The boot rom can only be turned off, the register doesn't read back
+            BOOT_ROM_DISABLE_REGISTER => OPEN_BUS,
+            _ => self.bytes[addr as usize],
+        }
+    }
+
+    fn write_internal(&mut self, addr: u16, value: u8) {
+        match addr {
+            0xC000..=0xFDFF => self.wram[self.wram_offset(addr)] = value,
+            0xFEA0..=0xFEFF => (),
+            DIV_CLOCK_REGISTER => self.reset_div(),
+            HDMA5_REGISTER => self.start_hdma(value),
+            KEY1_REGISTER => self.write_key1(value),
+            RP_REGISTER => self.bytes[addr as usize] = value & 0xC1,
+            SVBK_REGISTER => self.write_svbk(value),
+            BOOT_ROM_DISABLE_REGISTER => self.write_boot_rom_disable(value),
+            _ => self.bytes[addr as usize] = value,
         }
+    }
 
-        self.bytes[addr as usize] = value;*/
+    //NOTE: 0xC000 - 0xCFFF is always the first bank, on the cgb 0xD000 - 0xDFFF can be switched to
+    //      any of the other seven. 0xE000 - 0xFDFF mirrors the work ram, the banked part included.
+    fn wram_offset(&self, addr: u16) -> usize {
+        match addr {
+            0xC000..=0xCFFF => (addr - 0xC000) as usize,
+            0xD000..=0xDFFF => self.wram_bank * WRAM_BANK_SIZE + (addr - 0xD000) as usize,
+            _ => self.wram_offset(addr - 0x2000),
+        }
     }
 
-    fn read_generic(&self, addr: u16) -> u8 {
-        self.bytes[addr as usize]
+    //NOTE: Nothing is wired up behind the oam. The dmg reads 0x00 here unless the ppu is busy with the
+    //      oam, then the bus is locked and it reads 0xFF. Writes get lost either way.
+    fn read_unusable(&self) -> u8 {
+        match self.ppu.get_mode() {
+            PpuMode::OamScan | PpuMode::Drawing => OPEN_BUS,
+            _ => 0x00,
+        }
     }
-    fn write_generic(&mut self, addr: u16, value: u8) {
-        self.bytes[addr as usize] = value;
+
+    fn write_svbk(&mut self, value: u8) {
+        self.wram_bank = ((value & 0x07) as usize).max(1);
     }
 
-    fn serial_write_debug(&mut self, value: u8) {
-        if value == 0x81 {
-            let byte = char::from(self.bytes[0xFF01]);
-            print!("{}", byte);
-        } else {
-            self.bytes[0xFF02] = value;
+    fn read_key1(&self) -> u8 {
+        let speed = if self.double_speed { 0x80 } else { 0x00 };
+        let armed = if self.speed_switch_armed { 0x01 } else { 0x00 };
+
+        0x7E | speed | armed
+    }
+
+    fn write_key1(&mut self, value: u8) {
+        self.speed_switch_armed = value & 0x01 == 0x01;
+    }
+
+    fn write_boot_rom_disable(&mut self, value: u8) {
+        //NOTE: Once unmapped the boot rom stays gone until the next power cycle
+        if value != 0x00 {
+            self.boot_rom = None;
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::harness;
+    use crate::joypad::{Button, JOYPAD_REGISTER};
+    use crate::memory::dma::DMA_REGISTER;
+    use crate::memory::hdma::{HDMA1_REGISTER, HDMA2_REGISTER, HDMA3_REGISTER, HDMA4_REGISTER};
+    use crate::ppu::{LCDC_REGISTER, VBK_REGISTER};
+    use crate::serial::{SB_REGISTER, SC_REGISTER};
+    use std::path::{Path, PathBuf};
+
+    //NOTE: A plain 32 KiB cartridge without a memory bank controller filled up with `value`
+    fn filled_cartridge(value: u8) -> Vec<u8> {
+        let mut cartridge = vec![value; 0x8000];
+        cartridge[0x0147] = 0x00;
+        cartridge[0x0148] = 0x00;
+        cartridge[0x0149] = 0x00;
+        cartridge
+    }
+
+    #[test]
+    fn test_boot_rom_is_mapped_until_disabled() {
+        let mut mem = Memory::default();
+        mem.load_cartridge(&filled_cartridge(0x11)).unwrap();
+        mem.load_boot_rom(&[0x22; 0x100]);
+
+        assert_eq!(mem.read(0x0000), 0x22);
+        assert_eq!(mem.read(0x00FF), 0x22);
+        assert_eq!(mem.read(0x0100), 0x11);
+
+        mem.write(BOOT_ROM_DISABLE_REGISTER, 0x01);
+
+        assert!(!mem.is_boot_rom_mapped());
+        assert_eq!(mem.read(0x0000), 0x11);
+    }
+
+    #[test]
+    fn test_cgb_boot_rom_leaves_the_header_visible() {
+        let mut mem = Memory::default();
+        mem.load_cartridge(&filled_cartridge(0x11)).unwrap();
+        mem.load_boot_rom(&[0x22; 0x900]);
+
+        assert_eq!(mem.read(0x00FF), 0x22);
+        assert_eq!(mem.read(0x0143), 0x11);
+        assert_eq!(mem.read(0x0200), 0x22);
+        assert_eq!(mem.read(0x08FF), 0x22);
+        assert_eq!(mem.read(0x0900), 0x11);
+    }
+
+    #[test]
+    fn test_post_boot_io_state() {
+        let mut mem = Memory::default();
+        mem.apply_post_boot_state(Model::Dmg);
+
+        assert_eq!(mem.read(DIV_CLOCK_REGISTER), 0xAB);
+        assert_eq!(mem.read(TAC_CLOCK_REGISTER), 0xF8);
+        assert_eq!(mem.read(INTERRUPT_FLAG_REGISTER), 0xE1);
+        assert_eq!(mem.read(0xFF40), 0x91);
+        assert_eq!(mem.read(0xFF26), 0xF1);
+
+        let mut mem = Memory::default();
+        mem.apply_post_boot_state(Model::Sgb);
+
+        assert_eq!(mem.read(0xFF26), 0xF0);
+    }
+
+    #[test]
+    fn test_oam_dma() {
+        let mut mem = Memory::default();
+        mem.load_cartridge(&filled_cartridge(0x11)).unwrap();
+
+        for offset in 0..0xA0 {
+            mem.write(0xC100 + offset, 0x20 + offset as u8);
+        }
+
+        mem.write(DMA_REGISTER, 0xC1);
+        assert_eq!(mem.read(DMA_REGISTER), 0xC1);
+
+        //NOTE: The rom shares the bus with the work ram, the vram and the high ram don't
+        mem.update(2);
+        assert_eq!(mem.cpu_read(0xFE00), 0xFF);
+        assert_eq!(mem.cpu_read(0x0000), 0x20);
+        assert_eq!(mem.cpu_read(0x9000), 0x00);
+
+        mem.cpu_write(0xC000, 0x42);
+        mem.cpu_write(0xFF80, 0x42);
+        assert_eq!(mem.read(0xC000), 0x00);
+        assert_eq!(mem.cpu_read(0xFF80), 0x42);
+
+        mem.update(161);
+        assert_eq!(mem.cpu_read(0x0000), 0xBF);
+
+        mem.update(162);
+        assert_eq!(mem.cpu_read(0x0000), 0x11);
+        assert_eq!(mem.cpu_read(0xFE00), 0x20);
+        assert_eq!(mem.cpu_read(0xFE9F), 0xBF);
+    }
+
+    #[test]
+    fn test_joypad_interrupt() {
+        let mut mem = Memory::default();
+        mem.write(JOYPAD_REGISTER, 0x10);
+        mem.get_joypad_mut().press(Button::Start);
+        assert_eq!(mem.read(JOYPAD_REGISTER), 0xD7);
+        assert_eq!(
+            mem.read(INTERRUPT_FLAG_REGISTER) & Interrupt::Joypad.mask(),
+            0x00
+        );
+
+        mem.update(1);
+        assert_eq!(
+            mem.read(INTERRUPT_FLAG_REGISTER) & Interrupt::Joypad.mask(),
+            Interrupt::Joypad.mask()
+        );
+    }
+
+    #[test]
+    fn test_serial_interrupt() {
+        let mut mem = Memory::default();
+        mem.set_serial_link(Box::new(crate::serial::link::LoopbackLink));
+        mem.write(SB_REGISTER, 0x3C);
+        mem.write(SC_REGISTER, 0x81);
+
+        //NOTE: Eight bits at 8192 Hz take 1024 machine cycles
+        mem.update(1023);
+        assert_eq!(
+            mem.read(INTERRUPT_FLAG_REGISTER) & Interrupt::Serial.mask(),
+            0x00
+        );
+
+        mem.update(1024);
+        assert_eq!(
+            mem.read(INTERRUPT_FLAG_REGISTER) & Interrupt::Serial.mask(),
+            Interrupt::Serial.mask()
+        );
+        assert_eq!(mem.read(SB_REGISTER), 0x3C);
+        assert_eq!(mem.read(SC_REGISTER), 0x7F);
+    }
+
+    fn cgb_memory() -> Memory {
+        let mut cartridge = filled_cartridge(0x00);
+        cartridge[0x0143] = 0x80;
+
+        let mut mem = Memory::default();
+        mem.load_cartridge(&cartridge).unwrap();
+        mem
+    }
+
+    #[test]
+    fn test_cgb_wram_banking() {
+        let mut mem = cgb_memory();
+        mem.write(0xC000, 0x33);
+        mem.write(0xD000, 0x11);
+
+        mem.write(SVBK_REGISTER, 0x02);
+        assert_eq!(mem.read(SVBK_REGISTER), 0xFA);
+        assert_eq!(mem.read(0xD000), 0x00);
+        mem.write(0xD000, 0x22);
+
+        //NOTE: Bank zero can't be mapped to 0xD000, it selects bank one instead
+        mem.write(SVBK_REGISTER, 0x00);
+        assert_eq!(mem.read(SVBK_REGISTER), 0xF9);
+        assert_eq!(mem.read(0xC000), 0x33);
+        assert_eq!(mem.read(0xD000), 0x11);
+
+        let mut mem = Memory::default();
+        mem.write(SVBK_REGISTER, 0x02);
+        assert_eq!(mem.read(SVBK_REGISTER), 0xFF);
+    }
+
+    #[test]
+    fn test_hdma_transfers() {
+        let mut mem = cgb_memory();
+
+        for offset in 0..0x20 {
+            mem.write(0xC000 + offset, offset as u8 + 1);
+        }
+
+        //NOTE: A general purpose transfer copies both blocks at once into the selected vram bank
+        mem.write(VBK_REGISTER, 0x01);
+        mem.write(HDMA1_REGISTER, 0xC0);
+        mem.write(HDMA2_REGISTER, 0x00);
+        mem.write(HDMA3_REGISTER, 0x00);
+        mem.write(HDMA4_REGISTER, 0x10);
+        mem.write(HDMA5_REGISTER, 0x01);
+
+        assert_eq!(mem.take_stalled_cycles(), 16);
+        assert_eq!(mem.read(HDMA5_REGISTER), 0xFF);
+        assert_eq!(mem.read(0x8010), 0x01);
+        assert_eq!(mem.read(0x802F), 0x20);
+        mem.write(VBK_REGISTER, 0x00);
+        assert_eq!(mem.read(0x8010), 0x00);
+
+        //NOTE: A hblank transfer copies one block at the start of every hblank
+        mem.write(HDMA1_REGISTER, 0xC0);
+        mem.write(HDMA2_REGISTER, 0x00);
+        mem.write(HDMA3_REGISTER, 0x00);
+        mem.write(HDMA4_REGISTER, 0x00);
+        mem.write(LCDC_REGISTER, 0x91);
+        mem.write(HDMA5_REGISTER, 0x81);
+        assert_eq!(mem.read(HDMA5_REGISTER), 0x01);
+
+        mem.update(63);
+        assert_eq!(mem.read(HDMA5_REGISTER), 0x00);
+        assert_eq!(mem.read(0x8000), 0x01);
+        assert_eq!(mem.read(0x8010), 0x00);
+
+        mem.update(63 + 114);
+        assert_eq!(mem.read(HDMA5_REGISTER), 0xFF);
+        assert_eq!(mem.read(0x8010), 0x11);
+        assert_eq!(mem.take_stalled_cycles(), 16);
+    }
+
+    #[test]
+    #[ignore = "needs the mooneye oam dma roms in test_roms/"]
+    fn test_mooneye_oam_dma() {
+        let roms: Vec<_> = [
+            "test_roms/mooneye-tests/acceptance/oam_dma/basic.gb",
+            "test_roms/mooneye-tests/acceptance/oam_dma/reg_read.gb",
+            "test_roms/mooneye-tests/acceptance/oam_dma/sources-GS.gb",
+            "test_roms/mooneye-tests/acceptance/oam_dma_restart.gb",
+            "test_roms/mooneye-tests/acceptance/oam_dma_start.gb",
+            "test_roms/mooneye-tests/acceptance/oam_dma_timing.gb",
+        ]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+
+        let reports = harness::run_roms(&roms, 10_000_000);
+        assert!(
+            harness::all_passed(&reports),
+            "{}",
+            harness::summary_table(&reports)
+        );
+    }
+
+    #[test]
+    fn test_only_a_cgb_runs_in_cgb_mode() {
+        let mut cartridge = vec![0x00; 0x150];
+        cartridge[0x0143] = 0x80;
+
+        let mut mem = Memory::default();
+        mem.load_cartridge(&cartridge).unwrap();
+        mem.apply_post_boot_state(Model::Cgb);
+        assert!(mem.is_cgb_mode());
+
+        let mut mem = Memory::default();
+        mem.load_cartridge(&cartridge).unwrap();
+        mem.apply_post_boot_state(Model::Dmg);
+        assert!(!mem.is_cgb_mode());
+    }
+
+    #[test]
+    fn test_unmapped_io_is_open_bus() {
+        let mut mem = Memory::default();
+        for addr in [0xFF03, 0xFF4C, 0xFF56, 0xFF7F] {
+            mem.write(addr, 0x12);
+            assert_eq!(mem.read(addr), 0xFF);
+        }
+
+        let mut cartridge = vec![0x00; 0x150];
+        cartridge[0x0143] = 0x80;
+        mem.load_cartridge(&cartridge).unwrap();
+
+        //NOTE: The cgb registers only show up in cgb mode
+        mem.write(0xFF56, 0x01);
+        assert_eq!(mem.read(0xFF56), 0x3F);
+        assert_eq!(mem.read(0xFF03), 0xFF);
+
+        mem.set_model(Model::Dmg);
+        assert_eq!(mem.read(0xFF56), 0xFF);
+    }
+
+    #[test]
+    fn test_echo_ram_mirrors_the_work_ram() {
+        let mut cartridge = vec![0x00; 0x150];
+        cartridge[0x0143] = 0x80;
+        let mut mem = Memory::default();
+        mem.load_cartridge(&cartridge).unwrap();
+
+        mem.write(0xC123, 0x11);
+        assert_eq!(mem.read(0xE123), 0x11);
+        mem.write(0xFDFF, 0x22);
+        assert_eq!(mem.read(0xDDFF), 0x22);
+
+        //NOTE: The mirror follows the selected bank
+        mem.write(SVBK_REGISTER, 0x03);
+        mem.write(0xD456, 0x33);
+        assert_eq!(mem.read(0xF456), 0x33);
+        mem.write(SVBK_REGISTER, 0x01);
+        assert_eq!(mem.read(0xF456), 0x00);
+    }
+
+    #[test]
+    fn test_unusable_region() {
+        let mut mem = Memory::default();
+        mem.write(0xFEA0, 0x12);
+        mem.write(0xFEFF, 0x34);
+        assert_eq!(mem.read(0xFEA0), 0x00);
+        assert_eq!(mem.read(0xFEFF), 0x00);
+
+        //NOTE: Locked while the ppu scans the oam
+        mem.write(LCDC_REGISTER, 0x80);
+        assert_eq!(mem.get_ppu().get_mode(), PpuMode::OamScan);
+        assert_eq!(mem.read(0xFEA0), 0xFF);
+    }
+
+    //NOTE: Every register gets written with zeros, what reads back are the bits that don't exist
+    #[test]
+    fn test_io_register_read_masks() {
+        let registers = [
+            (0xFF00, 0xCF),
+            (0xFF01, 0x00),
+            (0xFF02, 0x7E),
+            (0xFF04, 0x00),
+            (0xFF05, 0x00),
+            (0xFF06, 0x00),
+            (0xFF07, 0xF8),
+            (0xFF0F, 0xE0),
+            (0xFF40, 0x00),
+            //NOTE: LY and LYC are both zero, so the coincidence bit is set
+            (0xFF41, 0x84),
+            (0xFF42, 0x00),
+            (0xFF43, 0x00),
+            (0xFF44, 0x00),
+            (0xFF45, 0x00),
+            (0xFF46, 0x00),
+            (0xFF47, 0x00),
+            (0xFF48, 0x00),
+            (0xFF49, 0x00),
+            (0xFF4A, 0x00),
+            (0xFF4B, 0x00),
+            (0xFF4D, 0xFF),
+            (0xFF4F, 0xFF),
+            (0xFF50, 0xFF),
+            (0xFF51, 0xFF),
+            (0xFF55, 0xFF),
+            (0xFF56, 0xFF),
+            (0xFF68, 0xFF),
+            (0xFF69, 0xFF),
+            (0xFF6A, 0xFF),
+            (0xFF6B, 0xFF),
+            (0xFF70, 0xFF),
+            (0xFFFF, 0x00),
+        ];
+
+        let mut mem = Memory::default();
+        for (addr, expected) in registers {
+            mem.write(addr, 0x00);
+            assert_eq!(mem.read(addr), expected, "register {:04X}", addr);
+        }
+
+        //NOTE: The apu registers only take writes while it is turned on
+        mem.write(0xFF26, 0x80);
+        let masks = [
+            0x80, 0x3F, 0x00, 0xFF, 0xBF, 0xFF, 0x3F, 0x00, 0xFF, 0xBF, 0x7F, 0xFF, 0x9F, 0xFF,
+            0xBF, 0xFF, 0xFF, 0x00, 0x00, 0xBF, 0x00, 0x00,
+        ];
+        for (addr, expected) in (0xFF10..=0xFF25).zip(masks) {
+            mem.write(addr, 0x00);
+            assert_eq!(mem.read(addr), expected, "register {:04X}", addr);
+        }
+        assert_eq!(mem.read(0xFF26), 0xF0);
+        mem.write(0xFF30, 0x00);
+        assert_eq!(mem.read(0xFF30), 0x00);
+    }
+
+    #[test]
+    fn test_cgb_io_register_read_masks() {
+        let registers = [
+            (0xFF02, 0x7C),
+            (0xFF4D, 0x7E),
+            (0xFF4F, 0xFE),
+            (0xFF51, 0xFF),
+            (0xFF52, 0xFF),
+            (0xFF53, 0xFF),
+            (0xFF54, 0xFF),
+            //NOTE: Starts a one block transfer that is done right away
+            (0xFF55, 0xFF),
+            (0xFF56, 0x3E),
+            (0xFF68, 0x40),
+            (0xFF69, 0x00),
+            (0xFF6A, 0x40),
+            (0xFF6B, 0x00),
+            //NOTE: Bank 0 selects bank 1
+            (0xFF70, 0xF9),
+        ];
+
+        let mut cartridge = vec![0x00; 0x150];
+        cartridge[0x0143] = 0x80;
+        let mut mem = Memory::default();
+        mem.load_cartridge(&cartridge).unwrap();
+
+        for (addr, expected) in registers {
+            mem.write(addr, 0x00);
+            assert_eq!(mem.read(addr), expected, "register {:04X}", addr);
+        }
+    }
+
+    #[test]
+    fn test_div_clocks_the_frame_sequencer() {
+        let mut mem = Memory::default();
+        mem.write(0xFF26, 0x80);
+        mem.write(0xFF12, 0xF0);
+        mem.write(0xFF11, 0x3E);
+        mem.write(0xFF14, 0xC0);
+        assert_eq!(mem.read(0xFF26), 0xF1);
+
+        //NOTE: The falling edges of bit 12 come every 2048 machine cycles, the length counter
+        //      gets clocked by steps 0 and 2
+        mem.update(2048 * 2);
+        assert_eq!(mem.read(0xFF26), 0xF1);
+        mem.update(2048 * 3);
+        assert_eq!(mem.read(0xFF26), 0xF0);
+
+        //NOTE: Resetting DIV with bit 12 set clocks the frame sequencer as well, the next step is 4
+        mem.write(0xFF11, 0x3F);
+        mem.write(0xFF14, 0xC0);
+        mem.update(2048 * 4 + 1024);
+        assert_eq!(mem.read(0xFF26), 0xF1);
+        mem.write(DIV_CLOCK_REGISTER, 0x00);
+        assert_eq!(mem.read(0xFF26), 0xF0);
+    }
+
+    #[test]
+    #[ignore = "needs the blargg dmg_sound roms in test_roms/"]
+    fn test_blargg_dmg_sound() {
+        const DMG_SOUND_ROMS: &str = "test_roms/blarggs/dmg_sound/rom_singles";
+
+        let roms = harness::find_roms(Path::new(DMG_SOUND_ROMS)).expect(DMG_SOUND_ROMS);
+
+        //NOTE: These report through the memory at 0xA000 and take a while
+        let reports = harness::run_roms(&roms, 100_000_000);
+        assert!(
+            harness::all_passed(&reports),
+            "{}",
+            harness::summary_table(&reports)
+        );
+    }
+}
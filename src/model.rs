@@ -0,0 +1,160 @@
+use std::fmt::Display;
+
+//NOTE: Register values are in the order A, F, B, C, D, E, H, L
+type RegisterState = [u8; 8];
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Model {
+    Dmg0,
+    Dmg,
+    Mgb,
+    Sgb,
+    Cgb,
+}
+
+impl Model {
+    //NOTE: Values every model shares after the boot rom handed over to the cartridge at 0x0100, the
+    //      model specific differences get patched in by `post_boot_io`.
+    const IO_TABLE: [(u16, u8); 40] = [
+        (0xFF00, 0xCF),
+        (0xFF01, 0x00),
+        (0xFF02, 0x7E),
+        (0xFF05, 0x00),
+        (0xFF06, 0x00),
+        (0xFF07, 0xF8),
+        (0xFF0F, 0xE1),
+        //NOTE: The apu ignores writes to its other registers while it is turned off
+        (0xFF26, 0xF1),
+        (0xFF10, 0x80),
+        (0xFF11, 0xBF),
+        (0xFF12, 0xF3),
+        (0xFF13, 0xFF),
+        //NOTE: Triggers channel 1 without its length counter, it reads back as 0xBF
+        (0xFF14, 0x87),
+        (0xFF16, 0x3F),
+        (0xFF17, 0x00),
+        (0xFF18, 0xFF),
+        (0xFF19, 0xBF),
+        (0xFF1A, 0x7F),
+        (0xFF1B, 0xFF),
+        (0xFF1C, 0x9F),
+        (0xFF1D, 0xFF),
+        (0xFF1E, 0xBF),
+        (0xFF20, 0xFF),
+        (0xFF21, 0x00),
+        (0xFF22, 0x00),
+        (0xFF23, 0xBF),
+        (0xFF24, 0x77),
+        (0xFF25, 0xF3),
+        (0xFF40, 0x91),
+        (0xFF41, 0x85),
+        (0xFF42, 0x00),
+        (0xFF43, 0x00),
+        (0xFF44, 0x00),
+        (0xFF45, 0x00),
+        (0xFF47, 0xFC),
+        (0xFF48, 0xFF),
+        (0xFF49, 0xFF),
+        (0xFF4A, 0x00),
+        (0xFF4B, 0x00),
+        (0xFFFF, 0x00),
+    ];
+
+    //NOTE: The sgb boot rom doesn't play the startup sound, turning the dac of channel 1 off and on
+    //      again leaves the channel disabled with the same register values
+    const SGB_IO_TABLE: [(u16, u8); 3] = [(0xFF12, 0x00), (0xFF12, 0xF3), (0xFF26, 0xF0)];
+
+    const CGB_IO_TABLE: [(u16, u8); 4] = [
+        (0xFF02, 0x7F),
+        (0xFF4F, 0xFE),
+        (0xFF56, 0x3E),
+        (0xFF70, 0xF8),
+    ];
+
+    pub fn from_name(name: &str) -> Option<Model> {
+        match name.to_ascii_lowercase().as_str() {
+            "dmg0" => Some(Model::Dmg0),
+            "dmg" => Some(Model::Dmg),
+            "mgb" => Some(Model::Mgb),
+            "sgb" => Some(Model::Sgb),
+            "cgb" => Some(Model::Cgb),
+            _ => None,
+        }
+    }
+
+    //NOTE: The dmg boot roms set the half carry and carry flag unless the header checksum is zero,
+    //      the cgb boot rom leaves a different state behind when it runs a dmg cartridge.
+    pub fn post_boot_registers(self, header_checksum: u8, cgb_mode: bool) -> RegisterState {
+        let dmg_flags = if header_checksum == 0x00 { 0x80 } else { 0xB0 };
+
+        match self {
+            Model::Dmg0 => [0x01, 0x00, 0xFF, 0x13, 0x00, 0xC1, 0x84, 0x03],
+            Model::Dmg => [0x01, dmg_flags, 0x00, 0x13, 0x00, 0xD8, 0x01, 0x4D],
+            Model::Mgb => [0xFF, dmg_flags, 0x00, 0x13, 0x00, 0xD8, 0x01, 0x4D],
+            Model::Sgb => [0x01, 0x00, 0x00, 0x14, 0x00, 0x00, 0xC0, 0x60],
+            Model::Cgb if cgb_mode => [0x11, 0x80, 0x00, 0x00, 0xFF, 0x56, 0x00, 0x0D],
+            //TODO: B is derived from the title checksum for licensed nintendo cartridges
+            Model::Cgb => [0x11, 0x80, 0x00, 0x00, 0x00, 0x08, 0x00, 0x7C],
+        }
+    }
+
+    pub fn post_boot_io(self) -> Vec<(u16, u8)> {
+        let mut io = Self::IO_TABLE.to_vec();
+
+        match self {
+            Model::Sgb => io.extend_from_slice(&Self::SGB_IO_TABLE),
+            Model::Cgb => io.extend_from_slice(&Self::CGB_IO_TABLE),
+            _ => (),
+        }
+
+        io
+    }
+
+    //NOTE: The internal 16 bit counter behind DIV, only the dmg values are documented. The others
+    //      depend on how long the boot rom took, so we start them from zero.
+    pub fn post_boot_div_counter(self) -> u16 {
+        match self {
+            Model::Dmg0 => 0x1830,
+            Model::Dmg | Model::Mgb => 0xABCC,
+            Model::Sgb | Model::Cgb => 0x0000,
+        }
+    }
+}
+
+impl Display for Model {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Model::Dmg0 => write!(f, "DMG0"),
+            Model::Dmg => write!(f, "DMG"),
+            Model::Mgb => write!(f, "MGB"),
+            Model::Sgb => write!(f, "SGB"),
+            Model::Cgb => write!(f, "CGB"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_model_from_name() {
+        assert_eq!(Model::from_name("dmg0"), Some(Model::Dmg0));
+        assert_eq!(Model::from_name("DMG"), Some(Model::Dmg));
+        assert_eq!(Model::from_name("cgb"), Some(Model::Cgb));
+        assert_eq!(Model::from_name("gba"), None);
+    }
+
+    #[test]
+    fn test_post_boot_flags_depend_on_header_checksum() {
+        assert_eq!(Model::Dmg.post_boot_registers(0x00, false)[1], 0x80);
+        assert_eq!(Model::Dmg.post_boot_registers(0x42, false)[1], 0xB0);
+        assert_eq!(Model::Mgb.post_boot_registers(0x42, false)[0], 0xFF);
+    }
+
+    #[test]
+    fn test_cgb_post_boot_registers_depend_on_mode() {
+        assert_eq!(Model::Cgb.post_boot_registers(0x00, true)[4], 0xFF);
+        assert_eq!(Model::Cgb.post_boot_registers(0x00, false)[4], 0x00);
+    }
+}
@@ -0,0 +1,177 @@
+use super::{Ppu, BG_ENABLE, DMG_SHADES, SPRITE_ENABLE};
+
+const PALETTE_MEMORY_SIZE: usize = 0x40;
+const PALETTE_AUTO_INCREMENT: u8 = 0x80;
+
+//NOTE: In cgb mode the second vram bank holds one attribute byte for every entry of the tile maps,
+//      sprites use the same bits in their oam attributes.
+pub(super) const ATTRIBUTE_PRIORITY: u8 = 0x80;
+pub(super) const ATTRIBUTE_Y_FLIP: u8 = 0x40;
+pub(super) const ATTRIBUTE_X_FLIP: u8 = 0x20;
+pub(super) const ATTRIBUTE_DMG_PALETTE: u8 = 0x10;
+pub(super) const ATTRIBUTE_BANK: u8 = 0x08;
+pub(super) const ATTRIBUTE_CGB_PALETTE: u8 = 0x07;
+
+#[derive(Copy, Clone, Default, Debug)]
+pub(super) struct BgPixel {
+    pub color: u8,
+    pub attributes: u8,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub(super) struct SpritePixel {
+    pub color: u8,
+    pub attributes: u8,
+    //NOTE: Position of the sprite in the oam, in cgb mode the lower index wins overlapping pixels
+    pub index: usize,
+}
+
+//NOTE: Eight palettes with four colors each. Every color is a little endian 15 bit value with five
+//      bits for red, green and blue. The palette memory is only reachable through an index register
+//      that can move on by itself after every write to the data register.
+pub(super) struct ColorPalettes {
+    data: [u8; PALETTE_MEMORY_SIZE],
+    index: u8,
+    auto_increment: bool,
+}
+
+impl Default for ColorPalettes {
+    //NOTE: The cgb boot rom leaves every color white
+    fn default() -> Self {
+        Self {
+            data: [0xFF; PALETTE_MEMORY_SIZE],
+            index: 0x00,
+            auto_increment: false,
+        }
+    }
+}
+
+impl ColorPalettes {
+    pub fn read_index(&self) -> u8 {
+        let auto_increment = if self.auto_increment {
+            PALETTE_AUTO_INCREMENT
+        } else {
+            0x00
+        };
+
+        0x40 | auto_increment | self.index
+    }
+
+    pub fn write_index(&mut self, value: u8) {
+        self.index = value & 0x3F;
+        self.auto_increment = value & PALETTE_AUTO_INCREMENT != 0;
+    }
+
+    pub fn read_data(&self) -> u8 {
+        self.data[self.index as usize]
+    }
+
+    pub fn write_data(&mut self, value: u8) {
+        self.data[self.index as usize] = value;
+
+        if self.auto_increment {
+            self.index = (self.index + 1) & 0x3F;
+        }
+    }
+
+    pub fn rgb(&self, palette: u8, color: u8) -> [u8; 3] {
+        let offset = palette as usize * 8 + color as usize * 2;
+        let value = u16::from_le_bytes([self.data[offset], self.data[offset + 1]]);
+
+        //NOTE: Scales the five bit channels up to eight bits so 0x1F becomes 0xFF
+        let channel = |shift: u16| {
+            let channel = ((value >> shift) & 0x1F) as u8;
+            channel << 3 | channel >> 2
+        };
+
+        [channel(0), channel(5), channel(10)]
+    }
+}
+
+impl Ppu {
+    //NOTE: Both renderers end up here once they know the background and the sprite pixel at a
+    //      position. On the dmg the bg enable bit hides the background, on the cgb it takes away
+    //      the priority of the background over the sprites instead.
+    pub(super) fn mix_pixel(&self, background: BgPixel, sprite: Option<SpritePixel>) -> [u8; 3] {
+        let bg_visible = self.cgb_mode || self.lcdc & BG_ENABLE != 0;
+        let background = if bg_visible {
+            background
+        } else {
+            BgPixel::default()
+        };
+
+        if let Some(sprite) = sprite {
+            let bg_priority = if self.cgb_mode {
+                self.lcdc & BG_ENABLE != 0
+                    && (sprite.attributes | background.attributes) & ATTRIBUTE_PRIORITY != 0
+            } else {
+                sprite.attributes & ATTRIBUTE_PRIORITY != 0
+            };
+
+            let hidden = bg_priority && background.color != 0x00;
+
+            if sprite.color != 0x00 && self.lcdc & SPRITE_ENABLE != 0 && !hidden {
+                return self.sprite_rgb(sprite);
+            }
+        }
+
+        if !bg_visible {
+            DMG_SHADES[0]
+        } else if self.cgb_mode {
+            self.bg_palettes.rgb(
+                background.attributes & ATTRIBUTE_CGB_PALETTE,
+                background.color,
+            )
+        } else {
+            DMG_SHADES[Self::palette_shade(self.bgp, background.color) as usize]
+        }
+    }
+
+    fn sprite_rgb(&self, sprite: SpritePixel) -> [u8; 3] {
+        if self.cgb_mode {
+            return self
+                .obj_palettes
+                .rgb(sprite.attributes & ATTRIBUTE_CGB_PALETTE, sprite.color);
+        }
+
+        let palette = if sprite.attributes & ATTRIBUTE_DMG_PALETTE != 0 {
+            self.obp1
+        } else {
+            self.obp0
+        };
+
+        DMG_SHADES[Self::palette_shade(palette, sprite.color) as usize]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_palette_index_auto_increment() {
+        let mut palettes = ColorPalettes::default();
+        palettes.write_index(PALETTE_AUTO_INCREMENT | 0x3E);
+        assert_eq!(palettes.read_index(), 0xFE);
+
+        palettes.write_data(0x1F);
+        palettes.write_data(0x00);
+        palettes.write_data(0xE0);
+        palettes.write_data(0x03);
+        assert_eq!(palettes.read_index(), 0xC2);
+
+        assert_eq!(palettes.rgb(7, 3), [0xFF, 0x00, 0x00]);
+        assert_eq!(palettes.rgb(0, 0), [0x00, 0xFF, 0x00]);
+
+        //NOTE: Without the auto increment the index stays where it is
+        palettes.write_index(0x01);
+        palettes.write_data(0x7C);
+        palettes.write_data(0x7C);
+        assert_eq!(palettes.read_index(), 0x41);
+        assert_eq!(palettes.read_data(), 0x7C);
+
+        palettes.write_index(0x00);
+        palettes.write_data(0x00);
+        assert_eq!(palettes.rgb(0, 0), [0x00, 0x00, 0xFF]);
+    }
+}
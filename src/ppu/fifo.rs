@@ -0,0 +1,586 @@
+use std::collections::VecDeque;
+
+use super::color::{BgPixel, SpritePixel, ATTRIBUTE_X_FLIP, ATTRIBUTE_Y_FLIP};
+use super::scanline::Sprite;
+use super::{
+    Ppu, BG_ENABLE, BG_TILE_MAP, SCREEN_WIDTH, SPRITE_ENABLE, SPRITE_SIZE, WINDOW_ENABLE,
+    WINDOW_TILE_MAP,
+};
+
+//NOTE: The first tile of every line gets fetched twice, the first fetch is thrown away
+const STARTUP_DOTS: u8 = 6;
+const SPRITE_FETCH_DOTS: u8 = 6;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum FetcherStep {
+    Tile,
+    DataLow,
+    DataHigh,
+    Push,
+}
+
+//NOTE: State of the background fetcher and the two pixel fifos for the line that is being drawn
+pub(super) struct PixelFifo {
+    bg: VecDeque<BgPixel>,
+    sprites: VecDeque<SpritePixel>,
+
+    step: FetcherStep,
+    step_dots: u8,
+    fetcher_x: u8,
+    tile_index: u8,
+    tile_attributes: u8,
+    tile_low: u8,
+    tile_high: u8,
+
+    lcd_x: usize,
+    //NOTE: The lower three bits of SCX are handled by throwing away the first pixels of the line
+    discard: u8,
+    startup_dots: u8,
+    in_window: bool,
+
+    line_sprites: Vec<Sprite>,
+    sprite_fetch: Option<(Sprite, u8)>,
+    last_sprite_tile: Option<usize>,
+}
+
+impl Default for PixelFifo {
+    fn default() -> Self {
+        Self {
+            bg: VecDeque::with_capacity(16),
+            sprites: VecDeque::with_capacity(8),
+
+            step: FetcherStep::Tile,
+            step_dots: 0,
+            fetcher_x: 0,
+            tile_index: 0,
+            tile_attributes: 0,
+            tile_low: 0,
+            tile_high: 0,
+
+            lcd_x: 0,
+            discard: 0,
+            startup_dots: 0,
+            in_window: false,
+
+            line_sprites: Vec::new(),
+            sprite_fetch: None,
+            last_sprite_tile: None,
+        }
+    }
+}
+
+//NOTE: Pushes one pixel per dot to the lcd just like the hardware does. Every register gets read
+//      at the moment the hardware would read it, which makes changes in the middle of a line
+//      visible and stretches the drawing mode for SCX, the window and sprites.
+impl Ppu {
+    pub(super) fn start_fifo_line(&mut self) {
+        let height = if self.lcdc & SPRITE_SIZE != 0 { 16 } else { 8 };
+
+        self.fifo = PixelFifo {
+            discard: self.scx % 8,
+            startup_dots: STARTUP_DOTS,
+            line_sprites: self.scan_oam(height),
+            ..Default::default()
+        };
+    }
+
+    //NOTE: Returns true once the last pixel of the line went out
+    pub(super) fn fifo_tick(&mut self) -> bool {
+        if self.fifo.startup_dots > 0 {
+            self.fifo.startup_dots -= 1;
+            return false;
+        }
+
+        //NOTE: The background fetcher and the pixel output stay paused while a sprite is fetched
+        if let Some((sprite, dots)) = self.fifo.sprite_fetch {
+            if dots > 1 {
+                self.fifo.sprite_fetch = Some((sprite, dots - 1));
+            } else {
+                self.fifo.sprite_fetch = None;
+                self.merge_sprite(sprite);
+            }
+
+            return false;
+        }
+
+        if self.window_starts() {
+            self.fifo.in_window = true;
+            self.fifo.bg.clear();
+            self.fifo.step = FetcherStep::Tile;
+            self.fifo.step_dots = 0;
+            self.fifo.fetcher_x = 0;
+        }
+
+        self.advance_fetcher();
+
+        if self.fifo.bg.is_empty() || self.start_sprite_fetch() {
+            return false;
+        }
+
+        let Some(background) = self.fifo.bg.pop_front() else {
+            return false;
+        };
+        let sprite = self.fifo.sprites.pop_front();
+
+        if self.fifo.discard > 0 {
+            self.fifo.discard -= 1;
+            return false;
+        }
+
+        let color = self.mix_pixel(background, sprite);
+        let x = self.fifo.lcd_x;
+        self.set_pixel(x, color);
+        self.fifo.lcd_x += 1;
+
+        if self.fifo.lcd_x < SCREEN_WIDTH {
+            return false;
+        }
+
+        if self.fifo.in_window {
+            self.window_line += 1;
+        }
+
+        true
+    }
+
+    //NOTE: A sprite costs six dots for its own fetch. The first sprite on a background tile also
+    //      has to wait until the background fetcher is done with that tile, that's up to five dots
+    //      more depending on which pixel of the tile the sprite starts on.
+    fn start_sprite_fetch(&mut self) -> bool {
+        if self.lcdc & SPRITE_ENABLE == 0 || self.fifo.discard > 0 {
+            return false;
+        }
+
+        let lcd_x = self.fifo.lcd_x;
+        let Some(index) = self
+            .fifo
+            .line_sprites
+            .iter()
+            .position(|sprite| sprite.x as usize <= lcd_x + 8)
+        else {
+            return false;
+        };
+
+        let pixel = lcd_x + (self.scx % 8) as usize;
+        let tile = pixel / 8;
+        let wait = if self.fifo.last_sprite_tile == Some(tile) {
+            0
+        } else {
+            5u8.saturating_sub((pixel % 8) as u8)
+        };
+
+        let sprite = self.fifo.line_sprites.remove(index);
+        //NOTE: The dot that starts the fetch already counts towards it
+        self.fifo.sprite_fetch = Some((sprite, SPRITE_FETCH_DOTS + wait - 1));
+        self.fifo.last_sprite_tile = Some(tile);
+
+        true
+    }
+
+    fn window_starts(&self) -> bool {
+        !self.fifo.in_window
+            && self.fifo.discard == 0
+            && self.lcdc & WINDOW_ENABLE != 0
+            && (self.cgb_mode || self.lcdc & BG_ENABLE != 0)
+            && self.window_triggered
+            && self.fifo.lcd_x + 7 >= self.wx as usize
+    }
+
+    //NOTE: Every step takes two dots, the push gets retried until the background fifo is empty
+    fn advance_fetcher(&mut self) {
+        if self.fifo.step == FetcherStep::Push {
+            if self.fifo.bg.is_empty() {
+                self.push_tile();
+            }
+
+            return;
+        }
+
+        self.fifo.step_dots += 1;
+        if self.fifo.step_dots < 2 {
+            return;
+        }
+
+        self.fifo.step_dots = 0;
+        self.fifo.step = match self.fifo.step {
+            FetcherStep::Tile => {
+                let entry = self.tile_map_entry();
+                self.fifo.tile_index = self.vram[entry];
+                self.fifo.tile_attributes = self.tile_attributes(entry);
+                FetcherStep::DataLow
+            }
+            FetcherStep::DataLow => {
+                self.fifo.tile_low = self.vram[self.tile_row_addr()];
+                FetcherStep::DataHigh
+            }
+            FetcherStep::DataHigh => {
+                self.fifo.tile_high = self.vram[self.tile_row_addr() + 1];
+                FetcherStep::Push
+            }
+            FetcherStep::Push => FetcherStep::Push,
+        };
+    }
+
+    fn push_tile(&mut self) {
+        let attributes = self.fifo.tile_attributes;
+
+        for column in 0..8 {
+            let bit = if attributes & ATTRIBUTE_X_FLIP != 0 {
+                column
+            } else {
+                7 - column
+            };
+
+            let color =
+                ((self.fifo.tile_high >> bit) & 0x01) << 1 | ((self.fifo.tile_low >> bit) & 0x01);
+            self.fifo.bg.push_back(BgPixel { color, attributes });
+        }
+
+        self.fifo.fetcher_x = self.fifo.fetcher_x.wrapping_add(1);
+        self.fifo.step = FetcherStep::Tile;
+    }
+
+    fn tile_map_entry(&self) -> usize {
+        let (map, x, y) = if self.fifo.in_window {
+            (
+                Self::tile_map(self.lcdc & WINDOW_TILE_MAP != 0),
+                self.fifo.fetcher_x as usize,
+                self.window_line,
+            )
+        } else {
+            (
+                Self::tile_map(self.lcdc & BG_TILE_MAP != 0),
+                self.scx as usize / 8 + self.fifo.fetcher_x as usize,
+                self.ly.wrapping_add(self.scy),
+            )
+        };
+
+        map + (y as usize / 8) * 32 + (x & 0x1F)
+    }
+
+    fn tile_row_addr(&self) -> usize {
+        let row = if self.fifo.in_window {
+            self.window_line % 8
+        } else {
+            self.ly.wrapping_add(self.scy) % 8
+        };
+
+        let row = if self.fifo.tile_attributes & ATTRIBUTE_Y_FLIP != 0 {
+            7 - row
+        } else {
+            row
+        };
+
+        self.tile_data_addr(self.fifo.tile_index, self.fifo.tile_attributes) + row as usize * 2
+    }
+
+    //NOTE: Pixels left of the lcd get dropped. An opaque pixel that is already in the fifo belongs
+    //      to a sprite that was fetched first and keeps its place, unless the cgb finds a sprite
+    //      with a lower oam index.
+    fn merge_sprite(&mut self, sprite: Sprite) {
+        let height = if self.lcdc & SPRITE_SIZE != 0 { 16 } else { 8 };
+        let skipped = self.fifo.lcd_x + 8 - sprite.x as usize;
+
+        for column in skipped..8 {
+            let pixel = SpritePixel {
+                color: self.sprite_color(&sprite, column as u8, height),
+                attributes: sprite.attributes,
+                index: sprite.index,
+            };
+
+            match self.fifo.sprites.get_mut(column - skipped) {
+                Some(existing) if existing.color == 0x00 => *existing = pixel,
+                Some(existing)
+                    if self.cgb_mode && pixel.color != 0x00 && pixel.index < existing.index =>
+                {
+                    *existing = pixel
+                }
+                Some(_) => (),
+                None => self.fifo.sprites.push_back(pixel),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cpu::Cpu;
+    use crate::memory::bus::IoDevice;
+    use crate::memory::Memory;
+    use crate::model::Model;
+    use crate::ppu::{
+        Renderer, BCPD_REGISTER, BCPS_REGISTER, BGP_REGISTER, DMG_SHADES, LCDC_REGISTER,
+        LCD_ENABLE, LY_REGISTER, OAM_SCAN_DOTS, OCPD_REGISTER, OCPS_REGISTER, SCX_REGISTER,
+        STAT_REGISTER, TILE_DATA_SELECT, VRAM_SIZE,
+    };
+
+    const DMG_ACID2_ROM: &str = "test_roms/dmg-acid2/dmg-acid2.gb";
+    const DMG_ACID2_REFERENCE: &str = "test_roms/dmg-acid2/reference-dmg.png";
+    const MEALYBUG_ROMS: &str = "test_roms/mealybug-tearoom-tests/build/ppu";
+    const MEALYBUG_REFERENCES: &str = "test_roms/mealybug-tearoom-tests/expected/DMG-blob";
+
+    fn build_ppu(lcdc: u8) -> Ppu {
+        let mut ppu = Ppu::default();
+        ppu.set_renderer(Renderer::Fifo);
+        ppu.write(BGP_REGISTER, 0xE4);
+        ppu.write(LCDC_REGISTER, LCD_ENABLE | lcdc);
+
+        //NOTE: Tile 1 is a vertical stripe pattern using all four colors
+        for row in 0..8 {
+            ppu.vram[16 + row * 2] = 0x55;
+            ppu.vram[16 + row * 2 + 1] = 0x33;
+        }
+
+        ppu.vram[0x1800..0x1C00].fill(1);
+        ppu
+    }
+
+    //NOTE: Length of the drawing mode of the first line in dots
+    fn drawing_length(ppu: &mut Ppu) -> usize {
+        ppu.update(OAM_SCAN_DOTS);
+        let mut dots = 0;
+
+        while ppu.read(STAT_REGISTER) & 0x03 == 0x03 {
+            ppu.update(1);
+            dots += 1;
+        }
+
+        dots
+    }
+
+    fn shades(ppu: &Ppu) -> Vec<u8> {
+        ppu.framebuffer
+            .chunks_exact(3)
+            .map(|pixel| DMG_SHADES.iter().position(|shade| shade == pixel).unwrap() as u8)
+            .collect()
+    }
+
+    #[test]
+    fn test_drawing_mode_length() {
+        assert_eq!(drawing_length(&mut build_ppu(BG_ENABLE)), 172);
+
+        let mut ppu = build_ppu(BG_ENABLE);
+        ppu.write(SCX_REGISTER, 0x05);
+        assert_eq!(drawing_length(&mut ppu), 177);
+
+        let mut ppu = build_ppu(BG_ENABLE | WINDOW_ENABLE);
+        ppu.wx = 87;
+        ppu.window_triggered = true;
+        assert_eq!(drawing_length(&mut ppu), 178);
+
+        //NOTE: A sprite costs between 6 and 11 dots depending on where the fetcher is
+        let mut ppu = build_ppu(BG_ENABLE | SPRITE_ENABLE);
+        ppu.oam[0..4].copy_from_slice(&[16, 8, 0, 0]);
+        assert_eq!(drawing_length(&mut ppu), 183);
+
+        let mut ppu = build_ppu(BG_ENABLE | SPRITE_ENABLE);
+        ppu.oam[0..4].copy_from_slice(&[16, 14, 0, 0]);
+        assert_eq!(drawing_length(&mut ppu), 178);
+    }
+
+    #[test]
+    fn test_fifo_matches_the_scanline_renderer() {
+        let lcdc = BG_ENABLE | SPRITE_ENABLE | WINDOW_ENABLE | TILE_DATA_SELECT;
+
+        let mut scanline = build_ppu(lcdc);
+        scanline.set_renderer(Renderer::Scanline);
+        let mut fifo = build_ppu(lcdc);
+
+        for ppu in [&mut scanline, &mut fifo] {
+            ppu.scx = 3;
+            ppu.scy = 2;
+            ppu.wy = 40;
+            ppu.wx = 50;
+            ppu.obp0 = 0x1B;
+            ppu.vram[0x1800 + 5] = 0;
+            ppu.oam[0..4].copy_from_slice(&[20, 3, 1, 0x00]);
+            ppu.oam[4..8].copy_from_slice(&[24, 40, 1, 0x20]);
+            ppu.oam[8..12].copy_from_slice(&[24, 44, 1, 0x80]);
+            ppu.update(456 * 154);
+        }
+
+        assert_eq!(shades(&scanline), shades(&fifo));
+    }
+
+    #[test]
+    fn test_fifo_draws_the_window_and_tall_sprites_like_the_scanline_renderer() {
+        let lcdc = BG_ENABLE
+            | SPRITE_ENABLE
+            | SPRITE_SIZE
+            | WINDOW_ENABLE
+            | WINDOW_TILE_MAP
+            | TILE_DATA_SELECT;
+
+        let mut scanline = build_ppu(lcdc);
+        scanline.set_renderer(Renderer::Scanline);
+        let mut fifo = build_ppu(lcdc);
+
+        for ppu in [&mut scanline, &mut fifo] {
+            //NOTE: Tile 2 is solid color 3, tile 3 solid color 1. The window uses its own map.
+            ppu.vram[32..48].fill(0xFF);
+            for row in 0..8 {
+                ppu.vram[48 + row * 2] = 0xFF;
+                ppu.vram[48 + row * 2 + 1] = 0x00;
+            }
+            ppu.vram[0x1C00..0x2000].fill(2);
+
+            ppu.wy = 72;
+            ppu.wx = 87;
+            ppu.obp0 = 0xE4;
+            ppu.obp1 = 0x1B;
+
+            //NOTE: 8x16 sprites made of tile 2 and 3, the second one flipped and using OBP1
+            ppu.oam[0..4].copy_from_slice(&[24, 16, 2, 0x00]);
+            ppu.oam[4..8].copy_from_slice(&[24, 48, 3, 0x50]);
+            ppu.update(456 * 154);
+        }
+
+        let shades_scanline = shades(&scanline);
+        let shades_fifo = shades(&fifo);
+        let pixel = |x: usize, y: usize| shades_fifo[y * SCREEN_WIDTH + x];
+
+        assert_eq!(pixel(10, 10), 3);
+        assert_eq!(pixel(10, 20), 1);
+        assert_eq!(pixel(42, 10), 2);
+        assert_eq!(pixel(42, 20), 0);
+        assert_eq!(pixel(120, 100), 3);
+        assert_eq!(shades_scanline, shades_fifo);
+    }
+
+    #[test]
+    fn test_fifo_matches_the_scanline_renderer_in_cgb_mode() {
+        let lcdc = BG_ENABLE | SPRITE_ENABLE | WINDOW_ENABLE | TILE_DATA_SELECT;
+
+        let mut scanline = build_ppu(lcdc);
+        scanline.set_renderer(Renderer::Scanline);
+        let mut fifo = build_ppu(lcdc);
+
+        for ppu in [&mut scanline, &mut fifo] {
+            ppu.set_cgb_mode(true);
+            ppu.wy = 40;
+            ppu.wx = 50;
+
+            ppu.write(BCPS_REGISTER, 0x80);
+            ppu.write(OCPS_REGISTER, 0x80);
+            for value in 0..0x40u8 {
+                ppu.write(BCPD_REGISTER, value.wrapping_mul(5));
+                ppu.write(OCPD_REGISTER, value.wrapping_mul(3));
+            }
+
+            //NOTE: Tile 1 of the second bank is a diagonal line
+            for row in 0..8 {
+                ppu.vram[VRAM_SIZE + 16 + row * 2] = 0x80 >> row;
+                ppu.vram[VRAM_SIZE + 16 + row * 2 + 1] = 0x01 << row;
+            }
+
+            for (entry, attributes) in ppu.vram[VRAM_SIZE + 0x1800..VRAM_SIZE + 0x1C00]
+                .iter_mut()
+                .enumerate()
+            {
+                *attributes = (entry * 37) as u8;
+            }
+
+            ppu.oam[0..4].copy_from_slice(&[20, 3, 1, 0x0B]);
+            ppu.oam[4..8].copy_from_slice(&[24, 44, 1, 0x22]);
+            ppu.oam[8..12].copy_from_slice(&[24, 40, 1, 0x80]);
+            ppu.update(456 * 154);
+        }
+
+        assert!(scanline.framebuffer == fifo.framebuffer);
+    }
+
+    #[test]
+    fn test_palette_change_in_the_middle_of_a_line() {
+        let mut ppu = build_ppu(BG_ENABLE | TILE_DATA_SELECT);
+        ppu.vram[16..32].fill(0xFF);
+
+        ppu.update(OAM_SCAN_DOTS + 6 + 80);
+        ppu.write(BGP_REGISTER, 0x00);
+        ppu.update(456 - OAM_SCAN_DOTS - 6 - 80);
+        assert_eq!(ppu.read(LY_REGISTER), 1);
+
+        let shades = shades(&ppu);
+        assert_eq!(shades[0], 3);
+        assert_eq!(shades[SCREEN_WIDTH - 1], 0);
+        assert!(shades[70..90].contains(&3) && shades[70..90].contains(&0));
+    }
+
+    //NOTE: Runs the rom until it hits the `LD B, B` it uses as a breakpoint
+    fn run_until_breakpoint(rom: &[u8]) -> Option<Memory> {
+        let mut memory = Memory::default();
+        memory.load_cartridge(rom).ok()?;
+        memory.set_ppu_renderer(Renderer::Fifo);
+        memory.apply_post_boot_state(Model::Dmg);
+        let mut cpu = Cpu::post_boot(Model::Dmg, &memory);
+
+        while cpu.machine_cycles < 20_000_000 {
+            if memory.read(cpu.pc) == 0x40 {
+                return Some(memory);
+            }
+
+            cpu.cycle(&mut memory).ok()?;
+        }
+
+        None
+    }
+
+    //NOTE: The reference images use plain greys from white to black
+    fn load_reference(path: &str) -> Option<Vec<u8>> {
+        let decoder = png::Decoder::new(std::fs::File::open(path).ok()?);
+        let mut reader = decoder.read_info().ok()?;
+        let mut buffer = vec![0x00; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer).ok()?;
+        let channels = info.color_type.samples();
+
+        Some(
+            buffer[..info.buffer_size()]
+                .chunks_exact(channels)
+                .map(|pixel| 3 - pixel[0] / 0x55)
+                .collect(),
+        )
+    }
+
+    fn compare_with_reference(rom_path: &str, reference_path: &str) -> Option<bool> {
+        let rom = std::fs::read(rom_path).ok()?;
+        let reference = load_reference(reference_path)?;
+        let memory = run_until_breakpoint(&rom)?;
+
+        Some(shades(memory.get_ppu()) == reference)
+    }
+
+    #[test]
+    #[ignore = "needs dmg-acid2 and its reference image in test_roms/"]
+    fn test_dmg_acid2() {
+        assert_eq!(
+            compare_with_reference(DMG_ACID2_ROM, DMG_ACID2_REFERENCE),
+            Some(true)
+        );
+    }
+
+    #[test]
+    #[ignore = "needs the mealybug tearoom roms and reference images in test_roms/"]
+    fn test_mealybug_tearoom() {
+        let entries = std::fs::read_dir(MEALYBUG_ROMS).expect(MEALYBUG_ROMS);
+
+        let mut failed = Vec::new();
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Some(name) = path.file_stem().and_then(|name| name.to_str()) else {
+                continue;
+            };
+
+            let reference = format!("{}/{}.png", MEALYBUG_REFERENCES, name);
+            if !std::path::Path::new(&reference).exists() {
+                continue;
+            }
+
+            if compare_with_reference(path.to_str().unwrap(), &reference) != Some(true) {
+                failed.push(String::from(name));
+            }
+        }
+
+        assert!(failed.is_empty(), "Failed: {:?}", failed);
+    }
+}
@@ -0,0 +1,531 @@
+use std::ops::RangeInclusive;
+
+use crate::cpu::interrupt::Interrupt;
+use crate::memory::bus::IoDevice;
+
+use self::color::ColorPalettes;
+use self::fifo::PixelFifo;
+
+mod color;
+mod fifo;
+mod scanline;
+
+pub const SCREEN_WIDTH: usize = 160;
+pub const SCREEN_HEIGHT: usize = 144;
+
+pub const LCDC_REGISTER: u16 = 0xFF40;
+pub const STAT_REGISTER: u16 = 0xFF41;
+pub const SCY_REGISTER: u16 = 0xFF42;
+pub const SCX_REGISTER: u16 = 0xFF43;
+pub const LY_REGISTER: u16 = 0xFF44;
+pub const LYC_REGISTER: u16 = 0xFF45;
+pub const BGP_REGISTER: u16 = 0xFF47;
+pub const OBP0_REGISTER: u16 = 0xFF48;
+pub const OBP1_REGISTER: u16 = 0xFF49;
+pub const WY_REGISTER: u16 = 0xFF4A;
+pub const WX_REGISTER: u16 = 0xFF4B;
+pub const VBK_REGISTER: u16 = 0xFF4F;
+pub const BCPS_REGISTER: u16 = 0xFF68;
+pub const BCPD_REGISTER: u16 = 0xFF69;
+pub const OCPS_REGISTER: u16 = 0xFF6A;
+pub const OCPD_REGISTER: u16 = 0xFF6B;
+
+const VRAM_START: u16 = 0x8000;
+const OAM_START: u16 = 0xFE00;
+//NOTE: Size of one vram bank, the cgb has two of them
+const VRAM_SIZE: usize = 0x2000;
+const OAM_SIZE: usize = 0xA0;
+
+const DOTS_PER_LINE: usize = 456;
+const OAM_SCAN_DOTS: usize = 80;
+//NOTE: The drawing mode takes 172 dots at least, sprites and the window make it longer. Only the
+//      fifo renderer models those penalties, the scanline renderer always uses the shortest length.
+const DRAWING_DOTS: usize = 172;
+const VBLANK_START_LINE: u8 = 144;
+const LINES_PER_FRAME: u8 = 154;
+pub const DOTS_PER_FRAME: usize = DOTS_PER_LINE * LINES_PER_FRAME as usize;
+
+const LCD_ENABLE: u8 = 0x80;
+const WINDOW_TILE_MAP: u8 = 0x40;
+const WINDOW_ENABLE: u8 = 0x20;
+const TILE_DATA_SELECT: u8 = 0x10;
+const BG_TILE_MAP: u8 = 0x08;
+const SPRITE_SIZE: u8 = 0x04;
+const SPRITE_ENABLE: u8 = 0x02;
+const BG_ENABLE: u8 = 0x01;
+
+const STAT_LYC_INTERRUPT: u8 = 0x40;
+const STAT_OAM_INTERRUPT: u8 = 0x20;
+const STAT_VBLANK_INTERRUPT: u8 = 0x10;
+const STAT_HBLANK_INTERRUPT: u8 = 0x08;
+const STAT_COINCIDENCE: u8 = 0x04;
+const STAT_WRITABLE_BITS: u8 = 0x78;
+
+//NOTE: The four shades of the dmg from the lightest to the darkest one
+const DMG_SHADES: [[u8; 3]; 4] = [
+    [0xE0, 0xF8, 0xD0],
+    [0x88, 0xC0, 0x70],
+    [0x34, 0x68, 0x56],
+    [0x08, 0x18, 0x20],
+];
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Renderer {
+    //NOTE: Draws every line in one go, fast but blind to mid line register changes
+    Scanline,
+    //NOTE: Models the pixel fifo and the fetcher dot by dot
+    Fifo,
+}
+
+impl Renderer {
+    pub fn from_name(name: &str) -> Option<Renderer> {
+        match name.to_ascii_lowercase().as_str() {
+            "scanline" => Some(Renderer::Scanline),
+            "fifo" => Some(Renderer::Fifo),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum PpuMode {
+    HBlank,
+    VBlank,
+    OamScan,
+    Drawing,
+}
+
+impl PpuMode {
+    fn bits(self) -> u8 {
+        match self {
+            PpuMode::HBlank => 0,
+            PpuMode::VBlank => 1,
+            PpuMode::OamScan => 2,
+            PpuMode::Drawing => 3,
+        }
+    }
+}
+
+impl std::fmt::Display for PpuMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PpuMode::HBlank => write!(f, "HBLANK"),
+            PpuMode::VBlank => write!(f, "VBLANK"),
+            PpuMode::OamScan => write!(f, "OAM SCAN"),
+            PpuMode::Drawing => write!(f, "DRAWING"),
+        }
+    }
+}
+
+pub struct Ppu {
+    vram: [u8; VRAM_SIZE * 2],
+    vram_bank: usize,
+    oam: [u8; OAM_SIZE],
+
+    lcdc: u8,
+    stat: u8,
+    scy: u8,
+    scx: u8,
+    ly: u8,
+    lyc: u8,
+    bgp: u8,
+    obp0: u8,
+    obp1: u8,
+    wy: u8,
+    wx: u8,
+
+    cgb_mode: bool,
+    bg_palettes: ColorPalettes,
+    obj_palettes: ColorPalettes,
+
+    mode: PpuMode,
+    line_dots: usize,
+    //NOTE: The stat interrupt only fires when one of the conditions goes from low to high
+    stat_line: bool,
+    //NOTE: The window has its own line counter which only moves on lines it was actually drawn on
+    window_line: u8,
+    window_triggered: bool,
+    //NOTE: Set whenever a visible line enters the hblank, the hblank dma waits for it
+    hblank_started: bool,
+
+    renderer: Renderer,
+    fifo: PixelFifo,
+
+    framebuffer: Vec<u8>,
+    frame_count: usize,
+}
+
+impl Default for Ppu {
+    fn default() -> Self {
+        Self {
+            vram: [0x00; VRAM_SIZE * 2],
+            vram_bank: 0,
+            oam: [0x00; OAM_SIZE],
+
+            lcdc: 0x00,
+            stat: 0x00,
+            scy: 0x00,
+            scx: 0x00,
+            ly: 0x00,
+            lyc: 0x00,
+            bgp: 0x00,
+            obp0: 0x00,
+            obp1: 0x00,
+            wy: 0x00,
+            wx: 0x00,
+
+            cgb_mode: false,
+            bg_palettes: ColorPalettes::default(),
+            obj_palettes: ColorPalettes::default(),
+
+            mode: PpuMode::HBlank,
+            line_dots: 0,
+            stat_line: false,
+            window_line: 0,
+            window_triggered: false,
+            hblank_started: false,
+
+            renderer: Renderer::Scanline,
+            fifo: PixelFifo::default(),
+
+            framebuffer: DMG_SHADES[0].repeat(SCREEN_WIDTH * SCREEN_HEIGHT),
+            frame_count: 0,
+        }
+    }
+}
+
+impl Ppu {
+    //NOTE: Runs the ppu for the given amount of dots and returns the interrupts it requested as a
+    //      mask for the interrupt flag register.
+    pub fn update(&mut self, dots: usize) -> u8 {
+        if self.lcdc & LCD_ENABLE == 0 {
+            return 0x00;
+        }
+
+        let mut interrupts = 0x00;
+
+        for _ in 0..dots {
+            interrupts |= self.tick();
+        }
+
+        interrupts
+    }
+
+    fn tick(&mut self) -> u8 {
+        let mut interrupts = 0x00;
+        self.line_dots += 1;
+
+        match self.mode {
+            PpuMode::OamScan if self.line_dots == OAM_SCAN_DOTS => {
+                self.mode = PpuMode::Drawing;
+
+                if self.renderer == Renderer::Fifo {
+                    self.start_fifo_line();
+                }
+            }
+            PpuMode::Drawing if self.renderer == Renderer::Fifo => {
+                let line_done = self.fifo_tick();
+
+                if line_done {
+                    self.start_hblank();
+                }
+            }
+            PpuMode::Drawing if self.line_dots == OAM_SCAN_DOTS + DRAWING_DOTS => {
+                self.render_scanline();
+                self.start_hblank();
+            }
+            PpuMode::HBlank | PpuMode::VBlank if self.line_dots == DOTS_PER_LINE => {
+                self.line_dots = 0;
+                self.ly += 1;
+
+                if self.ly == VBLANK_START_LINE {
+                    self.mode = PpuMode::VBlank;
+                    self.frame_count += 1;
+                    interrupts |= Interrupt::VBlank.mask();
+                } else if self.ly == LINES_PER_FRAME {
+                    self.ly = 0;
+                    self.window_line = 0;
+                    self.window_triggered = false;
+                    self.start_line();
+                } else if self.ly < VBLANK_START_LINE {
+                    self.start_line();
+                }
+            }
+            _ => (),
+        }
+
+        if self.update_stat_line() {
+            interrupts |= Interrupt::LcdStat.mask();
+        }
+
+        interrupts
+    }
+
+    fn start_line(&mut self) {
+        self.mode = PpuMode::OamScan;
+
+        if self.ly == self.wy {
+            self.window_triggered = true;
+        }
+    }
+
+    fn start_hblank(&mut self) {
+        self.mode = PpuMode::HBlank;
+        self.hblank_started = true;
+    }
+
+    //NOTE: Returns true if the stat interrupt line just went high
+    fn update_stat_line(&mut self) -> bool {
+        let coincidence = self.ly == self.lyc;
+
+        let line = (coincidence && self.stat & STAT_LYC_INTERRUPT != 0)
+            || match self.mode {
+                PpuMode::HBlank => self.stat & STAT_HBLANK_INTERRUPT != 0,
+                PpuMode::VBlank => self.stat & STAT_VBLANK_INTERRUPT != 0,
+                PpuMode::OamScan => self.stat & STAT_OAM_INTERRUPT != 0,
+                PpuMode::Drawing => false,
+            };
+
+        let rising_edge = line && !self.stat_line;
+        self.stat_line = line;
+
+        rising_edge
+    }
+
+    pub fn read_vram(&self, addr: u16) -> u8 {
+        self.vram[self.vram_bank * VRAM_SIZE + (addr - VRAM_START) as usize]
+    }
+
+    pub fn write_vram(&mut self, addr: u16, value: u8) {
+        self.vram[self.vram_bank * VRAM_SIZE + (addr - VRAM_START) as usize] = value;
+    }
+
+    pub fn read_oam(&self, addr: u16) -> u8 {
+        self.oam[(addr - OAM_START) as usize]
+    }
+
+    pub fn write_oam(&mut self, addr: u16, value: u8) {
+        self.oam[(addr - OAM_START) as usize] = value;
+    }
+
+    //NOTE: Turning the lcd off resets it to the start of the frame, turning it on starts a new
+    //      frame right away.
+    fn write_lcdc(&mut self, value: u8) {
+        let was_enabled = self.lcdc & LCD_ENABLE != 0;
+        let enabled = value & LCD_ENABLE != 0;
+        self.lcdc = value;
+
+        if was_enabled && !enabled {
+            self.ly = 0;
+            self.line_dots = 0;
+            self.mode = PpuMode::HBlank;
+            self.stat_line = false;
+        } else if !was_enabled && enabled {
+            self.window_line = 0;
+            self.window_triggered = false;
+            self.start_line();
+        }
+    }
+
+    //NOTE: Turns on the second vram bank, the color palettes and the background attributes
+    pub fn set_cgb_mode(&mut self, cgb_mode: bool) {
+        self.cgb_mode = cgb_mode;
+
+        if !cgb_mode {
+            self.vram_bank = 0;
+        }
+    }
+
+    pub fn take_hblank_started(&mut self) -> bool {
+        std::mem::take(&mut self.hblank_started)
+    }
+
+    pub fn set_renderer(&mut self, renderer: Renderer) {
+        self.renderer = renderer;
+    }
+
+    pub fn get_mode(&self) -> PpuMode {
+        self.mode
+    }
+
+    //NOTE: Three bytes (red, green, blue) for every pixel, row by row
+    pub fn get_framebuffer(&self) -> &[u8] {
+        &self.framebuffer
+    }
+
+    pub fn get_frame_count(&self) -> usize {
+        self.frame_count
+    }
+}
+
+//NOTE: Besides its registers the ppu owns the vram and the oam
+impl IoDevice for Ppu {
+    fn address_ranges(&self) -> &'static [RangeInclusive<u16>] {
+        &[
+            VRAM_START..=0x9FFF,
+            OAM_START..=0xFE9F,
+            LCDC_REGISTER..=LYC_REGISTER,
+            BGP_REGISTER..=WX_REGISTER,
+            VBK_REGISTER..=VBK_REGISTER,
+            BCPS_REGISTER..=OCPD_REGISTER,
+        ]
+    }
+
+    fn read(&self, addr: u16) -> u8 {
+        match addr {
+            VRAM_START..=0x9FFF => self.read_vram(addr),
+            OAM_START..=0xFE9F => self.read_oam(addr),
+            LCDC_REGISTER => self.lcdc,
+            STAT_REGISTER => {
+                let coincidence = if self.ly == self.lyc {
+                    STAT_COINCIDENCE
+                } else {
+                    0x00
+                };
+
+                //NOTE: With the lcd turned off the mode bits read as zero
+                let mode = if self.lcdc & LCD_ENABLE != 0 {
+                    self.mode.bits()
+                } else {
+                    0x00
+                };
+
+                0x80 | (self.stat & STAT_WRITABLE_BITS) | coincidence | mode
+            }
+            SCY_REGISTER => self.scy,
+            SCX_REGISTER => self.scx,
+            LY_REGISTER => self.ly,
+            LYC_REGISTER => self.lyc,
+            BGP_REGISTER => self.bgp,
+            OBP0_REGISTER => self.obp0,
+            OBP1_REGISTER => self.obp1,
+            WY_REGISTER => self.wy,
+            WX_REGISTER => self.wx,
+            //NOTE: The cgb registers read as 0xFF on the dmg and in dmg mode
+            VBK_REGISTER..=OCPD_REGISTER if !self.cgb_mode => 0xFF,
+            VBK_REGISTER => 0xFE | self.vram_bank as u8,
+            BCPS_REGISTER => self.bg_palettes.read_index(),
+            BCPD_REGISTER => self.bg_palettes.read_data(),
+            OCPS_REGISTER => self.obj_palettes.read_index(),
+            OCPD_REGISTER => self.obj_palettes.read_data(),
+            _ => panic!("I/O Address out of ppu range !"),
+        }
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        match addr {
+            VRAM_START..=0x9FFF => self.write_vram(addr, value),
+            OAM_START..=0xFE9F => self.write_oam(addr, value),
+            LCDC_REGISTER => self.write_lcdc(value),
+            STAT_REGISTER => self.stat = value & STAT_WRITABLE_BITS,
+            SCY_REGISTER => self.scy = value,
+            SCX_REGISTER => self.scx = value,
+            //NOTE: LY is read only
+            LY_REGISTER => (),
+            LYC_REGISTER => self.lyc = value,
+            BGP_REGISTER => self.bgp = value,
+            OBP0_REGISTER => self.obp0 = value,
+            OBP1_REGISTER => self.obp1 = value,
+            WY_REGISTER => self.wy = value,
+            WX_REGISTER => self.wx = value,
+            VBK_REGISTER..=OCPD_REGISTER if !self.cgb_mode => (),
+            VBK_REGISTER => self.vram_bank = (value & 0x01) as usize,
+            BCPS_REGISTER => self.bg_palettes.write_index(value),
+            BCPD_REGISTER => self.bg_palettes.write_data(value),
+            OCPS_REGISTER => self.obj_palettes.write_index(value),
+            OCPD_REGISTER => self.obj_palettes.write_data(value),
+            _ => panic!("Invalid address for the ppu registers"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn enabled_ppu() -> Ppu {
+        let mut ppu = Ppu::default();
+        ppu.write(LCDC_REGISTER, LCD_ENABLE | BG_ENABLE);
+        ppu
+    }
+
+    #[test]
+    fn test_mode_timing_of_a_line() {
+        let mut ppu = enabled_ppu();
+        assert_eq!(ppu.get_mode(), PpuMode::OamScan);
+
+        ppu.update(OAM_SCAN_DOTS - 1);
+        assert_eq!(ppu.get_mode(), PpuMode::OamScan);
+        ppu.update(1);
+        assert_eq!(ppu.get_mode(), PpuMode::Drawing);
+
+        ppu.update(DRAWING_DOTS);
+        assert_eq!(ppu.get_mode(), PpuMode::HBlank);
+        assert_eq!(ppu.read(STAT_REGISTER) & 0x03, 0x00);
+
+        ppu.update(DOTS_PER_LINE - OAM_SCAN_DOTS - DRAWING_DOTS);
+        assert_eq!(ppu.read(LY_REGISTER), 1);
+        assert_eq!(ppu.get_mode(), PpuMode::OamScan);
+        assert_eq!(ppu.read(STAT_REGISTER) & 0x03, 0x02);
+    }
+
+    #[test]
+    fn test_vblank_interrupt_once_per_frame() {
+        let mut ppu = enabled_ppu();
+
+        let interrupts = ppu.update(DOTS_PER_LINE * 144 - 1);
+        assert_eq!(interrupts & Interrupt::VBlank.mask(), 0x00);
+
+        let interrupts = ppu.update(1);
+        assert_eq!(interrupts, Interrupt::VBlank.mask());
+        assert_eq!(ppu.get_mode(), PpuMode::VBlank);
+        assert_eq!(ppu.read(LY_REGISTER), 144);
+        assert_eq!(ppu.get_frame_count(), 1);
+
+        ppu.update(DOTS_PER_LINE * 10);
+        assert_eq!(ppu.read(LY_REGISTER), 0);
+        assert_eq!(ppu.get_mode(), PpuMode::OamScan);
+    }
+
+    #[test]
+    fn test_lyc_coincidence_interrupt() {
+        let mut ppu = enabled_ppu();
+        ppu.write(LYC_REGISTER, 2);
+        ppu.write(STAT_REGISTER, STAT_LYC_INTERRUPT);
+
+        let interrupts = ppu.update(DOTS_PER_LINE * 2 - 1);
+        assert_eq!(interrupts, 0x00);
+        assert_eq!(ppu.read(STAT_REGISTER) & STAT_COINCIDENCE, 0x00);
+
+        let interrupts = ppu.update(1);
+        assert_eq!(interrupts, Interrupt::LcdStat.mask());
+        assert_eq!(ppu.read(STAT_REGISTER) & STAT_COINCIDENCE, STAT_COINCIDENCE);
+    }
+
+    #[test]
+    fn test_stat_interrupt_needs_a_rising_edge() {
+        let mut ppu = enabled_ppu();
+        ppu.write(STAT_REGISTER, STAT_HBLANK_INTERRUPT | STAT_OAM_INTERRUPT);
+
+        //NOTE: HBlank directly followed by the oam scan keeps the line high, no second interrupt
+        let interrupts = ppu.update(DOTS_PER_LINE - OAM_SCAN_DOTS);
+        assert_eq!(interrupts, Interrupt::LcdStat.mask());
+
+        let interrupts = ppu.update(OAM_SCAN_DOTS + OAM_SCAN_DOTS);
+        assert_eq!(interrupts, 0x00);
+    }
+
+    #[test]
+    fn test_lcd_off_resets_ly() {
+        let mut ppu = enabled_ppu();
+        ppu.update(DOTS_PER_LINE * 10);
+        assert_eq!(ppu.read(LY_REGISTER), 10);
+
+        ppu.write(LCDC_REGISTER, 0x00);
+        assert_eq!(ppu.read(LY_REGISTER), 0);
+        assert_eq!(ppu.read(STAT_REGISTER) & 0x03, 0x00);
+
+        ppu.update(DOTS_PER_LINE * 10);
+        assert_eq!(ppu.read(LY_REGISTER), 0);
+    }
+}
@@ -0,0 +1,381 @@
+use super::color::{BgPixel, SpritePixel, ATTRIBUTE_BANK, ATTRIBUTE_X_FLIP, ATTRIBUTE_Y_FLIP};
+use super::{
+    Ppu, BG_ENABLE, BG_TILE_MAP, SCREEN_WIDTH, SPRITE_ENABLE, SPRITE_SIZE, TILE_DATA_SELECT,
+    VRAM_SIZE, WINDOW_ENABLE, WINDOW_TILE_MAP,
+};
+
+const TILE_MAP_LOW: usize = 0x1800;
+const TILE_MAP_HIGH: usize = 0x1C00;
+const SPRITES_PER_LINE: usize = 10;
+
+#[derive(Copy, Clone, Debug)]
+pub(super) struct Sprite {
+    pub index: usize,
+    pub y: u8,
+    pub x: u8,
+    pub tile: u8,
+    pub attributes: u8,
+}
+
+//NOTE: Draws the whole line at once at the end of the drawing mode. Good enough for most games, mid
+//      line changes to the registers are not visible though.
+impl Ppu {
+    pub(super) fn render_scanline(&mut self) {
+        let mut background = [BgPixel::default(); SCREEN_WIDTH];
+        let mut sprites = [None; SCREEN_WIDTH];
+
+        //NOTE: On the dmg the bg enable bit turns off the background and the window
+        if self.cgb_mode || self.lcdc & BG_ENABLE != 0 {
+            self.render_background(&mut background);
+        }
+
+        if self.lcdc & SPRITE_ENABLE != 0 {
+            self.render_sprites(&mut sprites);
+        }
+
+        for x in 0..SCREEN_WIDTH {
+            let color = self.mix_pixel(background[x], sprites[x]);
+            self.set_pixel(x, color);
+        }
+    }
+
+    fn render_background(&mut self, background: &mut [BgPixel; SCREEN_WIDTH]) {
+        let window_x = self.wx as i16 - 7;
+        let window_visible = self.lcdc & WINDOW_ENABLE != 0
+            && self.window_triggered
+            && window_x < SCREEN_WIDTH as i16;
+
+        let bg_map = Self::tile_map(self.lcdc & BG_TILE_MAP != 0);
+        let window_map = Self::tile_map(self.lcdc & WINDOW_TILE_MAP != 0);
+
+        for (x, pixel) in background.iter_mut().enumerate() {
+            *pixel = if window_visible && x as i16 >= window_x {
+                let window_pixel = (x as i16 - window_x) as u8;
+                self.tile_map_pixel(window_map, window_pixel, self.window_line)
+            } else {
+                let bg_x = (x as u8).wrapping_add(self.scx);
+                let bg_y = self.ly.wrapping_add(self.scy);
+                self.tile_map_pixel(bg_map, bg_x, bg_y)
+            };
+        }
+
+        if window_visible {
+            self.window_line += 1;
+        }
+    }
+
+    fn render_sprites(&mut self, pixels: &mut [Option<SpritePixel>; SCREEN_WIDTH]) {
+        let height = if self.lcdc & SPRITE_SIZE != 0 { 16 } else { 8 };
+        let mut sprites = self.scan_oam(height);
+
+        //NOTE: On the dmg the sprite with the smaller x coordinate wins, the oam order breaks ties.
+        //      The sort is stable so the scan order is kept for equal coordinates. The cgb only
+        //      looks at the oam order.
+        if !self.cgb_mode {
+            sprites.sort_by_key(|sprite| sprite.x);
+        }
+
+        for (x, pixel) in pixels.iter_mut().enumerate() {
+            for sprite in sprites.iter() {
+                let sprite_x = sprite.x as i16 - 8;
+                let column = x as i16 - sprite_x;
+
+                if !(0..8).contains(&column) {
+                    continue;
+                }
+
+                let color = self.sprite_color(sprite, column as u8, height);
+                if color == 0x00 {
+                    continue;
+                }
+
+                //NOTE: The first visible sprite owns the pixel even if the background hides it
+                *pixel = Some(SpritePixel {
+                    color,
+                    attributes: sprite.attributes,
+                    index: sprite.index,
+                });
+
+                break;
+            }
+        }
+    }
+
+    //NOTE: The first ten sprites in oam order that cover the current line, the x coordinate
+    //      doesn't matter for the selection.
+    pub(super) fn scan_oam(&self, height: u8) -> Vec<Sprite> {
+        self.oam
+            .chunks_exact(4)
+            .enumerate()
+            .map(|(index, entry)| Sprite {
+                index,
+                y: entry[0],
+                x: entry[1],
+                tile: entry[2],
+                attributes: entry[3],
+            })
+            .filter(|sprite| {
+                let line = self.ly as i16 + 16 - sprite.y as i16;
+                (0..height as i16).contains(&line)
+            })
+            .take(SPRITES_PER_LINE)
+            .collect()
+    }
+
+    pub(super) fn sprite_color(&self, sprite: &Sprite, column: u8, height: u8) -> u8 {
+        let mut row = self.ly + 16 - sprite.y;
+        let mut column = column;
+
+        if sprite.attributes & ATTRIBUTE_Y_FLIP != 0 {
+            row = height - 1 - row;
+        }
+
+        if sprite.attributes & ATTRIBUTE_X_FLIP != 0 {
+            column = 7 - column;
+        }
+
+        //NOTE: Tall sprites ignore the lowest bit of the tile index
+        let tile = if height == 16 {
+            sprite.tile & 0xFE
+        } else {
+            sprite.tile
+        };
+
+        self.tile_color(
+            self.tile_bank(sprite.attributes) + tile as usize * 16 + (row as usize / 8) * 16,
+            column,
+            row % 8,
+        )
+    }
+
+    pub(super) fn tile_map(high: bool) -> usize {
+        if high {
+            TILE_MAP_HIGH
+        } else {
+            TILE_MAP_LOW
+        }
+    }
+
+    fn tile_map_pixel(&self, map: usize, x: u8, y: u8) -> BgPixel {
+        let entry = map + (y as usize / 8) * 32 + x as usize / 8;
+        let attributes = self.tile_attributes(entry);
+
+        let x = if attributes & ATTRIBUTE_X_FLIP != 0 {
+            7 - x % 8
+        } else {
+            x % 8
+        };
+        let y = if attributes & ATTRIBUTE_Y_FLIP != 0 {
+            7 - y % 8
+        } else {
+            y % 8
+        };
+
+        BgPixel {
+            color: self.tile_color(self.tile_data_addr(self.vram[entry], attributes), x, y),
+            attributes,
+        }
+    }
+
+    //NOTE: The attributes of a tile map entry sit at the same offset in the second vram bank
+    pub(super) fn tile_attributes(&self, entry: usize) -> u8 {
+        if self.cgb_mode {
+            self.vram[VRAM_SIZE + entry]
+        } else {
+            0x00
+        }
+    }
+
+    pub(super) fn tile_data_addr(&self, tile_index: u8, attributes: u8) -> usize {
+        //NOTE: The 0x8800 addressing mode uses signed tile indices relative to 0x9000
+        let tile_addr = if self.lcdc & TILE_DATA_SELECT != 0 {
+            tile_index as usize * 16
+        } else {
+            (0x1000 + (tile_index as i8 as i32) * 16) as usize
+        };
+
+        self.tile_bank(attributes) + tile_addr
+    }
+
+    fn tile_bank(&self, attributes: u8) -> usize {
+        if self.cgb_mode && attributes & ATTRIBUTE_BANK != 0 {
+            VRAM_SIZE
+        } else {
+            0
+        }
+    }
+
+    //NOTE: Every row of a tile is two bytes, the first one holds the low bits of all eight pixels
+    //      and the second one the high bits. The leftmost pixel is bit 7.
+    fn tile_color(&self, tile_addr: usize, x: u8, y: u8) -> u8 {
+        let low = self.vram[tile_addr + y as usize * 2];
+        let high = self.vram[tile_addr + y as usize * 2 + 1];
+        let bit = 7 - x;
+
+        ((high >> bit) & 0x01) << 1 | ((low >> bit) & 0x01)
+    }
+
+    pub(super) fn palette_shade(palette: u8, color: u8) -> u8 {
+        (palette >> (color * 2)) & 0x03
+    }
+
+    pub(super) fn set_pixel(&mut self, x: usize, color: [u8; 3]) {
+        let offset = (self.ly as usize * SCREEN_WIDTH + x) * 3;
+        self.framebuffer[offset..offset + 3].copy_from_slice(&color);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ppu::color::{ATTRIBUTE_DMG_PALETTE, ATTRIBUTE_PRIORITY};
+    use crate::ppu::{DMG_SHADES, LCD_ENABLE};
+
+    //NOTE: Identity palette, color 0 is the lightest and color 3 the darkest shade
+    const PALETTE: u8 = 0xE4;
+
+    fn shade_at(ppu: &Ppu, x: usize, y: usize) -> u8 {
+        let offset = (y * SCREEN_WIDTH + x) * 3;
+        let pixel = &ppu.framebuffer[offset..offset + 3];
+
+        DMG_SHADES.iter().position(|shade| shade == pixel).unwrap() as u8
+    }
+
+    //NOTE: A tile where every pixel has the given color
+    fn fill_tile(ppu: &mut Ppu, tile_addr: usize, color: u8) {
+        for row in 0..8 {
+            ppu.vram[tile_addr + row * 2] = if color & 0x01 != 0 { 0xFF } else { 0x00 };
+            ppu.vram[tile_addr + row * 2 + 1] = if color & 0x02 != 0 { 0xFF } else { 0x00 };
+        }
+    }
+
+    fn build_ppu(lcdc: u8) -> Ppu {
+        let mut ppu = Ppu {
+            lcdc: LCD_ENABLE | lcdc,
+            bgp: PALETTE,
+            obp0: PALETTE,
+            obp1: 0x1B,
+            ..Default::default()
+        };
+
+        fill_tile(&mut ppu, 16, 1);
+        fill_tile(&mut ppu, 32, 2);
+        fill_tile(&mut ppu, 48, 3);
+        ppu
+    }
+
+    #[test]
+    fn test_background_scrolling() {
+        let mut ppu = build_ppu(BG_ENABLE | TILE_DATA_SELECT);
+        ppu.vram[TILE_MAP_LOW + 1] = 1;
+        ppu.vram[TILE_MAP_LOW + 32] = 2;
+
+        ppu.render_scanline();
+        assert_eq!(shade_at(&ppu, 7, 0), 0);
+        assert_eq!(shade_at(&ppu, 8, 0), 1);
+
+        ppu.scx = 4;
+        ppu.render_scanline();
+        assert_eq!(shade_at(&ppu, 3, 0), 0);
+        assert_eq!(shade_at(&ppu, 4, 0), 1);
+
+        ppu.scx = 0;
+        ppu.scy = 8;
+        ppu.render_scanline();
+        assert_eq!(shade_at(&ppu, 0, 0), 2);
+    }
+
+    #[test]
+    fn test_signed_tile_addressing() {
+        let mut ppu = build_ppu(BG_ENABLE);
+        fill_tile(&mut ppu, 0x1000 - 16, 3);
+        ppu.vram[TILE_MAP_LOW] = 0xFF;
+
+        ppu.render_scanline();
+        assert_eq!(shade_at(&ppu, 0, 0), 3);
+    }
+
+    #[test]
+    fn test_window_uses_its_own_line_counter() {
+        let mut ppu = build_ppu(BG_ENABLE | TILE_DATA_SELECT | WINDOW_ENABLE | WINDOW_TILE_MAP);
+        ppu.vram[TILE_MAP_HIGH..TILE_MAP_HIGH + 32].fill(3);
+        ppu.vram[TILE_MAP_HIGH + 32..TILE_MAP_HIGH + 64].fill(2);
+        ppu.wx = 7 + 80;
+        ppu.window_triggered = true;
+
+        for line in 0..8 {
+            ppu.ly = line;
+            ppu.render_scanline();
+        }
+
+        assert_eq!(shade_at(&ppu, 79, 0), 0);
+        assert_eq!(shade_at(&ppu, 80, 0), 3);
+        assert_eq!(ppu.window_line, 8);
+
+        ppu.ly = 100;
+        ppu.render_scanline();
+        assert_eq!(shade_at(&ppu, 80, 100), 2);
+    }
+
+    #[test]
+    fn test_sprite_priority_and_flipping() {
+        let mut ppu = build_ppu(BG_ENABLE | TILE_DATA_SELECT | SPRITE_ENABLE);
+
+        //NOTE: A tile with only the leftmost column set to color 3
+        ppu.vram[64..80].copy_from_slice(&[0x80; 16]);
+
+        ppu.oam[0..4].copy_from_slice(&[16, 12, 4, 0x00]);
+        ppu.oam[4..8].copy_from_slice(&[16, 10, 3, ATTRIBUTE_DMG_PALETTE]);
+        ppu.oam[8..12].copy_from_slice(&[16, 30, 4, ATTRIBUTE_X_FLIP]);
+
+        ppu.render_scanline();
+
+        //NOTE: The second sprite is further left so it wins the overlapping pixels
+        assert_eq!(shade_at(&ppu, 2, 0), 0);
+        assert_eq!(shade_at(&ppu, 4, 0), 0);
+        assert_eq!(shade_at(&ppu, 22, 0), 0);
+        assert_eq!(shade_at(&ppu, 29, 0), 3);
+    }
+
+    #[test]
+    fn test_cgb_background_attributes() {
+        let mut ppu = build_ppu(BG_ENABLE | TILE_DATA_SELECT);
+        ppu.set_cgb_mode(true);
+
+        //NOTE: Tile 1 of the second bank only has its leftmost column set to color 3
+        ppu.vram[VRAM_SIZE + 16..VRAM_SIZE + 32].fill(0x80);
+        ppu.vram[TILE_MAP_LOW] = 1;
+        ppu.vram[VRAM_SIZE + TILE_MAP_LOW] = ATTRIBUTE_BANK | ATTRIBUTE_X_FLIP | 0x02;
+
+        //NOTE: Color 3 of palette 2 is pure red, everything else stays white
+        ppu.bg_palettes.write_index(0x80 | (2 * 8 + 3 * 2));
+        ppu.bg_palettes.write_data(0x1F);
+        ppu.bg_palettes.write_data(0x00);
+
+        ppu.render_scanline();
+        assert_eq!(ppu.framebuffer[0..3], [0xFF, 0xFF, 0xFF]);
+        assert_eq!(ppu.framebuffer[7 * 3..8 * 3], [0xFF, 0x00, 0x00]);
+        assert_eq!(ppu.framebuffer[8 * 3..9 * 3], [0xFF, 0xFF, 0xFF]);
+    }
+
+    #[test]
+    fn test_background_priority_and_sprite_limit() {
+        let mut ppu = build_ppu(BG_ENABLE | TILE_DATA_SELECT | SPRITE_ENABLE);
+        ppu.vram[TILE_MAP_LOW] = 1;
+
+        ppu.oam[0..4].copy_from_slice(&[16, 8, 3, ATTRIBUTE_PRIORITY]);
+        ppu.oam[4..8].copy_from_slice(&[16, 16, 3, ATTRIBUTE_PRIORITY]);
+        for sprite in 2..12 {
+            ppu.oam[sprite * 4..sprite * 4 + 4].copy_from_slice(&[16, 16 + sprite as u8 * 8, 3, 0]);
+        }
+
+        ppu.render_scanline();
+
+        //NOTE: Hidden behind a non zero background color, visible over color zero
+        assert_eq!(shade_at(&ppu, 0, 0), 1);
+        assert_eq!(shade_at(&ppu, 8, 0), 3);
+        assert_eq!(shade_at(&ppu, 16 + 7 * 8, 0), 3);
+        //NOTE: Only ten sprites per line, the eleventh and twelfth are dropped
+        assert_eq!(shade_at(&ppu, 16 + 9 * 8, 0), 0);
+    }
+}
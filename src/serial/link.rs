@@ -0,0 +1,135 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+//NOTE: The other end of the link cable. Bytes are exchanged as a whole, the side that drives the
+//      clock calls `transfer` once all eight bits are shifted, the side that waits on the external
+//      clock keeps calling `listen` until a byte arrived.
+pub trait SerialLink: Send {
+    //NOTE: Returns what the other side shifted back, 0xFF if nothing answered
+    fn transfer(&mut self, byte: u8) -> u8;
+
+    //NOTE: `byte` is what our shift register holds while we wait, None once we stopped waiting.
+    //      Returns the byte the other side clocked in.
+    fn listen(&mut self, byte: Option<u8>) -> Option<u8>;
+}
+
+//NOTE: Nothing plugged in, the input line floats high
+pub struct Disconnected;
+
+impl SerialLink for Disconnected {
+    fn transfer(&mut self, _byte: u8) -> u8 {
+        0xFF
+    }
+
+    fn listen(&mut self, _byte: Option<u8>) -> Option<u8> {
+        None
+    }
+}
+
+//NOTE: Prints every byte the game sends, the test roms report their results this way
+pub struct StdoutLink;
+
+impl SerialLink for StdoutLink {
+    fn transfer(&mut self, byte: u8) -> u8 {
+        print!("{}", char::from(byte));
+        let _ = std::io::stdout().flush();
+
+        0xFF
+    }
+
+    fn listen(&mut self, _byte: Option<u8>) -> Option<u8> {
+        None
+    }
+}
+
+//NOTE: The output wired to the input of the same port, every byte comes straight back
+pub struct LoopbackLink;
+
+impl SerialLink for LoopbackLink {
+    fn transfer(&mut self, byte: u8) -> u8 {
+        byte
+    }
+
+    fn listen(&mut self, _byte: Option<u8>) -> Option<u8> {
+        None
+    }
+}
+
+#[derive(Default)]
+struct Port {
+    //NOTE: The shift register of a side that waits on the external clock
+    listening: Option<u8>,
+    received: Option<u8>,
+}
+
+//NOTE: One end of a cable between two emulator instances in the same process. Both ends share the
+//      ports, so the instances can run on different threads as well.
+pub struct CableEnd {
+    ports: Arc<Mutex<[Port; 2]>>,
+    side: usize,
+}
+
+impl CableEnd {
+    pub fn pair() -> (CableEnd, CableEnd) {
+        let ports = Arc::new(Mutex::new([Port::default(), Port::default()]));
+
+        (
+            CableEnd {
+                ports: Arc::clone(&ports),
+                side: 0,
+            },
+            CableEnd { ports, side: 1 },
+        )
+    }
+}
+
+impl SerialLink for CableEnd {
+    //NOTE: Only a side that waits on the external clock takes part, otherwise nothing answers
+    fn transfer(&mut self, byte: u8) -> u8 {
+        let mut ports = self.ports.lock().unwrap();
+        let other = &mut ports[1 - self.side];
+
+        match other.listening.take() {
+            Some(answer) => {
+                other.received = Some(byte);
+                answer
+            }
+            None => 0xFF,
+        }
+    }
+
+    fn listen(&mut self, byte: Option<u8>) -> Option<u8> {
+        let mut ports = self.ports.lock().unwrap();
+        let port = &mut ports[self.side];
+
+        match port.received.take() {
+            Some(received) => Some(received),
+            None => {
+                port.listening = byte;
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_cable_exchanges_bytes() {
+        let (mut first, mut second) = CableEnd::pair();
+
+        //NOTE: Nobody listens on the other side yet
+        assert_eq!(first.transfer(0x12), 0xFF);
+
+        assert_eq!(second.listen(Some(0x34)), None);
+        assert_eq!(first.transfer(0x56), 0x34);
+        assert_eq!(second.listen(Some(0x34)), Some(0x56));
+
+        //NOTE: A side that stopped listening doesn't answer anymore
+        second.listen(Some(0x78));
+        second.listen(None);
+        assert_eq!(first.transfer(0x9A), 0xFF);
+    }
+}
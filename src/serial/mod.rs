@@ -0,0 +1,215 @@
+use std::ops::RangeInclusive;
+
+use self::link::{Disconnected, SerialLink};
+use crate::memory::bus::IoDevice;
+
+pub mod link;
+
+pub const SB_REGISTER: u16 = 0xFF01;
+pub const SC_REGISTER: u16 = 0xFF02;
+
+const TRANSFER_START: u8 = 0x80;
+const FAST_CLOCK: u8 = 0x02;
+const INTERNAL_CLOCK: u8 = 0x01;
+
+//NOTE: Machine cycles for one bit, the internal clock runs at 8192 Hz. The cgb can switch to 262144
+//      Hz, both get twice as fast in double speed mode as they run off the cpu clock.
+const BIT_CYCLES: usize = 128;
+const FAST_BIT_CYCLES: usize = 4;
+
+//NOTE: The serial port shifts SB out bit by bit and the bits of the other side in at the same time.
+//      The side with the internal clock drives the transfer, the other one waits until the clock
+//      arrives. Once all eight bits went over the serial interrupt gets requested.
+pub struct Serial {
+    data: u8,
+    control: u8,
+    //NOTE: Machine cycles until the internal clock shifted the whole byte
+    remaining_cycles: usize,
+    outgoing: u8,
+    link: Box<dyn SerialLink>,
+    //NOTE: Every byte the game sent with the internal clock
+    output: String,
+    interrupt_requested: bool,
+    cgb_mode: bool,
+}
+
+impl Default for Serial {
+    fn default() -> Self {
+        Self {
+            data: 0x00,
+            control: 0x00,
+            remaining_cycles: 0,
+            outgoing: 0x00,
+            link: Box::new(Disconnected),
+            output: String::new(),
+            interrupt_requested: false,
+            cgb_mode: false,
+        }
+    }
+}
+
+impl Serial {
+    pub fn set_link(&mut self, link: Box<dyn SerialLink>) {
+        self.link = link;
+    }
+
+    //NOTE: Only the cgb has the fast clock
+    pub fn set_cgb_mode(&mut self, cgb_mode: bool) {
+        self.cgb_mode = cgb_mode;
+    }
+
+    pub fn get_output(&self) -> &str {
+        &self.output
+    }
+
+    fn write_control(&mut self, value: u8) {
+        let was_listening = self.is_listening();
+
+        let mask = if self.cgb_mode {
+            TRANSFER_START | FAST_CLOCK | INTERNAL_CLOCK
+        } else {
+            TRANSFER_START | INTERNAL_CLOCK
+        };
+        self.control = value & mask;
+
+        if was_listening && !self.is_listening() {
+            self.link.listen(None);
+        }
+
+        if self.control & (TRANSFER_START | INTERNAL_CLOCK) == TRANSFER_START | INTERNAL_CLOCK {
+            let bit_cycles = if self.control & FAST_CLOCK != 0 {
+                FAST_BIT_CYCLES
+            } else {
+                BIT_CYCLES
+            };
+
+            self.remaining_cycles = bit_cycles * 8;
+            self.outgoing = self.data;
+            self.output.push(char::from(self.data));
+        }
+    }
+
+    fn is_listening(&self) -> bool {
+        self.control & (TRANSFER_START | INTERNAL_CLOCK) == TRANSFER_START
+    }
+
+    pub fn update(&mut self, m_cycles: usize) {
+        if self.control & TRANSFER_START == 0 {
+            return;
+        }
+
+        if self.is_listening() {
+            if let Some(received) = self.link.listen(Some(self.data)) {
+                self.finish_transfer(received);
+            }
+
+            return;
+        }
+
+        self.remaining_cycles = self.remaining_cycles.saturating_sub(m_cycles);
+        if self.remaining_cycles == 0 {
+            let received = self.link.transfer(self.outgoing);
+            self.finish_transfer(received);
+        }
+    }
+
+    fn finish_transfer(&mut self, received: u8) {
+        self.data = received;
+        self.control &= !TRANSFER_START;
+        self.interrupt_requested = true;
+    }
+
+    pub fn take_interrupt(&mut self) -> bool {
+        std::mem::take(&mut self.interrupt_requested)
+    }
+}
+
+impl IoDevice for Serial {
+    fn address_ranges(&self) -> &'static [RangeInclusive<u16>] {
+        &[SB_REGISTER..=SC_REGISTER]
+    }
+
+    fn read(&self, addr: u16) -> u8 {
+        match addr {
+            SB_REGISTER => self.data,
+            SC_REGISTER if self.cgb_mode => 0x7C | self.control,
+            SC_REGISTER => 0x7E | self.control,
+            _ => panic!("Invalid address for the serial port"),
+        }
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        match addr {
+            SB_REGISTER => self.data = value,
+            SC_REGISTER => self.write_control(value),
+            _ => panic!("Invalid address for the serial port"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::link::{CableEnd, LoopbackLink};
+    use super::*;
+
+    #[test]
+    fn test_internal_clock_transfer() {
+        let mut serial = Serial::default();
+        serial.set_link(Box::new(LoopbackLink));
+        serial.write(SB_REGISTER, 0x42);
+        serial.write(SC_REGISTER, 0x81);
+        assert_eq!(serial.read(SC_REGISTER), 0xFF);
+
+        serial.update(BIT_CYCLES * 8 - 1);
+        assert!(!serial.take_interrupt());
+        serial.write(SB_REGISTER, 0x00);
+
+        //NOTE: The byte that goes out is the one SB held when the transfer started
+        serial.update(1);
+        assert!(serial.take_interrupt());
+        assert_eq!(serial.read(SB_REGISTER), 0x42);
+        assert_eq!(serial.read(SC_REGISTER), 0x7F);
+        assert_eq!(serial.get_output(), "B");
+    }
+
+    #[test]
+    fn test_fast_clock_only_on_the_cgb() {
+        let mut serial = Serial::default();
+        serial.write(SC_REGISTER, 0x83);
+        assert_eq!(serial.read(SC_REGISTER), 0xFF);
+        assert_eq!(serial.remaining_cycles, BIT_CYCLES * 8);
+
+        serial.set_cgb_mode(true);
+        serial.write(SC_REGISTER, 0x83);
+        assert_eq!(serial.read(SC_REGISTER), 0xFF);
+        assert_eq!(serial.remaining_cycles, FAST_BIT_CYCLES * 8);
+    }
+
+    #[test]
+    fn test_two_instances_over_a_cable() {
+        let (first, second) = CableEnd::pair();
+        let mut master = Serial::default();
+        let mut slave = Serial::default();
+        master.set_link(Box::new(first));
+        slave.set_link(Box::new(second));
+
+        slave.write(SB_REGISTER, 0x55);
+        slave.write(SC_REGISTER, 0x80);
+        master.write(SB_REGISTER, 0xAA);
+        master.write(SC_REGISTER, 0x81);
+
+        //NOTE: The waiting side doesn't see a clock until the other side is done
+        for _ in 0..8 {
+            slave.update(BIT_CYCLES);
+            master.update(BIT_CYCLES);
+        }
+        assert!(master.take_interrupt());
+        assert!(!slave.take_interrupt());
+
+        slave.update(1);
+        assert!(slave.take_interrupt());
+        assert_eq!(master.read(SB_REGISTER), 0x55);
+        assert_eq!(slave.read(SB_REGISTER), 0xAA);
+        assert_eq!(slave.read(SC_REGISTER), 0x7E);
+    }
+}
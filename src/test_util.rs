@@ -0,0 +1,13 @@
+use std::path::{Path, PathBuf};
+
+//NOTE: The tests run in parallel and so can several test runs, the process id keeps their files apart
+pub fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("gboyrust_{}_{}", std::process::id(), name))
+}
+
+//NOTE: A 32 KiB rom without a mapper, `program` starts at the entry point
+pub fn write_rom(path: &Path, program: &[u8]) {
+    let mut rom = vec![0x00; 0x8000];
+    rom[0x0100..0x0100 + program.len()].copy_from_slice(program);
+    std::fs::write(path, rom).unwrap();
+}